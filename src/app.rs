@@ -1,3 +1,5 @@
+use std::collections::{BTreeMap, BTreeSet};
+
 use egui::{
     Color32, CornerRadius, CursorIcon, FontId, Key, PointerButton, Pos2, Rect, Sense, Stroke,
     StrokeKind, Vec2, epaint::CubicBezierShape,
@@ -14,33 +16,89 @@ use egui::{
 //
 // 代码阅读建议（初学者友好顺序）：
 // 常量 -> 数据结构 -> 几何辅助函数 -> 绘制函数 -> 输入处理 -> update 主循环
+//
+// 较独立的交互功能拆到 `app/` 子模块中，它们通过 `impl NodeGraphApp` 扩展本结构体。
 // ============================================================
 
+mod context_menu;
+mod dependency_highlight;
+mod documents;
+mod graph_analysis;
+mod graph_model;
+mod group_frame;
+mod inspector;
+mod link_cut;
+mod link_flow;
+mod link_routing;
+mod link_style;
+mod node_align;
+mod node_palette;
+mod node_resize;
+mod node_search;
+mod node_snap;
+mod node_tags;
+mod path_query;
+mod recovery;
+mod subgraph;
+mod svg_export;
+mod template_library;
+mod theme;
+mod undo;
+mod validation;
+
+use context_menu::{CanvasMenuTarget, ContextAction};
+use dependency_highlight::Dependencies;
+use documents::Document;
+use graph_analysis::AnalysisCache;
+use group_frame::{FrameAction, FrameDrag, GroupFrame};
+use link_flow::FlowAnimation;
+use link_routing::{LinkRouting, RouteCache};
+use link_style::LinkStyle;
+use node_palette::NodePalette;
+use node_search::NodeSearch;
+use node_snap::NodeDrag;
+use path_query::PathQuery;
+pub use path_query::{GraphPath, PathWeight};
+use recovery::Recovery;
+use subgraph::{ExposedPortCache, SubgraphAction};
+use template_library::TemplateLibrary;
+use theme::{Theme, ThemeMode};
+use undo::UndoHistory;
+use validation::Validation;
+pub use validation::{Issue, QuickFix, Severity, ValidationRule};
+
+/// 应用名：窗口标题，也决定 eframe 保存应用状态的目录（恢复目录默认放在那里）。
+pub const APP_NAME: &str = "eframe template";
+
 // 统一的节点尺寸，方便全局样式保持一致。
 const NODE_SIZE: Vec2 = Vec2::new(180.0, 130.0);
+// 转接点（Reroute）尺寸：只是一个可拖动的小圆点。
+const REROUTE_SIZE: Vec2 = Vec2::new(22.0, 22.0);
+// 转接点两侧端口的命中半径：比普通端口小，给中间的圆点留出拖动区域。
+const REROUTE_PORT_HIT_RADIUS: f32 = 6.0;
+const REROUTE_PORT_RADIUS: f32 = 3.5;
 // 标题栏高度。
 const HEADER_HEIGHT: f32 = 28.0;
+// 背景小网格间距（世界坐标），大网格为它的 4 倍；节点吸附到小网格。
+const GRID_SPACING: f32 = 24.0;
 // 端口视觉半径（你看到的小圆点大小）。
 // 端口命中半径（用于鼠标交互，通常比视觉半径大，便于点击/拖拽）。
 const PORT_HIT_RADIUS: f32 = 10.0;
 const NODE_INNER_PADDING_X: f32 = 10.0;
 const NODE_INNER_PADDING_Y: f32 = 8.0;
-const NODE_BG_COLOR: Color32 = Color32::from_rgb(30, 30, 35);
-const NODE_BORDER_IDLE_COLOR: Color32 = Color32::from_rgb(82, 82, 91);
-const NODE_BORDER_HOVER_COLOR: Color32 = Color32::from_rgb(148, 163, 184);
-const NODE_HEADER_COLOR: Color32 = Color32::from_rgb(57, 116, 245);
-const CANVAS_BG_COLOR: Color32 = Color32::from_rgb(20, 23, 29);
-const SIDE_PANEL_BG: Color32 = Color32::from_rgb(25, 28, 34);
-const LINK_COLOR: Color32 = Color32::from_rgb(122, 134, 156);
-const DRAG_LINK_COLOR: Color32 = Color32::from_rgb(100, 180, 255);
-const PORT_INPUT_COLOR: Color32 = Color32::from_rgb(255, 95, 87); // mac red
-const PORT_OUTPUT_COLOR: Color32 = Color32::from_rgb(254, 188, 46); // mac yellow
+// 颜色来自当前主题，见 `theme.rs`。
 const PORT_RADIUS: f32 = 6.5;
 const PORT_RING_STROKE: f32 = 2.0;
 const PORT_OUTSET: f32 = 8.0;
 const ZOOM_STEP: f32 = 1.10;
 const MIN_ZOOM_FACTOR: f32 = 0.60;
 const MAX_ZOOM_FACTOR: f32 = 2.50;
+// 连线命中阈值（屏幕像素）。
+const LINK_HIT_THRESHOLD: f32 = 10.0;
+// 在画布上拖动连线时，只有按在曲线参数 t >= 该值的一段（靠近输入端）才会拎起连线。
+const LINK_GRAB_T_MIN: f32 = 0.65;
+// 把连线曲线采样成折线时使用的段数（命中测试、切线都会用到）。
+const BEZIER_SAMPLES: usize = 24;
 
 /// 端口类型：输入端口 / 输出端口。
 ///
@@ -53,11 +111,77 @@ enum PortKind {
     Output,
 }
 
+impl PortKind {
+    /// 能与本端口相连的另一种端口。
+    fn opposite(self) -> Self {
+        match self {
+            Self::Input => Self::Output,
+            Self::Output => Self::Input,
+        }
+    }
+}
+
+/// 节点类型：决定节点拥有哪些端口，以及默认标题和尺寸。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+enum NodeKind {
+    /// 数据源：只有输出端口。
+    Source,
+    /// 处理节点：输入、输出端口都有。
+    Process,
+    /// 终点：只有输入端口。
+    Sink,
+    /// 转接点：不带正文的小圆点，用来整理连线走向。
+    Reroute,
+    /// 子图：代表一组收起来的节点，端口由跨越子图边界的连线决定（只能通过“收进子图”创建）。
+    Subgraph,
+}
+
+impl NodeKind {
+    /// 所有可创建的节点类型（菜单里按此顺序列出）。
+    const ALL: [Self; 4] = [Self::Source, Self::Process, Self::Sink, Self::Reroute];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Source => "Source",
+            Self::Process => "Process",
+            Self::Sink => "Sink",
+            Self::Reroute => "Reroute",
+            Self::Subgraph => "Subgraph",
+        }
+    }
+
+    fn has_port(self, port: PortKind) -> bool {
+        match self {
+            Self::Source => port == PortKind::Output,
+            Self::Sink => port == PortKind::Input,
+            Self::Process | Self::Reroute => true,
+            // 子图节点只有外露端口（见 `subgraph` 模块）。
+            Self::Subgraph => false,
+        }
+    }
+
+    fn port_hit_radius(self) -> f32 {
+        match self {
+            Self::Reroute => REROUTE_PORT_HIT_RADIUS,
+            Self::Source | Self::Process | Self::Sink | Self::Subgraph => PORT_HIT_RADIUS,
+        }
+    }
+
+    fn default_size(self) -> Vec2 {
+        match self {
+            Self::Reroute => REROUTE_SIZE,
+            Self::Source | Self::Process | Self::Sink | Self::Subgraph => NODE_SIZE,
+        }
+    }
+}
+
 /// 图中的一个节点。
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 struct Node {
     /// 节点唯一 ID（逻辑标识，不是数组下标）。
     id: usize,
+    /// 节点类型。
+    kind: NodeKind,
     /// 显示在标题栏的名称。
     title: String,
     /// 节点左上角在“世界坐标”中的位置。
@@ -69,6 +193,48 @@ struct Node {
     position: Pos2,
     /// 节点尺寸。
     size: Vec2,
+    /// 自定义标题栏颜色；`None` 表示使用主题的默认标题栏颜色。
+    header_color: Option<Color32>,
+    /// 自动高度：节点高度随正文内容增长。
+    #[serde(default)]
+    auto_height: bool,
+    /// 包含该节点的子图节点；`None` 表示在顶层。
+    #[serde(default)]
+    parent: Option<usize>,
+    /// 标题前显示的图标字符（一个符号或 emoji），`None` 表示不显示。
+    #[serde(default)]
+    icon: Option<String>,
+    /// 自由填写的标签，显示在正文下方，可用于筛选和搜索。
+    #[serde(default)]
+    tags: Vec<String>,
+    /// 耗时，供关键路径分析使用；`None` 表示未设置。
+    #[serde(default)]
+    duration: Option<f32>,
+}
+
+impl Node {
+    /// 按类型创建一个带默认标题、正文和尺寸的节点。
+    fn new(id: usize, kind: NodeKind, position: Pos2) -> Self {
+        Self {
+            id,
+            kind,
+            title: format!("{} {id}", kind.label()),
+            content: ".....".to_owned(),
+            position,
+            size: kind.default_size(),
+            header_color: None,
+            auto_height: false,
+            parent: None,
+            icon: None,
+            tags: Vec::new(),
+            duration: None,
+        }
+    }
+
+    /// 节点在世界坐标中的矩形。
+    fn world_rect(&self) -> Rect {
+        Rect::from_min_size(self.position, self.size)
+    }
 }
 
 /// 正在拖拽“临时连线”时的状态。
@@ -76,22 +242,98 @@ struct Node {
 struct DragLinkState {
     /// 起始节点 ID。
     from_node: usize,
-    /// 起始端口类型（本例中固定为 Output，但保留字段更易扩展）。
+    /// 起始端口类型：从输出端口拖出为 Output；从输入端口反向拖出（或复制连线）时为 Input。
     from_port: PortKind,
     /// 鼠标当前屏幕坐标，用于实时绘制“跟手”的临时曲线。
     current_pos: Pos2,
 }
 
+/// 连线的标识：`(起点节点 ID, 终点节点 ID)`。两个节点之间最多只有一条连线，所以它是唯一的。
+type LinkKey = (usize, usize);
+
 /// 一条正式连线（保存到状态里）。
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
 struct Connection {
     /// 起点节点 ID（默认取该节点 Output 端口位置）。
     from_node_id: usize,
     /// 终点节点 ID（默认取该节点 Input 端口位置）。
     to_node_id: usize,
+    /// 这条连线单独指定的走线方式；`None` 表示跟随文档默认值。
+    #[serde(default)]
+    routing: Option<LinkRouting>,
+    /// 显示在连线中点的文字标签，空字符串表示不显示。
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    style: LinkStyle,
+}
+
+impl Connection {
+    fn new(from_node_id: usize, to_node_id: usize) -> Self {
+        Self {
+            from_node_id,
+            to_node_id,
+            routing: None,
+            label: String::new(),
+            style: LinkStyle::default(),
+        }
+    }
+
+    fn key(&self) -> LinkKey {
+        (self.from_node_id, self.to_node_id)
+    }
+
+    /// 连线是否与指定节点相连（任一端）。
+    fn touches(&self, node_id: usize) -> bool {
+        self.from_node_id == node_id || self.to_node_id == node_id
+    }
+}
+
+/// 侧边栏的页。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+enum SidePanelTab {
+    #[default]
+    Controls,
+    /// 选中节点 / 连线的属性检查器。
+    Inspector,
+    /// 模板库。
+    Library,
+    /// 主题编辑器。
+    Theme,
+    /// 图分析（度数、连通分量、关键路径等）。
+    Analysis,
+    /// 校验发现的问题。
+    Issues,
+}
+
+impl SidePanelTab {
+    const ALL: [Self; 6] = [
+        Self::Controls,
+        Self::Inspector,
+        Self::Library,
+        Self::Theme,
+        Self::Analysis,
+        Self::Issues,
+    ];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Controls => "Controls",
+            Self::Inspector => "Inspector",
+            Self::Library => "Library",
+            Self::Theme => "Theme",
+            Self::Analysis => "Analysis",
+            Self::Issues => "Issues",
+        }
+    }
 }
 
 /// 整个节点编辑器 App 的运行时状态。
+///
+/// 图数据（节点、连线）和视图位置会随 eframe 的持久化一起保存；
+/// 标记为 `serde(skip)` 的字段只是交互过程中的临时状态。
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
 pub struct NodeGraphApp {
     /// 所有节点。
     nodes: Vec<Node>,
@@ -100,11 +342,148 @@ pub struct NodeGraphApp {
     /// 画布平移偏移量（世界坐标 -> 屏幕坐标）。
     pan_offset: Vec2,
     /// 当前是否处于“拖拽画布”模式。
+    #[serde(skip)]
     dragging_canvas: bool,
     /// 当前是否处于“拖拽连线”模式。
+    #[serde(skip)]
     dragging_link: Option<DragLinkState>,
     /// 下一次添加节点时使用的 ID（自增）。
     next_node_id: usize,
+    /// 通过右键菜单复制的节点，用于“粘贴”。
+    #[serde(skip)]
+    clipboard: Option<Node>,
+    /// 需要在下一帧让标题输入框获得焦点的节点（右键“重命名”）。
+    #[serde(skip)]
+    renaming_node: Option<usize>,
+    /// 正在编辑标签的连线。
+    #[serde(skip)]
+    editing_link_label: Option<LinkKey>,
+    /// 画布右键菜单的目标（空白处或某条连线），在右键点击时确定。
+    #[serde(skip)]
+    canvas_menu: Option<CanvasMenuTarget>,
+    /// 本帧从菜单中选出的操作，等绘制结束后统一执行。
+    #[serde(skip)]
+    pending_action: Option<ContextAction>,
+    /// 上一帧中央画布的屏幕矩形（快捷键需要在绘制画布之前判断指针是否在画布内）。
+    #[serde(skip)]
+    canvas_rect: Rect,
+    /// “添加节点”命令面板（打开时才有值）。
+    #[serde(skip)]
+    node_palette: Option<NodePalette>,
+    /// 最近通过命令面板添加的节点类型，最新的在前。
+    recent_node_kinds: Vec<NodeKind>,
+    /// 正在进行的“切线”手势轨迹（屏幕坐标，Ctrl + 右键拖动）。
+    #[serde(skip)]
+    cut_stroke: Option<Vec<Pos2>>,
+    /// 文档默认的连线走线方式（单条连线可以覆盖）。
+    link_routing: LinkRouting,
+    /// 直角走线的增量缓存。
+    #[serde(skip)]
+    route_cache: RouteCache,
+    /// 当前层子图节点的外露端口缓存，每帧重建。
+    #[serde(skip)]
+    exposed_port_cache: ExposedPortCache,
+    /// 活跃连线上的数据流动画样式。
+    flow_animation: FlowAnimation,
+    /// 每条连线的活跃度（0..=1），由求值引擎或宿主程序设置，不随文档保存。
+    #[serde(skip)]
+    link_activity: BTreeMap<LinkKey, f32>,
+    /// 拖动节点时吸附到网格。
+    snap_to_grid: bool,
+    /// 拖动节点时显示对齐参考线并吸附。
+    alignment_guides: bool,
+    /// 悬停 / 选中节点时高亮它的全部上游和下游。
+    dependency_highlight: bool,
+    /// 关键路径按节点耗时之和计算，而不是按节点数。
+    critical_path_durations: bool,
+    /// 在 `Issues` 页关闭的校验规则。
+    disabled_rules: BTreeSet<String>,
+    /// 参与校验的规则：内置规则加上宿主程序注册的规则。
+    #[serde(skip)]
+    validation_rules: Vec<Box<dyn ValidationRule>>,
+    /// 最近一次校验的结果。
+    #[serde(skip)]
+    validation: Validation,
+    /// 最近一次图分析的结果。
+    #[serde(skip)]
+    analysis_cache: AnalysisCache,
+    /// 本帧高亮的上下游集合。
+    #[serde(skip)]
+    dependencies: Dependencies,
+    /// 侧边栏 “Paths” 的起点 / 终点与查询结果。
+    #[serde(skip)]
+    path_query: PathQuery,
+    /// 正在拖动 / 缩放的节点。
+    #[serde(skip)]
+    node_drag: Option<NodeDrag>,
+    /// 选中的节点 ID（点击标题栏选中，Shift + 点击增减）。
+    #[serde(skip)]
+    selected_nodes: BTreeSet<usize>,
+    /// 选中的连线（点击连线选中，在检查器里编辑）。
+    #[serde(skip)]
+    selected_link: Option<LinkKey>,
+    /// 检查器里正在连续编辑的控件，同一控件的连续修改只记录一步撤销。
+    #[serde(skip)]
+    inspector_edit: Option<egui::Id>,
+    /// 检查器里最近一次被拒绝的修改的原因。
+    #[serde(skip)]
+    inspector_error: Option<String>,
+    /// Ctrl + F 搜索栏（打开时才有值）。
+    #[serde(skip)]
+    node_search: Option<NodeSearch>,
+    /// 右键菜单 / 检查器里正在输入的新标签。
+    #[serde(skip)]
+    tag_input: String,
+    /// 撤销 / 重做历史。
+    #[serde(skip)]
+    undo_history: UndoHistory,
+    /// 画在节点下面的分组框。
+    frames: Vec<GroupFrame>,
+    /// 下一次创建分组框时使用的 ID（自增）。
+    next_frame_id: usize,
+    /// 正在重命名的分组框。
+    #[serde(skip)]
+    renaming_frame: Option<usize>,
+    /// 正在拖动标题栏的分组框。
+    #[serde(skip)]
+    frame_drag: Option<FrameDrag>,
+    /// 画布当前显示的子图层级；`None` 表示顶层。
+    current_subgraph: Option<usize>,
+    /// 侧边栏当前显示的页。
+    side_panel_tab: SidePanelTab,
+    /// 模板库目录：每个模板是其中的一个 `.ron` 文件。
+    library_dir: String,
+    /// 模板库面板的状态。
+    #[serde(skip)]
+    template_library: TemplateLibrary,
+    /// 使用哪套主题（跟随系统 / 深色 / 浅色 / 自定义）。
+    theme_mode: ThemeMode,
+    /// 自定义主题：在主题页编辑过或从文件导入的配色。
+    custom_theme: Theme,
+    /// 本帧实际使用的主题，每帧开始时由 `theme_mode` 决定。
+    #[serde(skip)]
+    theme: Theme,
+    /// 导入 / 导出主题时使用的文件路径。
+    theme_path: String,
+    /// 导出 SVG 时写入的文件路径。
+    export_path: String,
+    /// 打开的全部文档（标签页）；前台文档的图数据在上面的字段里。
+    documents: Vec<Document>,
+    /// 前台文档在 `documents` 里的下标。
+    active_document: usize,
+    /// “Open in New Tab” 读取的文件路径。
+    open_path: String,
+    /// 正在询问是否保存后关闭的文档。
+    #[serde(skip)]
+    closing_document: Option<usize>,
+    /// 自动保存快照和快照日志所在的目录。
+    recovery_dir: String,
+    /// 自动保存的计时和启动时发现的可恢复文档。
+    #[serde(skip)]
+    recovery: Recovery,
+    /// 侧边栏底部显示的最近一次操作结果（例如导出成功 / 失败）。
+    #[serde(skip)]
+    status_message: Option<String>,
 }
 
 impl Default for NodeGraphApp {
@@ -112,50 +491,98 @@ impl Default for NodeGraphApp {
         // 初始化 3 个演示节点。
         let nodes = vec![
             Node {
-                id: 0,
                 title: "Input".to_owned(),
                 content: "这里是节点说明".to_owned(),
-                position: Pos2::new(100.0, 100.0),
-                size: NODE_SIZE,
+                ..Node::new(0, NodeKind::Process, Pos2::new(100.0, 100.0))
             },
             Node {
-                id: 1,
                 title: "Deal".to_owned(),
                 content: "这里是节点说明".to_owned(),
-                position: Pos2::new(340.0, 140.0),
-                size: NODE_SIZE,
+                ..Node::new(1, NodeKind::Process, Pos2::new(340.0, 140.0))
             },
             Node {
-                id: 2,
                 title: "Output".to_owned(),
                 content: "这里是节点说明".to_owned(),
-                position: Pos2::new(580.0, 100.0),
-                size: NODE_SIZE,
+                ..Node::new(2, NodeKind::Process, Pos2::new(580.0, 100.0))
             },
         ];
 
         Self {
             nodes,
             // 初始化两条演示连线：0 -> 1 -> 2
-            connections: vec![
-                Connection {
-                    from_node_id: 0,
-                    to_node_id: 1,
-                },
-                Connection {
-                    from_node_id: 1,
-                    to_node_id: 2,
-                },
-            ],
+            connections: vec![Connection::new(0, 1), Connection::new(1, 2)],
             pan_offset: Vec2::ZERO,
             dragging_canvas: false,
             dragging_link: None,
             next_node_id: 3,
+            clipboard: None,
+            renaming_node: None,
+            editing_link_label: None,
+            canvas_menu: None,
+            pending_action: None,
+            canvas_rect: Rect::NOTHING,
+            node_palette: None,
+            recent_node_kinds: Vec::new(),
+            cut_stroke: None,
+            link_routing: LinkRouting::default(),
+            route_cache: RouteCache::default(),
+            exposed_port_cache: ExposedPortCache::default(),
+            flow_animation: FlowAnimation::default(),
+            link_activity: BTreeMap::new(),
+            snap_to_grid: false,
+            alignment_guides: true,
+            dependency_highlight: false,
+            critical_path_durations: false,
+            disabled_rules: BTreeSet::new(),
+            validation_rules: validation::built_in_rules(),
+            validation: Validation::default(),
+            analysis_cache: AnalysisCache::default(),
+            dependencies: Dependencies::default(),
+            path_query: PathQuery::default(),
+            node_drag: None,
+            selected_nodes: BTreeSet::new(),
+            selected_link: None,
+            inspector_edit: None,
+            inspector_error: None,
+            node_search: None,
+            tag_input: String::new(),
+            undo_history: UndoHistory::default(),
+            frames: Vec::new(),
+            next_frame_id: 0,
+            renaming_frame: None,
+            frame_drag: None,
+            current_subgraph: None,
+            side_panel_tab: SidePanelTab::default(),
+            library_dir: "renode-library".to_owned(),
+            template_library: TemplateLibrary::default(),
+            theme_mode: ThemeMode::default(),
+            custom_theme: Theme::default(),
+            theme: Theme::default(),
+            theme_path: "renode-theme.ron".to_owned(),
+            export_path: "renode-export.svg".to_owned(),
+            documents: vec![Document::untitled(1)],
+            active_document: 0,
+            open_path: "untitled-1.ron".to_owned(),
+            closing_document: None,
+            recovery_dir: recovery::default_recovery_dir(),
+            recovery: Recovery::default(),
+            status_message: None,
         }
     }
 }
 
 impl NodeGraphApp {
+    /// 创建 App：如果有上次保存的状态就恢复，否则使用演示数据；
+    /// 恢复目录里有上次没正常退出留下的内容时，准备弹出恢复窗口。
+    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let mut app: Self = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        app.scan_recovery();
+        app
+    }
+
     // ========================
     // 状态管理 / 数据查询
     // ========================
@@ -166,15 +593,97 @@ impl NodeGraphApp {
         self.next_node_id += 1;
 
         self.nodes.push(Node {
-            id,
             title: format!("Node {id}"),
+            parent: self.current_subgraph,
             // 简单错开位置，避免新节点完全重叠。
-            position: Pos2::new(220.0 + (id as f32 * 24.0), 220.0),
-            content: ".....".to_owned(),
-            size: NODE_SIZE,
+            ..Node::new(
+                id,
+                NodeKind::Process,
+                Pos2::new(220.0 + (id as f32 * 24.0), 220.0),
+            )
         });
     }
 
+    /// 在指定世界坐标处（作为节点中心）添加一个指定类型的节点，返回新节点 ID。
+    fn add_node_at(&mut self, kind: NodeKind, world_center: Pos2) -> usize {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+
+        let position = world_center - kind.default_size() * 0.5;
+        self.nodes.push(Node {
+            parent: self.current_subgraph,
+            ..Node::new(id, kind, position)
+        });
+        id
+    }
+
+    /// 尝试创建一条 `from_node_id`（输出）→ `to_node_id`（输入）的连线。
+    ///
+    /// 规则：
+    /// 1) 两端节点都存在，且分别带有输出 / 输入端口
+    /// 2) 不允许自己连自己
+    /// 3) 不允许重复连线
+    ///
+    /// 成功创建返回 `true`。
+    fn try_connect(&mut self, from_node_id: usize, to_node_id: usize) -> bool {
+        let ports_exist = self
+            .node_by_id(from_node_id)
+            .is_some_and(|node| node.kind.has_port(PortKind::Output))
+            && self
+                .node_by_id(to_node_id)
+                .is_some_and(|node| node.kind.has_port(PortKind::Input));
+        let duplicate_exists = self
+            .connections
+            .iter()
+            .any(|connection| connection.key() == (from_node_id, to_node_id));
+
+        if !ports_exist || from_node_id == to_node_id || duplicate_exists {
+            return false;
+        }
+        self.connections
+            .push(Connection::new(from_node_id, to_node_id));
+        true
+    }
+
+    /// 把 `node_id` 的 `port` 端口与 `other_node_id` 上对应的另一种端口相连。
+    fn connect_ports(&mut self, node_id: usize, port: PortKind, other_node_id: usize) -> bool {
+        match port {
+            PortKind::Output => self.try_connect(node_id, other_node_id),
+            PortKind::Input => self.try_connect(other_node_id, node_id),
+        }
+    }
+
+    /// 删除节点以及与它相关的所有连线；删除子图节点时连同子图里的节点和分组框一起删除。
+    fn remove_node(&mut self, node_id: usize) {
+        let children: Vec<usize> = self
+            .nodes
+            .iter()
+            .filter(|node| node.parent == Some(node_id))
+            .map(|node| node.id)
+            .collect();
+        for child in children {
+            self.remove_node(child);
+        }
+        self.frames.retain(|frame| frame.parent != Some(node_id));
+        for frame in &mut self.frames {
+            frame.remove_member(node_id);
+        }
+
+        self.nodes.retain(|node| node.id != node_id);
+        self.connections
+            .retain(|connection| !connection.touches(node_id));
+        if self
+            .dragging_link
+            .is_some_and(|link| link.from_node == node_id)
+        {
+            self.cancel_link_edit();
+        }
+        if self.renaming_node == Some(node_id) {
+            self.renaming_node = None;
+        }
+        self.selected_nodes.remove(&node_id);
+    }
+
     /// 按节点 ID 查询节点引用。
     ///
     /// 注意：因为节点是 Vec 存储，ID 不一定等于下标，所以不要直接 `nodes[id]`。
@@ -182,6 +691,37 @@ impl NodeGraphApp {
         self.nodes.iter().find(|node| node.id == id)
     }
 
+    /// 列表里显示的节点名：节点的标题，找不到节点时为 `#ID`。
+    fn node_name(&self, id: usize) -> String {
+        self.node_by_id(id)
+            .map_or_else(|| format!("#{id}"), |node| node.title.clone())
+    }
+
+    /// 画布上显示的节点：属于当前子图层级，且没有被折叠的分组框隐藏。
+    fn visible_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|node| !self.is_node_hidden(node))
+    }
+
+    fn is_node_hidden(&self, node: &Node) -> bool {
+        node.parent != self.current_subgraph || self.collapsed_frame_of(node.id).is_some()
+    }
+
+    fn connection_by_key(&self, link: LinkKey) -> Option<&Connection> {
+        self.connections
+            .iter()
+            .find(|connection| connection.key() == link)
+    }
+
+    fn connection_by_key_mut(&mut self, link: LinkKey) -> Option<&mut Connection> {
+        self.connections
+            .iter_mut()
+            .find(|connection| connection.key() == link)
+    }
+
+    fn node_by_id_mut(&mut self, id: usize) -> Option<&mut Node> {
+        self.nodes.iter_mut().find(|node| node.id == id)
+    }
+
     // ========================
     // 坐标与几何辅助
     // ========================
@@ -197,10 +737,50 @@ impl NodeGraphApp {
     /// - Input 在左边中点
     /// - Output 在右边中点
     fn port_pos_screen(&self, node: &Node, port: PortKind) -> Pos2 {
-        let rect = self.node_rect_screen(node);
+        Self::port_pos_in_rect(self.node_rect_screen(node), node.kind, port)
+    }
+
+    /// 端口在世界坐标中的位置。
+    fn port_pos_world(node: &Node, port: PortKind) -> Pos2 {
+        Self::port_pos_in_rect(node.world_rect(), node.kind, port)
+    }
+
+    /// 连线在节点端口处的锚点（世界坐标）：
+    /// - 节点在更深的子图里：接到当前层子图节点的外露端口
+    /// - 节点（或代表它的子图节点）被折叠的分组框隐藏：接到分组框标题栏的左右两侧
+    fn port_anchor_world(&self, node: &Node, port: PortKind) -> Pos2 {
+        let Some(representative) = self
+            .scope_representative(node.id)
+            .and_then(|id| self.node_by_id(id))
+        else {
+            return Self::port_pos_world(node, port);
+        };
+
+        if let Some(frame) = self.collapsed_frame_of(representative.id) {
+            let header = frame.header_rect();
+            return match port {
+                PortKind::Input => header.left_center(),
+                PortKind::Output => header.right_center(),
+            };
+        }
+        if representative.id != node.id
+            && let Some(pos) = self.exposed_port_world(representative, node.id, port)
+        {
+            return pos;
+        }
+        Self::port_pos_world(node, port)
+    }
+
+    /// 根据节点矩形计算端口位置（转接点的端口贴在圆点边缘，不向外偏移）。
+    fn port_pos_in_rect(rect: Rect, kind: NodeKind, port: PortKind) -> Pos2 {
+        let outset = if kind == NodeKind::Reroute {
+            0.0
+        } else {
+            PORT_OUTSET
+        };
         match port {
-            PortKind::Input => Pos2::new(rect.left() - PORT_OUTSET, rect.center().y),
-            PortKind::Output => Pos2::new(rect.right() + PORT_OUTSET, rect.center().y),
+            PortKind::Input => Pos2::new(rect.left() - outset, rect.center().y),
+            PortKind::Output => Pos2::new(rect.right() + outset, rect.center().y),
         }
     }
 
@@ -208,25 +788,23 @@ impl NodeGraphApp {
     ///
     /// 返回 `(node_id, port_kind)`，找不到则返回 `None`。
     fn port_at(&self, pointer_pos: Pos2) -> Option<(usize, PortKind)> {
-        self.nodes.iter().find_map(|node| {
-            let input = self.port_pos_screen(node, PortKind::Input);
-            if input.distance(pointer_pos) <= PORT_HIT_RADIUS {
-                return Some((node.id, PortKind::Input));
-            }
-
-            let output = self.port_pos_screen(node, PortKind::Output);
-            if output.distance(pointer_pos) <= PORT_HIT_RADIUS {
-                return Some((node.id, PortKind::Output));
-            }
-
-            None
-        })
+        self.visible_nodes()
+            .find_map(|node| {
+                [PortKind::Input, PortKind::Output]
+                    .into_iter()
+                    .filter(|&port| node.kind.has_port(port))
+                    .find(|&port| {
+                        self.port_pos_screen(node, port).distance(pointer_pos)
+                            <= node.kind.port_hit_radius()
+                    })
+                    .map(|port| (node.id, port))
+            })
+            .or_else(|| self.exposed_port_at(pointer_pos))
     }
 
     /// 判断鼠标是否在任意节点本体上（用于区分是拖节点还是拖画布）。
     fn is_pointer_over_node(&self, pointer_pos: Pos2) -> bool {
-        self.nodes
-            .iter()
+        self.visible_nodes()
             .any(|node| self.node_rect_screen(node).contains(pointer_pos))
     }
 
@@ -254,33 +832,106 @@ impl NodeGraphApp {
         proj.distance(p)
     }
 
+    /// 连线贝塞尔曲线的四个控制点：两个控制点在水平方向展开，形成“流程图常见弯曲”。
+    fn bezier_control_points(from: Pos2, to: Pos2) -> [Pos2; 4] {
+        let horizontal = (to.x - from.x).abs();
+        let curvature = horizontal.max(60.0) * 0.45;
+
+        [
+            from,
+            from + Vec2::new(curvature, 0.0),
+            to - Vec2::new(curvature, 0.0),
+            to,
+        ]
+    }
+
+    /// 连线两端（输出端口、输入端口）的世界坐标；任一端节点不存在时返回 `None`。
+    ///
+    /// 端点节点在子图里时接到子图节点的外露端口，被折叠的分组框隐藏时接到分组框标题栏；
+    /// 两端落在同一处的连线不显示。
+    fn connection_endpoints_world(&self, connection: &Connection) -> Option<(Pos2, Pos2)> {
+        let from_node = self.node_by_id(connection.from_node_id)?;
+        let to_node = self.node_by_id(connection.to_node_id)?;
+        // 两端都要在当前层之下；都在同一个子图里的连线属于子图内部，不在本层显示。
+        let from_rep = self.scope_representative(from_node.id)?;
+        let to_rep = self.scope_representative(to_node.id)?;
+        let from_frame = self.collapsed_frame_of(from_rep).map(|frame| frame.id);
+        if from_rep == to_rep
+            || from_frame.is_some()
+                && from_frame == self.collapsed_frame_of(to_rep).map(|frame| frame.id)
+        {
+            return None;
+        }
+
+        Some((
+            self.port_anchor_world(from_node, PortKind::Output),
+            self.port_anchor_world(to_node, PortKind::Input),
+        ))
+    }
+
+    /// 把贝塞尔曲线参数 `t_min..=1` 这一段采样成折线（`samples` 段，含两端点）。
+    fn bezier_polyline(points: [Pos2; 4], t_min: f32, samples: usize) -> Vec<Pos2> {
+        let [p0, p1, p2, p3] = points;
+        (0..=samples)
+            .map(|i| {
+                let t = t_min + (1.0 - t_min) * (i as f32 / samples as f32);
+                Self::cubic_bezier_point(p0, p1, p2, p3, t)
+            })
+            .collect()
+    }
+
+    /// 两条线段 `a1-a2`、`b1-b2` 是否相交（含端点接触）。
+    fn segments_intersect(a1: Pos2, a2: Pos2, b1: Pos2, b2: Pos2) -> bool {
+        let cross = |o: Pos2, p: Pos2, q: Pos2| (p - o).x * (q - o).y - (p - o).y * (q - o).x;
+        let d1 = cross(b1, b2, a1);
+        let d2 = cross(b1, b2, a2);
+        let d3 = cross(a1, a2, b1);
+        let d4 = cross(a1, a2, b2);
+
+        if ((d1 > 0.0 && d2 < 0.0) || (d1 < 0.0 && d2 > 0.0))
+            && ((d3 > 0.0 && d4 < 0.0) || (d3 < 0.0 && d4 > 0.0))
+        {
+            return true;
+        }
+
+        // 共线或端点恰好落在另一条线段上的情况。
+        let on_segment =
+            |p: Pos2, a: Pos2, b: Pos2| Self::point_to_segment_distance(p, a, b) <= 0.01;
+        (d1 == 0.0 && on_segment(a1, b1, b2))
+            || (d2 == 0.0 && on_segment(a2, b1, b2))
+            || (d3 == 0.0 && on_segment(b1, a1, a2))
+            || (d4 == 0.0 && on_segment(b2, a1, a2))
+    }
+
+    /// 两条折线是否相交。
+    fn polylines_intersect(a: &[Pos2], b: &[Pos2]) -> bool {
+        a.windows(2).any(|seg_a| {
+            b.windows(2).any(|seg_b| match (seg_a, seg_b) {
+                ([a1, a2], [b1, b2]) => Self::segments_intersect(*a1, *a2, *b1, *b2),
+                _ => false,
+            })
+        })
+    }
+
     fn hit_test_connection(&self, pointer: Pos2, threshold: f32) -> Option<usize> {
         self.connections.iter().enumerate().find_map(|(idx, conn)| {
-            let from_node = self.node_by_id(conn.from_node_id)?;
-            let to_node = self.node_by_id(conn.to_node_id)?;
-
-            let from = self.port_pos_screen(from_node, PortKind::Output);
-            let to = self.port_pos_screen(to_node, PortKind::Input);
-
-            let horizontal = (to.x - from.x).abs();
-            let curvature = horizontal.max(60.0) * 0.45;
-            let c1 = from + Vec2::new(curvature, 0.0);
-            let c2 = to - Vec2::new(curvature, 0.0);
-
-            let mut min_d = f32::MAX;
-            let samples = 24;
-            let mut prev = from;
-            for i in 1..=samples {
-                let t = i as f32 / samples as f32;
-                let cur = Self::cubic_bezier_point(from, c1, c2, to, t);
-                min_d = min_d.min(Self::point_to_segment_distance(pointer, prev, cur));
-                prev = cur;
-            }
+            let min_d = self.connection_path_screen(conn)?.distance_to(pointer, 0.0);
 
             (min_d <= threshold).then_some(idx)
         })
     }
 
+    /// 命中测试：鼠标是否靠近某条连线的输入端（线的后段），用于把连线从输入端“拎起来”。
+    fn hit_test_connection_input_end(&self, pointer: Pos2) -> Option<usize> {
+        self.connections.iter().enumerate().find_map(|(idx, conn)| {
+            let min_d = self
+                .connection_path_screen(conn)?
+                .distance_to(pointer, LINK_GRAB_T_MIN);
+
+            (min_d <= LINK_HIT_THRESHOLD).then_some(idx)
+        })
+    }
+
     // ========================
     // 绘制相关
     // ========================
@@ -292,14 +943,8 @@ impl NodeGraphApp {
     /// - 终点：`to`
     /// - 两个控制点在水平方向展开，形成“流程图常见弯曲”
     fn draw_bezier(painter: &egui::Painter, from: Pos2, to: Pos2, color: Color32) {
-        let horizontal = (to.x - from.x).abs();
-        let curvature = horizontal.max(60.0) * 0.45;
-
-        let control_1 = from + Vec2::new(curvature, 0.0);
-        let control_2 = to - Vec2::new(curvature, 0.0);
-
         painter.add(CubicBezierShape::from_points_stroke(
-            [from, control_1, control_2, to],
+            Self::bezier_control_points(from, to),
             false,
             Color32::TRANSPARENT,
             Stroke::new(2.0, color),
@@ -309,19 +954,56 @@ impl NodeGraphApp {
     /// 绘制所有“正式连线”。
     fn draw_connections(&self, ui: &egui::Ui) {
         let painter = ui.painter();
+        let search_filter = self.search_filter();
 
         for connection in &self.connections {
-            let Some(from_node) = self.node_by_id(connection.from_node_id) else {
-                // 节点可能被删除（未来扩展场景），找不到就跳过。
+            // 节点可能已被删除，找不到端点就跳过。
+            let Some(path) = self.connection_path_screen(connection) else {
                 continue;
             };
-            let Some(to_node) = self.node_by_id(connection.to_node_id) else {
-                continue;
-            };
-
-            let from = self.port_pos_screen(from_node, PortKind::Output);
-            let to = self.port_pos_screen(to_node, PortKind::Input);
-            Self::draw_bezier(painter, from, to, LINK_COLOR);
+            // 选中的连线在下面垫一层半透明高亮。
+            if self.selected_link == Some(connection.key()) {
+                painter.add(egui::Shape::line(
+                    path.polyline(),
+                    Stroke::new(
+                        connection.style.width + 6.0,
+                        self.theme.node_border_selected.gamma_multiply(0.45),
+                    ),
+                ));
+            }
+            // 上下游高亮：在连线下面垫一层对应颜色。
+            if let Some(color) = self.dependency_link_color(connection.key()) {
+                painter.add(egui::Shape::line(
+                    path.polyline(),
+                    Stroke::new(connection.style.width + 4.0, color.gamma_multiply(0.6)),
+                ));
+            }
+            // 有问题的连线：垫一层问题颜色。
+            if let Some(color) = self.issue_link_color(connection.key()) {
+                painter.add(egui::Shape::line(
+                    path.polyline(),
+                    Stroke::new(connection.style.width + 4.0, color.gamma_multiply(0.5)),
+                ));
+            }
+            // 路径查询：高亮的路径再垫一层，画在上下游高亮之上。
+            if let Some(color) = self.path_link_color(connection.key()) {
+                painter.add(egui::Shape::line(
+                    path.polyline(),
+                    Stroke::new(connection.style.width + 4.0, color.gamma_multiply(0.8)),
+                ));
+            }
+            // 搜索筛选模式下，两端都没有命中的连线变暗。
+            let dimmed = search_filter.as_ref().is_some_and(|matches| {
+                !matches.contains(&connection.from_node_id)
+                    && !matches.contains(&connection.to_node_id)
+            });
+            if dimmed {
+                let mut style = connection.style;
+                style.color = Some(style.color(self.theme.link).gamma_multiply(0.25));
+                style.paint(painter, &path, self.theme.link);
+            } else {
+                connection.style.paint(painter, &path, self.theme.link);
+            }
         }
     }
 
@@ -337,87 +1019,313 @@ impl NodeGraphApp {
             return;
         };
 
-        let from = self.port_pos_screen(node, link.from_port);
-        Self::draw_bezier(ui.painter(), from, link.current_pos, DRAG_LINK_COLOR);
+        // 反向拖拽（从输入端口出发）时，鼠标一侧视为输出端，曲线弯曲方向才正确。
+        let port_pos = self.port_anchor_world(node, link.from_port) + self.pan_offset;
+        let (from, to) = match link.from_port {
+            PortKind::Output => (port_pos, link.current_pos),
+            PortKind::Input => (link.current_pos, port_pos),
+        };
+        Self::draw_bezier(ui.painter(), from, to, self.theme.drag_link);
     }
 
     /// 绘制单个节点，并处理该节点相关输入（拖拽、端口交互）。
     fn draw_node(&mut self, ui: &mut egui::Ui, node_index: usize) {
+        // 子图节点的正文是内部概况，需要在借出可变节点之前算好。
+        let summary = self
+            .nodes
+            .get(node_index)
+            .filter(|node| node.kind == NodeKind::Subgraph)
+            .map(|node| self.subgraph_summary(node.id));
         let Some(node) = self.nodes.get_mut(node_index) else {
             return;
         };
         let node_rect = Rect::from_min_size(node.position + self.pan_offset, node.size);
-        let header_rect =
-            Rect::from_min_size(node_rect.min, Vec2::new(node_rect.width(), HEADER_HEIGHT));
+        let header_rect = if node.kind == NodeKind::Reroute {
+            // 转接点没有标题栏，整个圆点都可以拖拽。
+            node_rect
+        } else {
+            Rect::from_min_size(node_rect.min, Vec2::new(node_rect.width(), HEADER_HEIGHT))
+        };
 
         // 节点拖拽只放在标题栏，避免正文编辑区被拖拽逻辑抢事件。
         let drag_response = ui
             .allocate_rect(header_rect, Sense::click_and_drag())
             .on_hover_cursor(CursorIcon::Grab);
-        if drag_response.dragged_by(PointerButton::Primary) {
-            node.position += drag_response.drag_motion();
-            ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
-        }
+        let selected = Self::handle_header_interaction(
+            ui,
+            node,
+            &drag_response,
+            &mut self.selected_nodes,
+            &mut self.node_drag,
+        );
 
         let node_id = node.id;
+        let kind = node.kind;
         let input_pos = Pos2::new(node_rect.left(), node_rect.center().y);
         let output_pos = Pos2::new(node_rect.right(), node_rect.center().y);
-        let node_hovered = drag_response.hovered();
-
-        // 端口命中区域（比视觉圆点大，增强可操作性）。
-        let input_hit_rect = Rect::from_center_size(input_pos, Vec2::splat(PORT_HIT_RADIUS * 2.0));
-        let output_hit_rect =
-            Rect::from_center_size(output_pos, Vec2::splat(PORT_HIT_RADIUS * 2.0));
-
-        // 给输入端口分配交互。
-        let input_response = ui
-            .interact(
-                input_hit_rect,
-                ui.make_persistent_id(("input_port", node_id)),
-                Sense::click_and_drag(),
-            )
-            .on_hover_cursor(CursorIcon::PointingHand);
-
-        // 给输出端口分配交互。
-        let output_response = ui
-            .interact(
-                output_hit_rect,
-                ui.make_persistent_id(("output_port", node_id)),
-                Sense::click_and_drag(),
-            )
-            .on_hover_cursor(CursorIcon::PointingHand);
+        let resizing = kind != NodeKind::Reroute
+            && Self::handle_node_resize(ui, node, node_rect, &mut self.node_drag);
+        let node_hovered = drag_response.hovered() || resizing;
+
+        let (input_response, output_response, port_drag_start) =
+            Self::allocate_node_ports(ui, node_id, kind, input_pos, output_pos);
+
+        let theme = self.theme;
+        let header_color = node.header_color.unwrap_or(theme.node_header);
+        let mut menu_responses = vec![drag_response];
+        if kind == NodeKind::Reroute {
+            Self::draw_reroute(ui, &theme, node_rect, header_color, node_hovered, selected);
+        } else {
+            Self::draw_node_frame(
+                ui,
+                &theme,
+                node_rect,
+                header_rect,
+                header_color,
+                node_hovered,
+                selected,
+            );
+            let focus_title = self.renaming_node.take_if(|id| *id == node_id).is_some();
+            let (title_resp, content_resp) = Self::draw_node_text_editors(
+                ui,
+                &theme,
+                node,
+                [node_rect, header_rect],
+                focus_title,
+                summary.as_deref(),
+            );
+            self.handle_node_text_edits(
+                ui,
+                node_index,
+                &title_resp,
+                &content_resp,
+                summary.is_some(),
+            );
+            menu_responses.push(title_resp);
+            menu_responses.push(content_resp);
+        }
 
-        // 当从输出端口开始拖拽时，进入“拖拽连线”状态。
-        if output_response.drag_started() {
-            let pointer_pos = output_response.interact_pointer_pos().unwrap_or(output_pos);
+        // 输入/输出端口可视化：使用“插槽”风格而不是简单圆点。
+        for (response, pos, port) in [
+            (&input_response, input_pos, PortKind::Input),
+            (&output_response, output_pos, PortKind::Output),
+        ] {
+            match response {
+                Some(response) if kind == NodeKind::Reroute => {
+                    Self::draw_reroute_handle(ui, &theme, pos, port, response.hovered());
+                }
+                Some(response) => {
+                    Self::draw_port_socket(ui, &theme, pos, port, response.hovered());
+                }
+                None => {}
+            }
+        }
+        if kind == NodeKind::Subgraph {
+            self.draw_exposed_ports(ui, node_id, node_rect);
+        }
 
-            self.dragging_link = Some(DragLinkState {
-                from_node: node_id,
-                from_port: PortKind::Output,
-                current_pos: pointer_pos,
-            });
+        if let Some((port, pointer_pos)) = port_drag_start {
+            let duplicate = ui.input(|i| i.modifiers.command);
+            self.start_port_drag(node_id, port, pointer_pos, duplicate);
         }
 
-        Self::draw_node_frame(ui, node_rect, header_rect, node_hovered);
-        let is_editing = Self::draw_node_text_editors(ui, node, node_rect, header_rect);
+        // 右键菜单：节点本体（标题栏 / 文本区）与端口各自一份。
+        for response in &menu_responses {
+            response.context_menu(|ui| self.node_menu_contents(ui, node_id));
+        }
+        for (response, port) in [
+            (input_response, PortKind::Input),
+            (output_response, PortKind::Output),
+        ] {
+            if let Some(response) = response {
+                response.context_menu(|ui| self.port_menu_contents(ui, node_id, port));
+            }
+        }
+    }
 
-        // 输入/输出端口可视化：使用“插槽”风格而不是简单圆点。
-        Self::draw_port_socket(ui, input_pos, PortKind::Input, input_response.hovered());
-        Self::draw_port_socket(ui, output_pos, PortKind::Output, output_response.hovered());
+    /// 给节点存在的端口分配交互，并返回本帧开始拖拽的端口（及按下位置）。
+    fn allocate_node_ports(
+        ui: &egui::Ui,
+        node_id: usize,
+        kind: NodeKind,
+        input_pos: Pos2,
+        output_pos: Pos2,
+    ) -> (
+        Option<egui::Response>,
+        Option<egui::Response>,
+        Option<(PortKind, Pos2)>,
+    ) {
+        // 给存在的端口分配交互；命中区域比视觉圆点大，增强可操作性。
+        let port_response = |ui: &egui::Ui, port: PortKind, pos: Pos2, id_source: &str| {
+            kind.has_port(port).then(|| {
+                ui.interact(
+                    Rect::from_center_size(pos, Vec2::splat(kind.port_hit_radius() * 2.0)),
+                    ui.make_persistent_id((id_source, node_id)),
+                    Sense::click_and_drag(),
+                )
+                .on_hover_cursor(CursorIcon::PointingHand)
+            })
+        };
+        let input_response = port_response(ui, PortKind::Input, input_pos, "input_port");
+        let output_response = port_response(ui, PortKind::Output, output_pos, "output_port");
+
+        // 从端口开始拖拽时，等节点绘制完再进入“拖拽连线”状态（见 `start_port_drag`）。
+        let port_drag_start = [
+            (&input_response, input_pos, PortKind::Input),
+            (&output_response, output_pos, PortKind::Output),
+        ]
+        .into_iter()
+        .find_map(|(response, pos, port)| {
+            let response = response.as_ref()?;
+            response
+                .drag_started()
+                .then(|| (port, response.interact_pointer_pos().unwrap_or(pos)))
+        });
 
-        // 编辑时切换为文字光标，减少“可编辑区域不明确”的体验问题。
-        if is_editing {
-            ui.ctx().set_cursor_icon(CursorIcon::Text);
+        (input_response, output_response, port_drag_start)
+    }
+
+    /// 标题栏交互：选中与拖动。返回节点是否处于选中状态。
+    ///
+    /// 点击选中节点，Shift + 点击增减选中；开始拖动未选中的节点时也选中它。
+    /// 拖动时先累积未吸附的位置，所有节点绘制完后再吸附（见 `apply_node_drag`）。
+    fn handle_header_interaction(
+        ui: &egui::Ui,
+        node: &Node,
+        response: &egui::Response,
+        selected_nodes: &mut BTreeSet<usize>,
+        node_drag: &mut Option<NodeDrag>,
+    ) -> bool {
+        let extend = ui.input(|i| i.modifiers.shift);
+        if response.clicked() {
+            if !extend {
+                selected_nodes.clear();
+            }
+            if !selected_nodes.remove(&node.id) || !extend {
+                selected_nodes.insert(node.id);
+            }
+        } else if response.drag_started() && !selected_nodes.contains(&node.id) {
+            if !extend {
+                selected_nodes.clear();
+            }
+            selected_nodes.insert(node.id);
         }
+
+        if response.dragged_by(PointerButton::Primary) {
+            let drag = NodeDrag::for_node(node_drag, node, None);
+            drag.raw = drag.raw.translate(response.drag_motion());
+            ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+        }
+
+        selected_nodes.contains(&node.id)
     }
 
-    fn draw_node_frame(ui: &egui::Ui, node_rect: Rect, header_rect: Rect, node_hovered: bool) {
-        let border_color = if node_hovered {
-            NODE_BORDER_HOVER_COLOR
+    /// 从端口开始拖拽连线。
+    ///
+    /// - 输出端口：新建一条连线；按住 Ctrl 时改为复制该端口最近的一条连线，
+    ///   保留它的输入端，拖动输出端去接另一个输出端口
+    /// - 已连接的输入端口：把最近接入的那条连线从输入端拎起来，可以改接或丢弃
+    /// - 未连接的输入端口：反向拖出一条连线，去找输出端口
+    fn start_port_drag(
+        &mut self,
+        node_id: usize,
+        port: PortKind,
+        pointer_pos: Pos2,
+        duplicate: bool,
+    ) {
+        // 一次拖线（新建、改接或删除连线）记为一步撤销，松开时连线变了才记。
+        self.begin_link_edit();
+        // 拎起连线时，被抓住的一端跟着鼠标走，另一端不动；按住 Ctrl 时保留原连线（即复制）。
+        let (from_node, from_port) = match port {
+            PortKind::Output => self
+                .connections
+                .iter()
+                .rev()
+                .find(|connection| duplicate && connection.from_node_id == node_id)
+                .map_or((node_id, PortKind::Output), |connection| {
+                    (connection.to_node_id, PortKind::Input)
+                }),
+            PortKind::Input => self
+                .connections
+                .iter()
+                .rposition(|connection| connection.to_node_id == node_id)
+                .map_or((node_id, PortKind::Input), |index| {
+                    let lifted = if duplicate {
+                        self.connections.get(index).map(Connection::key)
+                    } else {
+                        Some(self.connections.remove(index).key())
+                    };
+                    lifted.map_or((node_id, PortKind::Input), |(from, _)| {
+                        (from, PortKind::Output)
+                    })
+                }),
+        };
+
+        self.dragging_link = Some(DragLinkState {
+            from_node,
+            from_port,
+            current_pos: pointer_pos,
+        });
+    }
+
+    /// 绘制转接点：一个带描边的小圆点。
+    fn draw_reroute(
+        ui: &egui::Ui,
+        theme: &Theme,
+        rect: Rect,
+        color: Color32,
+        hovered: bool,
+        selected: bool,
+    ) {
+        let radius = rect.width().min(rect.height()) * 0.5 - 2.0;
+        ui.painter().circle_filled(rect.center(), radius, color);
+        ui.painter().circle_stroke(
+            rect.center(),
+            radius,
+            Self::node_border(theme, hovered, selected),
+        );
+    }
+
+    /// 节点边框：选中时用高亮色加粗，悬停时变亮。
+    fn node_border(theme: &Theme, hovered: bool, selected: bool) -> Stroke {
+        if selected {
+            Stroke::new(2.0, theme.node_border_selected)
+        } else if hovered {
+            Stroke::new(1.5, theme.node_border_hover)
+        } else {
+            Stroke::new(1.5, theme.node_border_idle)
+        }
+    }
+
+    /// 转接点两侧的小端口：只画一个实心小圆点，悬停时放大一点。
+    fn draw_reroute_handle(
+        ui: &egui::Ui,
+        theme: &Theme,
+        center: Pos2,
+        kind: PortKind,
+        hovered: bool,
+    ) {
+        let color = match kind {
+            PortKind::Input => theme.port_input,
+            PortKind::Output => theme.port_output,
+        };
+        let radius = if hovered {
+            REROUTE_PORT_RADIUS + 1.5
         } else {
-            NODE_BORDER_IDLE_COLOR
+            REROUTE_PORT_RADIUS
         };
+        ui.painter().circle_filled(center, radius, color);
+    }
 
+    fn draw_node_frame(
+        ui: &egui::Ui,
+        theme: &Theme,
+        node_rect: Rect,
+        header_rect: Rect,
+        header_color: Color32,
+        node_hovered: bool,
+        selected: bool,
+    ) {
         // 阴影层。
         ui.painter().rect_filled(
             node_rect.translate(Vec2::new(0.0, 3.0)).expand(1.0),
@@ -427,11 +1335,11 @@ impl NodeGraphApp {
 
         // 节点主体背景与边框。
         ui.painter()
-            .rect_filled(node_rect, CornerRadius::same(8), NODE_BG_COLOR);
+            .rect_filled(node_rect, CornerRadius::same(8), theme.node_bg);
         ui.painter().rect_stroke(
             node_rect,
             CornerRadius::same(8),
-            Stroke::new(1.5, border_color),
+            Self::node_border(theme, node_hovered, selected),
             StrokeKind::Outside,
         );
 
@@ -443,79 +1351,133 @@ impl NodeGraphApp {
                 sw: 0,
                 se: 0,
             },
-            NODE_HEADER_COLOR,
+            header_color,
         );
     }
 
+    /// 节点标题和正文输入框的光标、撤销和“已修改”标记（见 `track_text_edit`）；
+    /// 子图节点的正文是内部概况，双击进入子图。
+    fn handle_node_text_edits(
+        &mut self,
+        ui: &egui::Ui,
+        node_index: usize,
+        title: &egui::Response,
+        content: &egui::Response,
+        is_summary: bool,
+    ) {
+        // 编辑时切换为文字光标，减少“可编辑区域不明确”的体验问题。
+        if title.has_focus() || content.has_focus() {
+            ui.ctx().set_cursor_icon(CursorIcon::Text);
+        }
+        let editing = |response: &egui::Response| response.has_focus() || response.lost_focus();
+        let Some(node) = self.nodes.get(node_index) else {
+            return;
+        };
+        if is_summary && content.double_clicked() {
+            self.pending_action =
+                Some(ContextAction::Subgraph(SubgraphAction::Open(Some(node.id))));
+        }
+        let title_text = editing(title).then(|| node.title.clone());
+        let content_text = (!is_summary && editing(content)).then(|| node.content.clone());
+        if let Some(text) = title_text {
+            self.track_text_edit(title, &text);
+        }
+        if let Some(text) = content_text {
+            self.track_text_edit(content, &text);
+        }
+    }
+
+    /// 标题与正文输入框。
+    ///
+    /// `summary` 不为空时（子图节点）正文区域显示这段只读文字，而不是正文输入框。
     fn draw_node_text_editors(
         ui: &mut egui::Ui,
+        theme: &Theme,
         node: &mut Node,
-        node_rect: Rect,
-        header_rect: Rect,
-    ) -> bool {
+        [node_rect, header_rect]: [Rect; 2],
+        focus_title: bool,
+        summary: Option<&str>,
+    ) -> (egui::Response, egui::Response) {
         // 文本框必须直接绑定 node 字段，才能真正修改状态。
-        let title_rect = header_rect.shrink2(Vec2::new(NODE_INNER_PADDING_X, 5.0));
+        let mut title_rect = header_rect.shrink2(Vec2::new(NODE_INNER_PADDING_X, 5.0));
+        if let Some(icon) = &node.icon {
+            Self::draw_node_icon(ui, theme, icon, header_rect);
+            title_rect.min.x += node_tags::ICON_WIDTH;
+        }
         let title_resp = ui.put(
             title_rect,
             egui::TextEdit::singleline(&mut node.title)
                 .frame(false)
                 .font(FontId::proportional(14.0))
-                .text_color(Color32::WHITE)
+                .text_color(theme.node_title_text)
                 .desired_width(f32::INFINITY),
         );
+        if focus_title {
+            title_resp.request_focus();
+        }
 
+        // 有标签时正文区域让出底部的一行给标签。
+        let body_rect = node_rect.with_max_y(node_rect.bottom() - node.tag_row_height());
         let content_rect = Rect::from_min_max(
             Pos2::new(
-                node_rect.left() + NODE_INNER_PADDING_X,
+                body_rect.left() + NODE_INNER_PADDING_X,
                 header_rect.bottom() + NODE_INNER_PADDING_Y,
             ),
             Pos2::new(
-                node_rect.right() - NODE_INNER_PADDING_X,
-                node_rect.bottom() - NODE_INNER_PADDING_Y,
+                body_rect.right() - NODE_INNER_PADDING_X,
+                body_rect.bottom() - NODE_INNER_PADDING_Y,
             ),
         );
         ui.painter()
-            .rect_filled(content_rect, CornerRadius::same(6), NODE_BG_COLOR);
+            .rect_filled(content_rect, CornerRadius::same(6), theme.node_bg);
+        if !node.tags.is_empty() {
+            Self::draw_tag_chips(ui, theme, &node.tags, node_rect);
+        }
         let content_text_rect = content_rect.shrink2(Vec2::new(8.0, 6.0));
-        let content_resp = ui.put(
-            content_text_rect,
-            egui::TextEdit::multiline(&mut node.content)
-                .frame(false)
-                .desired_width(content_text_rect.width())
-                .desired_rows(Self::max_content_lines(node_rect))
-                .font(FontId::proportional(12.0))
-                .text_color(Color32::from_gray(220)),
-        );
-        Self::clamp_text_lines(&mut node.content, Self::max_content_lines(node_rect));
-
-        title_resp.has_focus() || content_resp.has_focus()
-    }
-
-    /// 限制正文最多显示行数，防止在固定高度输入框里“回车无限下沉”。
-    fn clamp_text_lines(text: &mut String, max_lines: usize) {
-        let mut merged = String::new();
-        for (idx, line) in text.split('\n').take(max_lines).enumerate() {
-            if idx > 0 {
-                merged.push('\n');
-            }
-            merged.push_str(line);
+        if let Some(summary) = summary {
+            let summary_resp =
+                Self::draw_subgraph_summary(ui, theme, node.id, summary, content_rect);
+            return (title_resp, summary_resp);
         }
-        if *text != merged {
-            *text = merged;
+
+        // 正文放进滚动区域：内容超出节点高度时滚动查看，而不是截断。
+        let content_resp = ui
+            .scope_builder(egui::UiBuilder::new().max_rect(content_text_rect), |ui| {
+                egui::ScrollArea::vertical()
+                    .id_salt(("node_content", node.id))
+                    .auto_shrink(false)
+                    .show(ui, |ui| {
+                        ui.add(
+                            egui::TextEdit::multiline(&mut node.content)
+                                .frame(false)
+                                .desired_width(f32::INFINITY)
+                                .desired_rows(Self::visible_content_lines(body_rect))
+                                .font(FontId::proportional(12.0))
+                                .text_color(theme.node_text),
+                        )
+                    })
+                    .inner
+            })
+            .inner;
+        if node.auto_height {
+            Self::fit_node_height_to_content(ui, node, content_text_rect.width());
         }
+
+        (title_resp, content_resp)
     }
 
-    fn max_content_lines(node_rect: Rect) -> usize {
+    /// 正文区域能完整显示的行数，用来让输入框铺满正文区域。
+    fn visible_content_lines(node_rect: Rect) -> usize {
         let content_height = node_rect.height() - HEADER_HEIGHT - NODE_INNER_PADDING_Y * 2.0 - 12.0;
         ((content_height / 18.0).floor() as usize).max(1)
     }
 
     /// 绘制端口：输入为空心环，输出为带实心核的圆点。
     /// 这是更常见的节点编辑器视觉语义。
-    fn draw_port_socket(ui: &egui::Ui, center: Pos2, kind: PortKind, hovered: bool) {
+    fn draw_port_socket(ui: &egui::Ui, theme: &Theme, center: Pos2, kind: PortKind, hovered: bool) {
         let color = match kind {
-            PortKind::Input => PORT_INPUT_COLOR,
-            PortKind::Output => PORT_OUTPUT_COLOR,
+            PortKind::Input => theme.port_input,
+            PortKind::Output => theme.port_output,
         };
 
         if hovered {
@@ -528,14 +1490,14 @@ impl NodeGraphApp {
 
         // 外环统一：与节点背景分离，辨识度更高。
         ui.painter()
-            .circle_filled(center, PORT_RADIUS, NODE_BG_COLOR);
+            .circle_filled(center, PORT_RADIUS, theme.node_bg);
         ui.painter()
             .circle_stroke(center, PORT_RADIUS, Stroke::new(PORT_RING_STROKE, color));
 
         // 输入端口做“空心”语义；输出端口做“实心核”语义。
         match kind {
             PortKind::Input => {
-                ui.painter().circle_filled(center, 2.0, NODE_BG_COLOR);
+                ui.painter().circle_filled(center, 2.0, theme.node_bg);
             }
             PortKind::Output => {
                 ui.painter().circle_filled(
@@ -558,17 +1520,20 @@ impl NodeGraphApp {
         );
     }
 
-    fn draw_canvas_grid(ui: &egui::Ui, rect: Rect, pan_offset: Vec2) {
-        let spacing_minor = 24.0;
+    fn draw_canvas_grid(&self, ui: &egui::Ui, rect: Rect) {
+        let pan_offset = self.pan_offset;
+        let spacing_minor = GRID_SPACING;
         let spacing_major = spacing_minor * 4.0;
         let painter = ui.painter();
-        let grid_minor_color = Color32::from_rgba_unmultiplied(120, 130, 150, 16);
-        let grid_major_color = Color32::from_rgba_unmultiplied(120, 130, 150, 30);
+        let grid_minor_color = self.theme.grid_minor;
+        let grid_major_color = self.theme.grid_major;
 
-        let offset_x_minor = pan_offset.x.rem_euclid(spacing_minor);
-        let offset_y_minor = pan_offset.y.rem_euclid(spacing_minor);
-        let offset_x_major = pan_offset.x.rem_euclid(spacing_major);
-        let offset_y_major = pan_offset.y.rem_euclid(spacing_major);
+        // 网格线对齐世界坐标原点，这样节点吸附后正好落在线上。
+        let origin = pan_offset - rect.min.to_vec2();
+        let offset_x_minor = origin.x.rem_euclid(spacing_minor);
+        let offset_y_minor = origin.y.rem_euclid(spacing_minor);
+        let offset_x_major = origin.x.rem_euclid(spacing_major);
+        let offset_y_major = origin.y.rem_euclid(spacing_major);
 
         let mut x = rect.left() + offset_x_minor;
         while x <= rect.right() {
@@ -652,10 +1617,9 @@ impl NodeGraphApp {
 
     /// 在鼠标松开时，尝试结束“拖拽连线”。
     ///
-    /// 规则：
-    /// 1) 只有拖到 Input 端口才创建连线
-    /// 2) 不允许自己连自己
-    /// 3) 不允许重复连线
+    /// - 拖到可连接的端口上：创建连线（规则见 `try_connect`）
+    /// - 拖到空白画布上：打开只列出可连接类型的命令面板，选中后在松开处创建并接好新节点
+    /// - 其他情况：丢弃这条临时连线
     fn finish_dragging_link_if_needed(&mut self, ctx: &egui::Context) {
         let Some(link) = self.dragging_link else {
             return;
@@ -663,27 +1627,49 @@ impl NodeGraphApp {
 
         // 只在“鼠标左键已松开”时结算。
         if !ctx.input(|i| i.pointer.primary_down()) {
-            if let Some(pointer_pos) = ctx.input(|i| i.pointer.interact_pos())
-                && let Some((target_node_id, target_port)) = self.port_at(pointer_pos)
-            {
-                let duplicate_exists = self.connections.iter().any(|connection| {
-                    connection.from_node_id == link.from_node
-                        && connection.to_node_id == target_node_id
-                });
-
-                if target_port == PortKind::Input
-                    && target_node_id != link.from_node
-                    && !duplicate_exists
-                {
-                    self.connections.push(Connection {
-                        from_node_id: link.from_node,
-                        to_node_id: target_node_id,
-                    });
+            if let Some(pointer_pos) = ctx.input(|i| i.pointer.interact_pos()) {
+                match self.port_at(pointer_pos) {
+                    Some((target_node_id, target_port))
+                        if target_port == link.from_port.opposite() =>
+                    {
+                        self.connect_ports(link.from_node, link.from_port, target_node_id);
+                    }
+                    None if self.canvas_rect.contains(pointer_pos)
+                        && !self.is_pointer_over_node(pointer_pos) =>
+                    {
+                        self.node_palette = Some(NodePalette::new(
+                            pointer_pos,
+                            self.pan_offset,
+                            Some((link.from_node, link.from_port)),
+                        ));
+                    }
+                    _ => {}
                 }
             }
 
             // 无论是否连接成功，都退出临时拖拽状态。
             self.dragging_link = None;
+            self.finish_link_edit();
+        }
+    }
+
+    /// Ctrl+A 全选，Esc 取消选中；正在输入文字时不处理。
+    fn handle_selection_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() || self.node_palette.is_some() {
+            return;
+        }
+
+        let (select_all, clear) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::COMMAND, Key::A),
+                i.consume_key(egui::Modifiers::NONE, Key::Escape),
+            )
+        });
+        if select_all {
+            self.selected_nodes = self.visible_nodes().map(|node| node.id).collect();
+        } else if clear {
+            self.selected_nodes.clear();
+            self.selected_link = None;
         }
     }
 
@@ -692,15 +1678,23 @@ impl NodeGraphApp {
     /// 关键思路：
     /// - 只有在“空白区域按下并拖动”才平移
     /// - 若起始点在节点或端口上，则不进入平移
+    /// - 若起始点靠近某条连线的输入端，则把连线拎起来（按住 Ctrl 则复制一份）
     fn handle_canvas_pan(&mut self, canvas_response: &egui::Response, ctx: &egui::Context) {
         if canvas_response.drag_started_by(PointerButton::Primary) {
-            self.dragging_canvas =
-                canvas_response
-                    .interact_pointer_pos()
-                    .is_some_and(|pointer_pos| {
-                        !self.is_pointer_over_node(pointer_pos)
-                            && self.port_at(pointer_pos).is_none()
-                    });
+            let press_pos = ctx
+                .input(|i| i.pointer.press_origin())
+                .or_else(|| canvas_response.interact_pointer_pos());
+            let on_empty_canvas = press_pos.is_some_and(|pointer_pos| {
+                !self.is_pointer_over_node(pointer_pos) && self.port_at(pointer_pos).is_none()
+            });
+            let grabbed_link = press_pos
+                .filter(|_| on_empty_canvas)
+                .and_then(|pointer_pos| self.hit_test_connection_input_end(pointer_pos));
+
+            if let Some(index) = grabbed_link {
+                self.lift_connection(index, ctx);
+            }
+            self.dragging_canvas = on_empty_canvas && grabbed_link.is_none();
         }
 
         if self.dragging_canvas && canvas_response.dragged_by(PointerButton::Primary) {
@@ -715,9 +1709,185 @@ impl NodeGraphApp {
             self.dragging_canvas = false;
         }
     }
+
+    /// 左侧面板：顶部切换页，下面是当前页的内容。
+    fn side_panel_contents(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Node Control");
+        ui.horizontal_wrapped(|ui| {
+            for tab in SidePanelTab::ALL {
+                ui.selectable_value(&mut self.side_panel_tab, tab, tab.label());
+            }
+        });
+        ui.separator();
+
+        match self.side_panel_tab {
+            SidePanelTab::Controls => self.control_panel_contents(ui),
+            SidePanelTab::Inspector => self.inspector_panel_contents(ui),
+            SidePanelTab::Library => self.library_panel_contents(ui),
+            SidePanelTab::Theme => self.theme_panel_contents(ui),
+            SidePanelTab::Analysis => self.analysis_panel_contents(ui),
+            SidePanelTab::Issues => self.issues_panel_contents(ui),
+        }
+        if let Some(message) = &self.status_message {
+            ui.separator();
+            ui.weak(message);
+        }
+    }
+
+    /// 侧边栏“控制”页。
+    fn control_panel_contents(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.undo_history.can_undo(), egui::Button::new("Undo"))
+                .clicked()
+            {
+                self.undo();
+            }
+            if ui
+                .add_enabled(self.undo_history.can_redo(), egui::Button::new("Redo"))
+                .clicked()
+            {
+                self.redo();
+            }
+        });
+
+        if ui.button("Add Node").clicked() {
+            self.record_undo();
+            self.add_node();
+        }
+
+        if ui.button("Reset View").clicked() {
+            self.pan_offset = Vec2::ZERO;
+        }
+
+        if ui.button("Clear Links").clicked() {
+            self.record_undo();
+            self.connections.clear();
+        }
+
+        egui::ComboBox::from_label("Link routing")
+            .selected_text(self.link_routing.label())
+            .show_ui(ui, |ui| {
+                for routing in LinkRouting::ALL {
+                    ui.selectable_value(&mut self.link_routing, routing, routing.label());
+                }
+            });
+
+        egui::ComboBox::from_label("Flow animation")
+            .selected_text(self.flow_animation.label())
+            .show_ui(ui, |ui| {
+                for animation in FlowAnimation::ALL {
+                    ui.selectable_value(&mut self.flow_animation, animation, animation.label());
+                }
+            });
+        ui.horizontal(|ui| {
+            if ui.button("Simulate Activity").clicked() {
+                self.simulate_link_activity();
+            }
+            if ui.button("Clear Activity").clicked() {
+                self.clear_link_activity();
+            }
+        });
+
+        ui.checkbox(&mut self.snap_to_grid, "Snap to grid");
+        ui.checkbox(&mut self.alignment_guides, "Alignment guides");
+        ui.checkbox(&mut self.dependency_highlight, "Dependency highlight")
+            .on_hover_text("Highlight upstream and downstream of the hovered or selected node");
+
+        ui.separator();
+        ui.weak("Tab / Space: quick add node");
+        ui.weak("Ctrl + F: search nodes");
+        ui.weak("Hold Alt while dragging: toggle snapping");
+
+        ui.separator();
+        ui.label(format!("Nodes: {}", self.nodes.len()));
+        ui.label(format!("Links: {}", self.connections.len()));
+        ui.label(format!("Selected: {}", self.selected_nodes.len()));
+        ui.collapsing("Align", |ui| {
+            if let Some(command) = self.align_buttons(ui) {
+                self.align_selected(command);
+            }
+        });
+        ui.collapsing("Tags", |ui| self.tag_filter_contents(ui));
+        ui.collapsing("Paths", |ui| self.path_panel_contents(ui));
+        if ui
+            .add_enabled(
+                !self.selected_nodes.is_empty(),
+                egui::Button::new("Frame Selection"),
+            )
+            .clicked()
+        {
+            self.pending_action = Some(ContextAction::Frame(FrameAction::CreateFromSelection));
+        }
+        if ui
+            .add_enabled(
+                !self.selected_nodes.is_empty(),
+                egui::Button::new("Collapse to Subgraph"),
+            )
+            .clicked()
+        {
+            self.pending_action = Some(ContextAction::Subgraph(SubgraphAction::CollapseSelection));
+        }
+
+        ui.separator();
+        self.document_file_contents(ui);
+
+        ui.label("Recovery directory");
+        ui.text_edit_singleline(&mut self.recovery_dir)
+            .on_hover_text("Autosave snapshots and the crash-recovery snapshot log go here");
+
+        ui.separator();
+        ui.label("Export path");
+        ui.text_edit_singleline(&mut self.export_path);
+        if ui.button("Export SVG").clicked() {
+            self.export_svg_to_file();
+        }
+    }
+
+    /// 处理全局快捷键（缩放、面板、撤销、对齐、选择、搜索）。
+    fn handle_shortcuts(&mut self, ctx: &egui::Context) {
+        Self::handle_zoom_shortcuts(ctx);
+        self.handle_palette_shortcut(ctx);
+        self.handle_undo_shortcuts(ctx);
+        self.handle_align_shortcuts(ctx);
+        self.handle_selection_shortcuts(ctx);
+        self.handle_search_shortcut(ctx);
+    }
+
+    /// 从输入端拎起一条连线，转为“拖拽连线”状态：输入端跟着鼠标走，输出端不动；
+    /// 按住 Ctrl 时保留原连线（即复制），和从端口拖出时的规则一致（见 `start_port_drag`）。
+    fn lift_connection(&mut self, index: usize, ctx: &egui::Context) {
+        let Some(from_node) = self
+            .connections
+            .get(index)
+            .map(|connection| connection.from_node_id)
+        else {
+            return;
+        };
+        self.begin_link_edit();
+        if !ctx.input(|i| i.modifiers.command) {
+            self.connections.remove(index);
+        }
+
+        self.dragging_link = Some(DragLinkState {
+            from_node,
+            from_port: PortKind::Output,
+            current_pos: ctx.input(|i| i.pointer.interact_pos()).unwrap_or_default(),
+        });
+    }
 }
 
 impl eframe::App for NodeGraphApp {
+    /// 关闭前以及定期由 eframe 调用，用于保存状态。
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, self);
+    }
+
+    /// 正常退出：清空恢复目录，下次启动不再提示恢复。
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.clear_recovery();
+    }
+
     /// 每一帧都会调用 `update`。
     ///
     /// 你可以把它理解为 UI 主循环：
@@ -725,7 +1895,23 @@ impl eframe::App for NodeGraphApp {
     /// 2) 画中央画布（连接线、节点、临时线）
     /// 3) 更新交互状态（鼠标拖拽、松开结算）
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        Self::handle_zoom_shortcuts(ctx);
+        self.refresh_theme(ctx);
+        self.ensure_documents();
+        self.handle_shortcuts(ctx);
+        self.update_search_matches();
+        self.update_dependencies(ctx);
+        self.update_path_query();
+        self.update_validation();
+        self.update_recovery(ctx);
+
+        // ---------- 顶部文档标签栏 ----------
+        egui::TopBottomPanel::top("document_tabs")
+            .frame(
+                egui::Frame::new()
+                    .fill(self.theme.side_panel_bg)
+                    .inner_margin(egui::Margin::symmetric(12, 6)),
+            )
+            .show(ctx, |ui| self.document_tabs(ui));
 
         // ---------- 左侧控制面板 ----------
         egui::SidePanel::left("left_panel")
@@ -733,48 +1919,53 @@ impl eframe::App for NodeGraphApp {
             .default_width(220.0)
             .frame(
                 egui::Frame::new()
-                    .fill(SIDE_PANEL_BG)
+                    .fill(self.theme.side_panel_bg)
                     .inner_margin(egui::Margin::symmetric(12, 10)),
             )
-            .show(ctx, |ui| {
-                ui.heading("Node Control");
-                ui.separator();
-
-                if ui.button("Add Node").clicked() {
-                    self.add_node();
-                }
-
-                if ui.button("Reset View").clicked() {
-                    self.pan_offset = Vec2::ZERO;
-                }
-
-                if ui.button("Clear Links").clicked() {
-                    self.connections.clear();
-                }
-
-                ui.separator();
-                ui.label(format!("Nodes: {}", self.nodes.len()));
-                ui.label(format!("Links: {}", self.connections.len()));
-            });
+            .show(ctx, |ui| self.side_panel_contents(ui));
 
         // ---------- 中央画布 ----------
         egui::CentralPanel::default()
-            .frame(egui::Frame::new().fill(CANVAS_BG_COLOR))
+            .frame(egui::Frame::new().fill(self.theme.canvas_bg))
             .show(ctx, |ui| {
                 // 给整个中央区域注册一个可拖拽响应，专门用于“画布平移”。
                 let canvas_rect = ui.max_rect();
-                Self::draw_canvas_grid(ui, canvas_rect, self.pan_offset);
-                let canvas_response = ui.allocate_rect(canvas_rect, Sense::drag());
+                self.canvas_rect = canvas_rect;
+                self.draw_canvas_grid(ui, canvas_rect);
+                let canvas_response = ui.allocate_rect(canvas_rect, Sense::click_and_drag());
 
                 // 绘制顺序很重要：
-                // 先画连接线（在下层）
-                // 再画节点（在上层）
+                // 先画分组框（最底层）
+                // 再画连接线（在下层）
+                // 最后画节点（在上层）
+                self.draw_frames(ui);
+                self.update_exposed_ports();
+                self.update_link_routes();
                 self.draw_connections(ui);
+                self.draw_link_flow(ui);
                 self.draw_dragging_link(ui);
+                self.draw_link_labels(ui);
 
                 for node_index in 0..self.nodes.len() {
-                    self.draw_node(ui, node_index);
+                    // 被折叠分组框隐藏的节点不绘制。
+                    if self
+                        .nodes
+                        .get(node_index)
+                        .is_some_and(|node| !self.is_node_hidden(node))
+                    {
+                        self.draw_node(ui, node_index);
+                    }
                 }
+                // 节点拖动 / 缩放：吸附到网格或其他节点，并显示对齐参考线。
+                self.apply_node_drag(ui);
+                // 子图内显示面包屑导航。
+                self.draw_breadcrumb(ui, canvas_rect);
+                // 上下游高亮、路径高亮、校验问题标记、搜索命中高亮与搜索栏。
+                self.draw_dependency_highlights(ui);
+                self.draw_path_highlights(ui);
+                self.draw_issue_marks(ui);
+                self.draw_search_highlights(ui);
+                self.draw_search_bar(ctx, canvas_rect);
 
                 // 如果正在拖拽临时连线，每帧更新鼠标位置。
                 if let Some(link) = &mut self.dragging_link
@@ -783,15 +1974,111 @@ impl eframe::App for NodeGraphApp {
                     link.current_pos = pointer_pos;
                     ctx.request_repaint();
                 }
-                if ctx.input(|i| i.pointer.button_clicked(PointerButton::Secondary))
-                    && let Some(pos) = ctx.input(|i| i.pointer.interact_pos())
-                    && let Some(index) = self.hit_test_connection(pos, 10.0)
+                // Ctrl + 右键拖动：切断轨迹经过的连线。
+                self.handle_cut_gesture(ui);
+
+                // 单击连线选中连线，单击空白处取消选中。
+                if canvas_response.clicked() && !ctx.input(|i| i.modifiers.shift) {
+                    self.selected_nodes.clear();
+                    self.selected_link = canvas_response.interact_pointer_pos().and_then(|pos| {
+                        match self.canvas_menu_target_at(pos) {
+                            CanvasMenuTarget::Link { link, .. } => Some(link),
+                            CanvasMenuTarget::Empty { .. } => None,
+                        }
+                    });
+                } else if !self.selected_nodes.is_empty() {
+                    self.selected_link = None;
+                }
+
+                // 右键空白处或连线：记录菜单目标，然后显示对应菜单。
+                if canvas_response.secondary_clicked()
+                    && let Some(pos) = canvas_response.interact_pointer_pos()
                 {
-                    self.connections.remove(index);
+                    self.canvas_menu = Some(self.canvas_menu_target_at(pos));
+                }
+                canvas_response.context_menu(|ui| self.canvas_menu_contents(ui));
+
+                // 双击连线：在双击处插入一个转接点。
+                if canvas_response.double_clicked()
+                    && let Some(pos) = canvas_response.interact_pointer_pos()
+                    && let CanvasMenuTarget::Link { link, world_pos } =
+                        self.canvas_menu_target_at(pos)
+                {
+                    self.pending_action = Some(ContextAction::InsertOnLink {
+                        link,
+                        kind: NodeKind::Reroute,
+                        world_pos,
+                    });
+                }
+
+                // 菜单操作放在所有绘制之后执行，避免在遍历节点时修改节点列表。
+                if let Some(action) = self.pending_action.take() {
+                    self.apply_context_action(action, canvas_rect);
                 }
                 // 先结算“连线拖拽是否结束”，再处理“画布平移”。
                 self.finish_dragging_link_if_needed(ctx);
                 self.handle_canvas_pan(&canvas_response, ctx);
             });
+
+        self.show_node_palette(ctx);
+        self.show_close_prompt(ctx);
+        self.show_recovery_prompt(ctx);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::Pos2;
+
+    use super::NodeGraphApp;
+
+    fn intersect(a: [(f32, f32); 2], b: [(f32, f32); 2]) -> bool {
+        let [a1, a2] = a.map(|(x, y)| Pos2::new(x, y));
+        let [b1, b2] = b.map(|(x, y)| Pos2::new(x, y));
+        NodeGraphApp::segments_intersect(a1, a2, b1, b2)
+    }
+
+    #[test]
+    fn crossing_segments_intersect() {
+        assert!(
+            intersect([(0.0, 0.0), (10.0, 10.0)], [(0.0, 10.0), (10.0, 0.0)]),
+            "对角线应相交"
+        );
+    }
+
+    #[test]
+    fn separate_segments_do_not_intersect() {
+        assert!(
+            !intersect([(0.0, 0.0), (10.0, 0.0)], [(0.0, 5.0), (10.0, 5.0)]),
+            "平行线段不相交"
+        );
+        assert!(
+            !intersect([(0.0, 0.0), (4.0, 4.0)], [(6.0, 0.0), (10.0, -4.0)]),
+            "延长线才相交的线段不算相交"
+        );
+    }
+
+    #[test]
+    fn touching_endpoints_intersect() {
+        assert!(
+            intersect([(0.0, 0.0), (5.0, 5.0)], [(5.0, 5.0), (10.0, 0.0)]),
+            "端点接触算相交"
+        );
+        assert!(
+            intersect([(0.0, 0.0), (10.0, 0.0)], [(5.0, 0.0), (5.0, 5.0)]),
+            "端点落在另一条线段上算相交"
+        );
+    }
+
+    #[test]
+    fn collinear_segments() {
+        assert!(
+            intersect([(0.0, 0.0), (10.0, 0.0)], [(5.0, 0.0), (15.0, 0.0)]),
+            "共线且重叠的线段相交"
+        );
+        assert!(
+            !intersect([(0.0, 0.0), (4.0, 0.0)], [(6.0, 0.0), (10.0, 0.0)]),
+            "共线但不重叠的线段不相交"
+        );
     }
 }
@@ -0,0 +1,625 @@
+use egui::{Color32, CornerRadius, Pos2, Rect, Sense, Vec2};
+
+use super::dependency_highlight::FlowDirection;
+use super::group_frame::FrameAction;
+use super::link_style::LinkDash;
+use super::node_align::AlignCommand;
+use super::node_tags::TagAction;
+use super::path_query::PathEndpoint;
+use super::subgraph::SubgraphAction;
+use super::validation::QuickFix;
+use super::{
+    Connection, LINK_HIT_THRESHOLD, LinkKey, LinkRouting, LinkStyle, Node, NodeGraphApp, NodeKind,
+    PortKind, Theme,
+};
+
+// ============================================================
+// 右键菜单
+//
+// 菜单本身只负责“选出一个操作”（`ContextAction`），
+// 真正修改图数据的逻辑统一放在 `apply_context_action` 里，
+// 这样绘制节点时不会因为菜单操作而改动正在遍历的节点列表。
+// ============================================================
+
+// 标题栏 / 分组框可选颜色（右键菜单 -> Color）。
+pub(super) const HEADER_COLOR_PRESETS: [Color32; 6] = [
+    Color32::from_rgb(57, 116, 245),
+    Color32::from_rgb(34, 160, 107),
+    Color32::from_rgb(214, 128, 36),
+    Color32::from_rgb(200, 64, 64),
+    Color32::from_rgb(142, 84, 214),
+    Color32::from_rgb(96, 104, 120),
+];
+// 复制节点时相对原节点的偏移，避免完全重叠。
+const DUPLICATE_OFFSET: Vec2 = Vec2::new(24.0, 24.0);
+
+/// 画布右键菜单的目标：在右键点击那一刻确定，菜单打开期间保持不变。
+#[derive(Clone, Copy, Debug)]
+pub(super) enum CanvasMenuTarget {
+    /// 空白画布，`world_pos` 为右键位置（世界坐标）。
+    Empty { world_pos: Pos2 },
+    /// 某条连线，`world_pos` 为右键位置（世界坐标）。
+    Link { link: LinkKey, world_pos: Pos2 },
+}
+
+/// 右键菜单选出的操作。
+#[derive(Clone, Debug)]
+pub(super) enum ContextAction {
+    /// 在指定位置添加某类型的节点。
+    AddNode {
+        kind: NodeKind,
+        world_pos: Pos2,
+    },
+    /// 把剪贴板里的节点粘贴到指定位置。
+    Paste {
+        world_pos: Pos2,
+    },
+    /// 平移视图，让所有节点居中显示。
+    FrameAll,
+    /// 选中某个节点并让它居中显示；节点在别的子图层级里时先进入那一层。
+    FocusNode(usize),
+    CopyNode(usize),
+    DuplicateNode(usize),
+    DeleteNode(usize),
+    RenameNode(usize),
+    /// 设置标题栏颜色，`None` 表示恢复默认。
+    SetHeaderColor {
+        node_id: usize,
+        color: Option<Color32>,
+    },
+    /// 开关节点的自动高度。
+    SetAutoHeight {
+        node_id: usize,
+        enabled: bool,
+    },
+    /// 恢复节点类型的默认尺寸（同时关闭自动高度）。
+    ResetNodeSize(usize),
+    /// 断开节点的全部连线。
+    DisconnectNode(usize),
+    /// 断开某个端口上的全部连线。
+    DisconnectPort {
+        node_id: usize,
+        port: PortKind,
+    },
+    DeleteLink(LinkKey),
+    /// 开始编辑连线标签。
+    EditLinkLabel(LinkKey),
+    SetLinkStyle {
+        link: LinkKey,
+        style: LinkStyle,
+    },
+    /// 设置单条连线的走线方式，`None` 表示跟随文档默认值。
+    SetLinkRouting {
+        link: LinkKey,
+        routing: Option<LinkRouting>,
+    },
+    /// 在连线中间插入一个节点，原连线被拆成两段。
+    InsertOnLink {
+        link: LinkKey,
+        kind: NodeKind,
+        world_pos: Pos2,
+    },
+    /// 选中某个节点的全部上游或下游节点。
+    SelectDependencies {
+        node_id: usize,
+        direction: FlowDirection,
+    },
+    /// 设置路径查询的起点或终点。
+    SetPathEndpoint {
+        node_id: usize,
+        endpoint: PathEndpoint,
+    },
+    /// 选中一组节点（可以在其他子图层级里）。
+    SelectNodes(Vec<usize>),
+    /// 对齐 / 分布选中的节点。
+    Align(AlignCommand),
+    /// 分组框操作。
+    Frame(FrameAction),
+    /// 子图操作。
+    Subgraph(SubgraphAction),
+    /// 图标 / 标签操作。
+    Tag(TagAction),
+    /// 校验问题的快速修复。
+    Fix(QuickFix),
+}
+
+impl ContextAction {
+    /// 会修改图数据、需要在执行前记录撤销的操作。
+    ///
+    /// `Align` 由 `align_selected` 自己记录（选中节点不足时不会产生撤销步骤）。
+    fn is_undoable(&self) -> bool {
+        match self {
+            Self::FrameAll
+            | Self::FocusNode(_)
+            | Self::CopyNode(_)
+            | Self::RenameNode(_)
+            | Self::EditLinkLabel(_)
+            | Self::SelectDependencies { .. }
+            | Self::SetPathEndpoint { .. }
+            | Self::SelectNodes(_)
+            | Self::Align(_) => false,
+            Self::Frame(frame) => frame.is_undoable(),
+            Self::Subgraph(subgraph) => subgraph.is_undoable(),
+            Self::Tag(tag) => tag.is_undoable(),
+            _ => true,
+        }
+    }
+}
+
+impl NodeGraphApp {
+    /// 根据右键位置（屏幕坐标）判断画布菜单的目标：优先连线，否则为空白处。
+    pub(super) fn canvas_menu_target_at(&self, pointer_pos: Pos2) -> CanvasMenuTarget {
+        let world_pos = pointer_pos - self.pan_offset;
+        self.hit_test_connection(pointer_pos, LINK_HIT_THRESHOLD)
+            .and_then(|index| self.connections.get(index))
+            .map_or(CanvasMenuTarget::Empty { world_pos }, |connection| {
+                CanvasMenuTarget::Link {
+                    link: connection.key(),
+                    world_pos,
+                }
+            })
+    }
+
+    /// 画布菜单：空白处（添加节点 / 粘贴 / 显示全部）或连线（删除 / 插入节点 / 转接点）。
+    pub(super) fn canvas_menu_contents(&mut self, ui: &mut egui::Ui) {
+        let mut action = None;
+
+        match self.canvas_menu {
+            Some(CanvasMenuTarget::Empty { world_pos }) => {
+                ui.menu_button("Add Node", |ui| {
+                    for kind in NodeKind::ALL {
+                        if ui.button(kind.label()).clicked() {
+                            action = Some(ContextAction::AddNode { kind, world_pos });
+                        }
+                    }
+                });
+                if ui
+                    .add_enabled(self.clipboard.is_some(), egui::Button::new("Paste"))
+                    .clicked()
+                {
+                    action = Some(ContextAction::Paste { world_pos });
+                }
+                ui.separator();
+                if ui
+                    .add_enabled(
+                        self.visible_nodes().next().is_some(),
+                        egui::Button::new("Frame All"),
+                    )
+                    .clicked()
+                {
+                    action = Some(ContextAction::FrameAll);
+                }
+            }
+            Some(CanvasMenuTarget::Link { link, world_pos }) => {
+                ui.label(self.connection_label(link));
+                ui.separator();
+                if ui.button("Insert Node").clicked() {
+                    action = Some(ContextAction::InsertOnLink {
+                        link,
+                        kind: NodeKind::Process,
+                        world_pos,
+                    });
+                }
+                if ui.button("Add Reroute").clicked() {
+                    action = Some(ContextAction::InsertOnLink {
+                        link,
+                        kind: NodeKind::Reroute,
+                        world_pos,
+                    });
+                }
+                if ui.button("Edit Label").clicked() {
+                    action = Some(ContextAction::EditLinkLabel(link));
+                }
+                if let Some(style) = self.connection_by_key(link).map(|c| c.style) {
+                    ui.menu_button("Style", |ui| {
+                        if let Some(style) = Self::link_style_menu_contents(ui, style, &self.theme)
+                        {
+                            action = Some(ContextAction::SetLinkStyle { link, style });
+                        }
+                    });
+                }
+                let current = self.connection_by_key(link).and_then(|c| c.routing);
+                ui.menu_button("Routing", |ui| {
+                    let default_label = format!("Default ({})", self.link_routing.label());
+                    if ui.radio(current.is_none(), default_label).clicked() {
+                        action = Some(ContextAction::SetLinkRouting {
+                            link,
+                            routing: None,
+                        });
+                    }
+                    for routing in LinkRouting::ALL {
+                        if ui
+                            .radio(current == Some(routing), routing.label())
+                            .clicked()
+                        {
+                            action = Some(ContextAction::SetLinkRouting {
+                                link,
+                                routing: Some(routing),
+                            });
+                        }
+                    }
+                });
+                ui.separator();
+                if ui.button("Delete Link").clicked() {
+                    action = Some(ContextAction::DeleteLink(link));
+                }
+            }
+            None => ui.close(),
+        }
+
+        if action.is_some() {
+            self.pending_action = action;
+        }
+    }
+
+    /// 节点菜单：重命名 / 复制 / 颜色 / 图标 / 标签 / 断开连线 / 删除。
+    pub(super) fn node_menu_contents(&mut self, ui: &mut egui::Ui, node_id: usize) {
+        let mut action = None;
+        let kind = self.node_by_id(node_id).map(|node| node.kind);
+        let is_reroute = kind == Some(NodeKind::Reroute);
+        let is_subgraph = kind == Some(NodeKind::Subgraph);
+        let has_connections = self
+            .connections
+            .iter()
+            .any(|connection| connection.touches(node_id));
+
+        if !is_reroute && ui.button("Rename").clicked() {
+            action = Some(ContextAction::RenameNode(node_id));
+        }
+        // 复制只复制节点本身，对子图节点没有意义。
+        if !is_subgraph {
+            if ui.button("Duplicate").clicked() {
+                action = Some(ContextAction::DuplicateNode(node_id));
+            }
+            if ui.button("Copy").clicked() {
+                action = Some(ContextAction::CopyNode(node_id));
+            }
+        }
+        ui.menu_button("Color", |ui| {
+            if let Some(color_action) = Self::header_color_menu_contents(ui, &self.theme, node_id) {
+                action = Some(color_action);
+            }
+        });
+        if !is_reroute {
+            ui.menu_button("Icon", |ui| {
+                if let Some(icon_action) = Self::icon_menu_contents(ui, node_id) {
+                    action = Some(icon_action);
+                }
+            });
+            ui.menu_button("Tags", |ui| {
+                if let Some(tag_action) = self.tags_menu_contents(ui, node_id) {
+                    action = Some(tag_action);
+                }
+            });
+            let mut auto_height = self
+                .node_by_id(node_id)
+                .is_some_and(|node| node.auto_height);
+            if ui.checkbox(&mut auto_height, "Auto Height").changed() {
+                action = Some(ContextAction::SetAutoHeight {
+                    node_id,
+                    enabled: auto_height,
+                });
+            }
+            if ui.button("Reset Size").clicked() {
+                action = Some(ContextAction::ResetNodeSize(node_id));
+            }
+        }
+        // 右键的是选中节点之一时，可以对整个选区执行对齐 / 分布。
+        if self.selected_nodes.len() > 1 && self.selected_nodes.contains(&node_id) {
+            ui.menu_button("Align", |ui| {
+                if let Some(command) = self.align_buttons(ui) {
+                    action = Some(ContextAction::Align(command));
+                }
+            });
+        }
+        if self.selected_nodes.contains(&node_id) {
+            if ui.button("Frame Selection").clicked() {
+                action = Some(ContextAction::Frame(FrameAction::CreateFromSelection));
+            }
+            if ui.button("Collapse to Subgraph").clicked() {
+                action = Some(ContextAction::Subgraph(SubgraphAction::CollapseSelection));
+            }
+        }
+        if is_subgraph {
+            if ui.button("Open Subgraph").clicked() {
+                action = Some(ContextAction::Subgraph(SubgraphAction::Open(Some(node_id))));
+            }
+            if ui.button("Ungroup").clicked() {
+                action = Some(ContextAction::Subgraph(SubgraphAction::Ungroup(node_id)));
+            }
+        }
+        ui.separator();
+        if let Some(select) =
+            Self::dependency_menu_buttons(ui, node_id, has_connections || is_subgraph)
+        {
+            action = Some(select);
+        }
+        if !is_subgraph && let Some(path) = Self::path_menu_buttons(ui, node_id) {
+            action = Some(path);
+        }
+        if ui
+            .add_enabled(has_connections, egui::Button::new("Disconnect All"))
+            .clicked()
+        {
+            action = Some(ContextAction::DisconnectNode(node_id));
+        }
+        if ui.button("Delete").clicked() {
+            action = Some(ContextAction::DeleteNode(node_id));
+        }
+
+        if action.is_some() {
+            self.pending_action = action;
+        }
+    }
+
+    /// 节点菜单里的“颜色”子菜单：预设色块 / 恢复默认。
+    fn header_color_menu_contents(
+        ui: &mut egui::Ui,
+        theme: &Theme,
+        node_id: usize,
+    ) -> Option<ContextAction> {
+        let mut action = None;
+        ui.horizontal(|ui| {
+            for color in HEADER_COLOR_PRESETS {
+                if Self::color_swatch(ui, theme, color).clicked() {
+                    action = Some(ContextAction::SetHeaderColor {
+                        node_id,
+                        color: Some(color),
+                    });
+                }
+            }
+        });
+        if ui.button("Default").clicked() {
+            action = Some(ContextAction::SetHeaderColor {
+                node_id,
+                color: None,
+            });
+        }
+        action
+    }
+
+    /// 端口菜单：查看该端口上的连线（点击可跳转到对端节点）/ 断开。
+    pub(super) fn port_menu_contents(&mut self, ui: &mut egui::Ui, node_id: usize, port: PortKind) {
+        let mut action = None;
+        let peers: Vec<(usize, String)> = self
+            .connections
+            .iter()
+            .filter_map(|connection| match port {
+                PortKind::Input if connection.to_node_id == node_id => {
+                    Some(connection.from_node_id)
+                }
+                PortKind::Output if connection.from_node_id == node_id => {
+                    Some(connection.to_node_id)
+                }
+                PortKind::Input | PortKind::Output => None,
+            })
+            .filter_map(|peer_id| {
+                self.node_by_id(peer_id)
+                    .map(|peer| (peer_id, peer.title.clone()))
+            })
+            .collect();
+
+        ui.add_enabled_ui(!peers.is_empty(), |ui| {
+            ui.menu_button(format!("Show Connections ({})", peers.len()), |ui| {
+                for (peer_id, title) in &peers {
+                    let arrow = match port {
+                        PortKind::Input => "←",
+                        PortKind::Output => "→",
+                    };
+                    if ui.button(format!("{arrow} {title}")).clicked() {
+                        action = Some(ContextAction::FocusNode(*peer_id));
+                    }
+                }
+            });
+            if ui.button("Disconnect").clicked() {
+                action = Some(ContextAction::DisconnectPort { node_id, port });
+            }
+        });
+
+        if action.is_some() {
+            self.pending_action = action;
+        }
+    }
+
+    /// 执行菜单选出的操作。`canvas_rect` 用于“居中显示”类操作计算平移量。
+    pub(super) fn apply_context_action(&mut self, action: ContextAction, canvas_rect: Rect) {
+        if action.is_undoable() {
+            self.record_undo();
+        }
+
+        match action {
+            ContextAction::AddNode { kind, world_pos } => {
+                self.add_node_at(kind, world_pos);
+            }
+            ContextAction::Paste { world_pos } => {
+                if let Some(node) = self.clipboard.clone() {
+                    self.insert_node_copy(&node, world_pos - node.size * 0.5);
+                }
+            }
+            ContextAction::FrameAll => {
+                if let Some(bounds) = self
+                    .visible_nodes()
+                    .map(|node| node.world_rect())
+                    .reduce(|a, b| a.union(b))
+                {
+                    self.center_view_on(bounds, canvas_rect);
+                }
+            }
+            ContextAction::FocusNode(node_id) => self.select_nodes(&[node_id], canvas_rect),
+            ContextAction::CopyNode(node_id) => {
+                self.clipboard = self.node_by_id(node_id).cloned();
+            }
+            ContextAction::DuplicateNode(node_id) => {
+                if let Some(node) = self.node_by_id(node_id).cloned() {
+                    self.insert_node_copy(&node, node.position + DUPLICATE_OFFSET);
+                }
+            }
+            ContextAction::DeleteNode(node_id) => self.remove_node(node_id),
+            ContextAction::RenameNode(node_id) => self.renaming_node = Some(node_id),
+            ContextAction::SetHeaderColor { node_id, color } => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.header_color = color;
+                }
+            }
+            ContextAction::SetAutoHeight { node_id, enabled } => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.auto_height = enabled;
+                }
+            }
+            ContextAction::ResetNodeSize(node_id) => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.size = node.kind.default_size();
+                    node.auto_height = false;
+                }
+            }
+            ContextAction::DisconnectNode(node_id) => {
+                self.connections
+                    .retain(|connection| !connection.touches(node_id));
+            }
+            ContextAction::DisconnectPort { node_id, port } => {
+                self.connections.retain(|connection| match port {
+                    PortKind::Input => connection.to_node_id != node_id,
+                    PortKind::Output => connection.from_node_id != node_id,
+                });
+            }
+            ContextAction::DeleteLink(link) => {
+                self.connections.retain(|existing| existing.key() != link);
+            }
+            ContextAction::EditLinkLabel(link) => self.editing_link_label = Some(link),
+            ContextAction::SetLinkStyle { link, style } => {
+                if let Some(connection) = self.connection_by_key_mut(link) {
+                    connection.style = style;
+                }
+            }
+            ContextAction::SetLinkRouting { link, routing } => {
+                if let Some(connection) = self.connection_by_key_mut(link) {
+                    connection.routing = routing;
+                }
+            }
+            ContextAction::InsertOnLink {
+                link,
+                kind,
+                world_pos,
+            } => self.insert_node_on_link(link, kind, world_pos),
+            ContextAction::SelectDependencies { node_id, direction } => {
+                self.select_dependencies(node_id, direction);
+            }
+            ContextAction::SetPathEndpoint { node_id, endpoint } => {
+                self.set_path_endpoint(node_id, endpoint);
+            }
+            ContextAction::SelectNodes(ids) => self.select_nodes(&ids, canvas_rect),
+            ContextAction::Align(command) => self.align_selected(command),
+            ContextAction::Frame(frame) => self.apply_frame_action(frame),
+            ContextAction::Subgraph(subgraph) => self.apply_subgraph_action(subgraph, canvas_rect),
+            ContextAction::Tag(tag) => self.apply_tag_action(tag),
+            ContextAction::Fix(fix) => self.apply_quick_fix(fix),
+        }
+    }
+
+    /// 在连线中间插入一个节点，原连线被拆成两段。
+    fn insert_node_on_link(&mut self, link: LinkKey, kind: NodeKind, world_pos: Pos2) {
+        let Some(index) = self
+            .connections
+            .iter()
+            .position(|existing| existing.key() == link)
+        else {
+            return;
+        };
+        let new_id = self.add_node_at(kind, world_pos);
+        // 拆出的两段沿用原连线的走线方式和样式，标签只留在前一段。
+        let connection = self.connections.remove(index);
+        self.connections.push(Connection {
+            from_node_id: new_id,
+            label: String::new(),
+            ..connection.clone()
+        });
+        self.connections.insert(
+            index,
+            Connection {
+                to_node_id: new_id,
+                ..connection
+            },
+        );
+    }
+
+    /// 连线样式子菜单：在当前样式的副本上修改，有改动时返回新样式。
+    ///
+    /// 主题的连线颜色显示在“默认”色块上。
+    pub(super) fn link_style_menu_contents(
+        ui: &mut egui::Ui,
+        current: LinkStyle,
+        theme: &Theme,
+    ) -> Option<LinkStyle> {
+        let mut style = current;
+
+        ui.horizontal(|ui| {
+            if Self::color_swatch(ui, theme, theme.link)
+                .on_hover_text("Default")
+                .clicked()
+            {
+                style.color = None;
+            }
+            for color in HEADER_COLOR_PRESETS {
+                if Self::color_swatch(ui, theme, color).clicked() {
+                    style.color = Some(color);
+                }
+            }
+        });
+        ui.add(
+            egui::Slider::new(
+                &mut style.width,
+                LinkStyle::MIN_WIDTH..=LinkStyle::MAX_WIDTH,
+            )
+            .text("Width"),
+        );
+        for dash in LinkDash::ALL {
+            ui.radio_value(&mut style.dash, dash, dash.label());
+        }
+        ui.checkbox(&mut style.arrow, "Arrow");
+
+        (style != current).then_some(style)
+    }
+
+    /// 以 `position` 为左上角插入一个节点副本（分配新 ID），返回新节点 ID。
+    fn insert_node_copy(&mut self, node: &Node, position: Pos2) -> usize {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+
+        self.nodes.push(Node {
+            id,
+            position,
+            parent: self.current_subgraph,
+            ..node.clone()
+        });
+        id
+    }
+
+    /// 调整平移量，使世界坐标中的 `bounds` 位于画布中央。
+    pub(super) fn center_view_on(&mut self, bounds: Rect, canvas_rect: Rect) {
+        self.pan_offset = canvas_rect.center() - bounds.center();
+    }
+
+    /// 连线的描述文字，例如 `Input → Deal`。
+    pub(super) fn connection_label(&self, (from_node_id, to_node_id): LinkKey) -> String {
+        format!(
+            "{} → {}",
+            self.node_name(from_node_id),
+            self.node_name(to_node_id)
+        )
+    }
+
+    /// 颜色色块按钮。
+    pub(super) fn color_swatch(ui: &mut egui::Ui, theme: &Theme, color: Color32) -> egui::Response {
+        let (rect, response) = ui.allocate_exact_size(Vec2::splat(18.0), Sense::click());
+        ui.painter().rect_filled(rect, CornerRadius::same(4), color);
+        if response.hovered() {
+            ui.painter().rect_stroke(
+                rect,
+                CornerRadius::same(4),
+                egui::Stroke::new(1.5, theme.swatch_hover),
+                egui::StrokeKind::Outside,
+            );
+        }
+        response
+    }
+}
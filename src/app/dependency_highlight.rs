@@ -0,0 +1,212 @@
+use std::collections::{BTreeSet, VecDeque};
+
+use egui::{Color32, CornerRadius, Stroke, StrokeKind};
+
+use super::context_menu::ContextAction;
+use super::{LinkKey, NodeGraphApp, NodeKind, Theme};
+
+// ============================================================
+// 上下游依赖高亮
+//
+// 打开侧边栏的 “Dependency highlight” 后，悬停（没有悬停时取选中）的节点
+// 会把它传递依赖的全部上游节点和连线、以及受它影响的全部下游节点和连线
+// 用两种颜色标出来。依赖关系只看 `connections`：
+// 子图里的节点由当前层的子图节点代表，悬停子图节点等于悬停它的全部内部节点。
+// 节点右键菜单的 “Select Upstream / Downstream” 可以直接选中整个上游 / 下游集合。
+// ============================================================
+
+/// 沿连线的方向。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum FlowDirection {
+    /// 逆着连线找输入来源。
+    Upstream,
+    /// 顺着连线找受影响的节点。
+    Downstream,
+}
+
+impl FlowDirection {
+    fn color(self, theme: &Theme) -> Color32 {
+        match self {
+            Self::Upstream => theme.upstream,
+            Self::Downstream => theme.downstream,
+        }
+    }
+}
+
+/// 当前高亮的上下游集合（每帧重新计算）。
+#[derive(Debug, Default)]
+pub(super) struct Dependencies {
+    /// 悬停 / 选中的节点本身，不画描边。
+    roots: BTreeSet<usize>,
+    upstream: BTreeSet<usize>,
+    downstream: BTreeSet<usize>,
+    upstream_links: BTreeSet<LinkKey>,
+    downstream_links: BTreeSet<LinkKey>,
+}
+
+impl NodeGraphApp {
+    /// 从 `roots` 出发沿连线传递可达的节点及经过的连线。
+    ///
+    /// 结果不含起点本身，除非环路又回到了起点。
+    pub(super) fn reachable_from(
+        &self,
+        roots: &BTreeSet<usize>,
+        direction: FlowDirection,
+    ) -> (BTreeSet<usize>, BTreeSet<LinkKey>) {
+        let mut nodes = BTreeSet::new();
+        let mut links = BTreeSet::new();
+        let mut queue: VecDeque<usize> = roots.iter().copied().collect();
+        while let Some(current) = queue.pop_front() {
+            for connection in &self.connections {
+                let next = match direction {
+                    FlowDirection::Upstream if connection.to_node_id == current => {
+                        connection.from_node_id
+                    }
+                    FlowDirection::Downstream if connection.from_node_id == current => {
+                        connection.to_node_id
+                    }
+                    _ => continue,
+                };
+                links.insert(connection.key());
+                if nodes.insert(next) {
+                    queue.push_back(next);
+                }
+            }
+        }
+        (nodes, links)
+    }
+
+    /// 高亮的起点：悬停的节点，没有悬停时取选中的节点。
+    fn dependency_roots(&self, ctx: &egui::Context) -> BTreeSet<usize> {
+        let hovered = ctx
+            .pointer_hover_pos()
+            .filter(|pos| self.canvas_rect.contains(*pos))
+            .and_then(|pos| {
+                self.visible_nodes()
+                    .filter(|node| self.node_rect_screen(node).contains(pos))
+                    .last()
+                    .map(|node| node.id)
+            });
+        hovered.map_or_else(
+            || self.selected_nodes.clone(),
+            |id| std::iter::once(id).collect(),
+        )
+    }
+
+    /// 把集合里的子图节点换成它（任意深度）的全部内部节点。
+    fn expand_subgraphs(&self, ids: &BTreeSet<usize>) -> BTreeSet<usize> {
+        let mut expanded = ids.clone();
+        for &id in ids {
+            if self
+                .node_by_id(id)
+                .is_some_and(|node| node.kind == NodeKind::Subgraph)
+            {
+                expanded.extend(
+                    self.nodes
+                        .iter()
+                        .filter(|node| self.is_inside(node.id, id))
+                        .map(|node| node.id),
+                );
+            }
+        }
+        expanded
+    }
+
+    /// 每帧绘制之前重新计算上下游集合；没有打开高亮模式时清空。
+    pub(super) fn update_dependencies(&mut self, ctx: &egui::Context) {
+        if !self.dependency_highlight || self.node_drag.is_some() {
+            self.dependencies = Dependencies::default();
+            return;
+        }
+        let roots = self.dependency_roots(ctx);
+        let expanded = self.expand_subgraphs(&roots);
+        let (upstream, upstream_links) = self.reachable_from(&expanded, FlowDirection::Upstream);
+        let (downstream, downstream_links) =
+            self.reachable_from(&expanded, FlowDirection::Downstream);
+        self.dependencies = Dependencies {
+            roots,
+            upstream,
+            downstream,
+            upstream_links,
+            downstream_links,
+        };
+    }
+
+    /// 连线高亮色：属于上游或下游时返回对应颜色。
+    pub(super) fn dependency_link_color(&self, link: LinkKey) -> Option<Color32> {
+        if self.dependencies.upstream_links.contains(&link) {
+            Some(self.theme.upstream)
+        } else if self.dependencies.downstream_links.contains(&link) {
+            Some(self.theme.downstream)
+        } else {
+            None
+        }
+    }
+
+    /// 在上游 / 下游节点（或代表它们的子图节点）外面画彩色描边。
+    pub(super) fn draw_dependency_highlights(&self, ui: &egui::Ui) {
+        let painter = ui.painter();
+        for (direction, ids) in [
+            (FlowDirection::Upstream, &self.dependencies.upstream),
+            (FlowDirection::Downstream, &self.dependencies.downstream),
+        ] {
+            // 子图节点的内部连线会让它的内部节点互为上下游，不给起点本身画描边。
+            let representatives: BTreeSet<usize> = ids
+                .iter()
+                .filter_map(|&id| self.scope_representative(id))
+                .filter(|id| !self.dependencies.roots.contains(id))
+                .collect();
+            for node in self
+                .visible_nodes()
+                .filter(|node| representatives.contains(&node.id))
+            {
+                // 同时属于上下游（环路）时两圈都画，下游在外圈。
+                let expand = match direction {
+                    FlowDirection::Upstream => 3.0,
+                    FlowDirection::Downstream => 6.0,
+                };
+                painter.rect_stroke(
+                    self.node_rect_screen(node).expand(expand),
+                    CornerRadius::same(10),
+                    Stroke::new(2.0, direction.color(&self.theme)),
+                    StrokeKind::Outside,
+                );
+            }
+        }
+    }
+
+    /// 节点右键菜单里的 “Select Upstream / Downstream”。
+    pub(super) fn dependency_menu_buttons(
+        ui: &mut egui::Ui,
+        node_id: usize,
+        enabled: bool,
+    ) -> Option<ContextAction> {
+        let mut action = None;
+        for (label, direction) in [
+            ("Select Upstream", FlowDirection::Upstream),
+            ("Select Downstream", FlowDirection::Downstream),
+        ] {
+            if ui.add_enabled(enabled, egui::Button::new(label)).clicked() {
+                action = Some(ContextAction::SelectDependencies { node_id, direction });
+            }
+        }
+        action
+    }
+
+    /// 选中某个节点的全部上游或下游节点（限当前层可见的节点及代表它们的子图节点）。
+    pub(super) fn select_dependencies(&mut self, node_id: usize, direction: FlowDirection) {
+        let roots = self.expand_subgraphs(&std::iter::once(node_id).collect());
+        let (reachable, _) = self.reachable_from(&roots, direction);
+        let representatives: BTreeSet<usize> = reachable
+            .iter()
+            .filter_map(|&id| self.scope_representative(id))
+            .filter(|&id| id != node_id)
+            .collect();
+        self.selected_nodes = self
+            .visible_nodes()
+            .filter(|node| representatives.contains(&node.id))
+            .map(|node| node.id)
+            .collect();
+        self.selected_link = None;
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use egui::Vec2;
+
+use super::graph_analysis::AnalysisCache;
+use super::group_frame::GroupFrame;
+use super::link_routing::{LinkRouting, RouteCache};
+use super::path_query::PathQuery;
+use super::subgraph::ExposedPortCache;
+use super::undo::UndoHistory;
+use super::validation::Validation;
+use super::{Connection, LinkKey, Node, NodeGraphApp};
+
+// ============================================================
+// 多文档标签页
+//
+// 画布上方的标签栏里可以同时打开多张图，每张图有自己的节点、连线、
+// 平移 / 缩放、选中状态和撤销历史。
+// 前台文档的数据直接放在 `NodeGraphApp` 的字段上（其他模块不用关心有几个文档），
+// 切换标签时把它收进 `Document`，再把目标文档的数据换出来 —— 和撤销快照的做法一样。
+// 文档可以保存为 `.ron` 文件；有未保存修改的标签显示 ●，关闭前会询问是否保存。
+// ============================================================
+
+/// 文档里随文件保存的部分。
+#[derive(Clone, Default, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct DocumentGraph {
+    pub(super) nodes: Vec<Node>,
+    pub(super) connections: Vec<Connection>,
+    next_node_id: usize,
+    frames: Vec<GroupFrame>,
+    next_frame_id: usize,
+    link_routing: LinkRouting,
+}
+
+/// 一个打开的文档（标签页）。
+///
+/// 图数据和视图状态只在文档不在前台时有效，前台文档的这些数据在 `NodeGraphApp` 上。
+#[derive(serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Document {
+    /// 崩溃恢复文件里标识这个文档的 ID（见 `recovery` 模块）。
+    pub(super) recovery_id: String,
+    /// 标签上显示的名称。
+    pub(super) name: String,
+    /// 保存 / 另存时写入的文件路径。
+    pub(super) path: String,
+    /// 上次保存以后有没有修改过。
+    pub(super) modified: bool,
+    pub(super) graph: DocumentGraph,
+    pan_offset: Vec2,
+    zoom: f32,
+    current_subgraph: Option<usize>,
+    #[serde(skip)]
+    selected_nodes: BTreeSet<usize>,
+    #[serde(skip)]
+    selected_link: Option<LinkKey>,
+    #[serde(skip)]
+    undo_history: UndoHistory,
+}
+
+impl Default for Document {
+    fn default() -> Self {
+        Self::untitled(1)
+    }
+}
+
+impl Document {
+    /// 第 `number` 个未命名的空文档。
+    pub(super) fn untitled(number: usize) -> Self {
+        Self {
+            recovery_id: String::new(),
+            name: format!("Untitled {number}"),
+            path: format!("untitled-{number}.ron"),
+            modified: false,
+            graph: DocumentGraph::default(),
+            pan_offset: Vec2::ZERO,
+            zoom: 1.0,
+            current_subgraph: None,
+            selected_nodes: BTreeSet::new(),
+            selected_link: None,
+            undo_history: UndoHistory::default(),
+        }
+    }
+
+    /// 读取 `path` 处的文档文件；图数据放在返回的 `Document` 里，还没有换到前台。
+    pub(super) fn read(path: &str) -> Result<Self, String> {
+        let graph = std::fs::read_to_string(path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| {
+                ron::from_str::<DocumentGraph>(&text).map_err(|err| err.to_string())
+            })?;
+        Ok(Self {
+            name: file_stem(path),
+            path: path.to_owned(),
+            graph,
+            ..Self::default()
+        })
+    }
+
+    /// 从崩溃恢复文件里找回的文档，标记为有未保存的修改。
+    pub(super) fn recovered(
+        recovery_id: String,
+        name: String,
+        path: String,
+        graph: DocumentGraph,
+    ) -> Self {
+        Self {
+            recovery_id,
+            name,
+            path,
+            modified: true,
+            graph,
+            ..Self::default()
+        }
+    }
+}
+
+/// 文件路径里不带扩展名的文件名，用作标签名。
+fn file_stem(path: &str) -> String {
+    Path::new(path).file_stem().map_or_else(
+        || path.to_owned(),
+        |stem| stem.to_string_lossy().into_owned(),
+    )
+}
+
+impl NodeGraphApp {
+    /// 前台文档的元数据（名称、路径、是否修改过）。
+    fn active_document_mut(&mut self) -> Option<&mut Document> {
+        self.documents.get_mut(self.active_document)
+    }
+
+    /// 保证至少有一个文档，前台文档的下标有效，且每个文档都有恢复 ID
+    /// （旧版本保存的状态里没有这些）。
+    pub(super) fn ensure_documents(&mut self) {
+        if self.documents.is_empty() {
+            self.documents.push(Document::untitled(1));
+        }
+        self.active_document = self.active_document.min(self.documents.len() - 1);
+        while let Some(index) = self
+            .documents
+            .iter()
+            .position(|document| document.recovery_id.is_empty())
+        {
+            let id = self.new_recovery_id();
+            if let Some(document) = self.documents.get_mut(index) {
+                document.recovery_id = id;
+            }
+        }
+    }
+
+    /// 标记前台文档有未保存的修改；记录撤销和在画布上改文字时调用。
+    pub(super) fn mark_document_modified(&mut self) {
+        if let Some(document) = self.active_document_mut() {
+            document.modified = true;
+        }
+    }
+
+    /// 前台文档的图数据（复制一份）。
+    pub(super) fn current_graph(&self) -> DocumentGraph {
+        DocumentGraph {
+            nodes: self.nodes.clone(),
+            connections: self.connections.clone(),
+            next_node_id: self.next_node_id,
+            frames: self.frames.clone(),
+            next_frame_id: self.next_frame_id,
+            link_routing: self.link_routing,
+        }
+    }
+
+    /// 把前台文档的图数据和视图状态收进它的 `Document`。
+    fn stash_active_document(&mut self, ctx: &egui::Context) {
+        let graph = DocumentGraph {
+            nodes: std::mem::take(&mut self.nodes),
+            connections: std::mem::take(&mut self.connections),
+            next_node_id: self.next_node_id,
+            frames: std::mem::take(&mut self.frames),
+            next_frame_id: self.next_frame_id,
+            link_routing: self.link_routing,
+        };
+        let Some(document) = self.documents.get_mut(self.active_document) else {
+            return;
+        };
+        document.graph = graph;
+        document.pan_offset = self.pan_offset;
+        document.zoom = ctx.zoom_factor();
+        document.current_subgraph = self.current_subgraph;
+        document.selected_nodes = std::mem::take(&mut self.selected_nodes);
+        document.selected_link = self.selected_link.take();
+        document.undo_history = std::mem::take(&mut self.undo_history);
+    }
+
+    /// 把 `active_document` 指向的文档换到前台，并清掉属于上一个文档的临时状态。
+    fn load_active_document(&mut self, ctx: &egui::Context) {
+        let Some(document) = self.documents.get_mut(self.active_document) else {
+            return;
+        };
+        let graph = std::mem::take(&mut document.graph);
+        self.pan_offset = document.pan_offset;
+        self.current_subgraph = document.current_subgraph;
+        self.selected_nodes = std::mem::take(&mut document.selected_nodes);
+        self.selected_link = document.selected_link.take();
+        self.undo_history = std::mem::take(&mut document.undo_history);
+        ctx.set_zoom_factor(document.zoom);
+
+        self.apply_graph(graph);
+    }
+
+    /// 把图数据放到前台（不涉及视图状态），并清掉属于上一张图的临时状态。
+    pub(super) fn apply_graph(&mut self, graph: DocumentGraph) {
+        self.nodes = graph.nodes;
+        self.connections = graph.connections;
+        self.next_node_id = graph.next_node_id;
+        self.frames = graph.frames;
+        self.next_frame_id = graph.next_frame_id;
+        self.link_routing = graph.link_routing;
+
+        self.dragging_canvas = false;
+        self.cancel_link_edit();
+        self.renaming_node = None;
+        self.editing_link_label = None;
+        self.canvas_menu = None;
+        self.pending_action = None;
+        self.cut_stroke = None;
+        self.route_cache = RouteCache::default();
+        self.exposed_port_cache = ExposedPortCache::default();
+        self.link_activity.clear();
+        self.node_drag = None;
+        self.inspector_edit = None;
+        self.inspector_error = None;
+        self.node_search = None;
+        self.path_query = PathQuery::default();
+        self.validation = Validation::default();
+        self.analysis_cache = AnalysisCache::default();
+        self.renaming_frame = None;
+        self.frame_drag = None;
+    }
+
+    /// 切换到第 `index` 个文档。
+    fn switch_document(&mut self, index: usize, ctx: &egui::Context) {
+        if index == self.active_document || index >= self.documents.len() {
+            return;
+        }
+        self.stash_active_document(ctx);
+        self.active_document = index;
+        self.load_active_document(ctx);
+    }
+
+    /// 在新标签页里打开一个文档，并切换过去。
+    pub(super) fn push_document(&mut self, document: Document, ctx: &egui::Context) {
+        self.stash_active_document(ctx);
+        self.documents.push(document);
+        self.active_document = self.documents.len() - 1;
+        self.load_active_document(ctx);
+    }
+
+    /// 新建一个空文档，编号取还没被使用的最小值。
+    fn new_document(&mut self, ctx: &egui::Context) {
+        let number = (1..)
+            .find(|number| {
+                let name = format!("Untitled {number}");
+                self.documents.iter().all(|document| document.name != name)
+            })
+            .unwrap_or(1);
+        self.push_document(Document::untitled(number), ctx);
+    }
+
+    /// 关闭第 `index` 个文档（不询问）；关掉最后一个时换成一个空文档。
+    fn close_document(&mut self, index: usize, ctx: &egui::Context) {
+        if index >= self.documents.len() {
+            return;
+        }
+        self.forget_recovery(index);
+        if self.documents.len() == 1 {
+            self.documents = vec![Document::untitled(1)];
+            self.active_document = 0;
+            self.load_active_document(ctx);
+            return;
+        }
+        self.documents.remove(index);
+        if index == self.active_document {
+            // 前台文档的数据还在 App 字段上，直接用新的前台文档覆盖。
+            self.active_document = index.min(self.documents.len() - 1);
+            self.load_active_document(ctx);
+        } else if index < self.active_document {
+            self.active_document -= 1;
+        }
+    }
+
+    /// 请求关闭文档：有未保存修改时先弹出询问框。
+    fn request_close_document(&mut self, index: usize, ctx: &egui::Context) {
+        if self
+            .documents
+            .get(index)
+            .is_some_and(|document| document.modified)
+        {
+            self.closing_document = Some(index);
+        } else {
+            self.close_document(index, ctx);
+        }
+    }
+
+    /// 把第 `index` 个文档写入它的文件，成功时返回 `true`。
+    fn save_document(&mut self, index: usize) -> bool {
+        let graph = if index == self.active_document {
+            self.current_graph()
+        } else {
+            match self.documents.get(index) {
+                Some(document) => document.graph.clone(),
+                None => return false,
+            }
+        };
+        let Some(document) = self.documents.get_mut(index) else {
+            return false;
+        };
+        let result = ron::ser::to_string_pretty(&graph, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| std::fs::write(&document.path, text).map_err(|err| err.to_string()));
+        let saved = result.is_ok();
+        self.status_message = Some(match result {
+            Ok(()) => {
+                document.modified = false;
+                document.name = file_stem(&document.path);
+                format!("Saved {}", document.path)
+            }
+            Err(err) => {
+                log::warn!("保存文档失败 '{}'：{err}", document.path);
+                format!("Saving failed: {err}")
+            }
+        });
+        if saved {
+            self.forget_recovery(index);
+        }
+        saved
+    }
+
+    /// 在新标签页里打开 `open_path` 指向的文档文件。
+    fn open_document(&mut self, ctx: &egui::Context) {
+        let path = self.open_path.clone();
+        match Document::read(&path) {
+            Ok(document) => {
+                self.push_document(document, ctx);
+                self.status_message = Some(format!("Opened {path}"));
+            }
+            Err(err) => {
+                log::warn!("打开文档失败 '{path}'：{err}");
+                self.status_message = Some(format!("Opening failed: {err}"));
+            }
+        }
+    }
+
+    /// 画布上方的标签栏：点击标签切换，× 关闭，+ 新建。
+    pub(super) fn document_tabs(&mut self, ui: &mut egui::Ui) {
+        let mut switch_to = None;
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (index, document) in self.documents.iter().enumerate() {
+                let marker = if document.modified { " ●" } else { "" };
+                let text = format!("{}{marker}", document.name);
+                if ui
+                    .selectable_label(index == self.active_document, text)
+                    .on_hover_text(&document.path)
+                    .clicked()
+                {
+                    switch_to = Some(index);
+                }
+                if ui.small_button("×").on_hover_text("Close").clicked() {
+                    close = Some(index);
+                }
+                ui.separator();
+            }
+            if ui.button("+").on_hover_text("New document").clicked() {
+                self.new_document(ui.ctx());
+            }
+        });
+        if let Some(index) = switch_to {
+            self.switch_document(index, ui.ctx());
+        }
+        if let Some(index) = close {
+            self.request_close_document(index, ui.ctx());
+        }
+    }
+
+    /// 侧边栏里的文件操作：保存前台文档、在新标签页里打开文件。
+    pub(super) fn document_file_contents(&mut self, ui: &mut egui::Ui) {
+        ui.label("Document file");
+        if let Some(document) = self.active_document_mut() {
+            ui.text_edit_singleline(&mut document.path);
+        }
+        if ui.button("Save").clicked() {
+            self.save_document(self.active_document);
+        }
+        ui.label("Open file");
+        ui.text_edit_singleline(&mut self.open_path);
+        if ui.button("Open in New Tab").clicked() {
+            self.open_document(ui.ctx());
+        }
+    }
+
+    /// 关闭有未保存修改的文档前的询问框。
+    pub(super) fn show_close_prompt(&mut self, ctx: &egui::Context) {
+        let Some(index) = self.closing_document else {
+            return;
+        };
+        let Some(name) = self
+            .documents
+            .get(index)
+            .map(|document| document.name.clone())
+        else {
+            self.closing_document = None;
+            return;
+        };
+
+        let mut choice = None;
+        egui::Modal::new(egui::Id::new("close_document_prompt")).show(ctx, |ui| {
+            ui.label(format!("Save changes to \"{name}\" before closing?"));
+            ui.horizontal(|ui| {
+                for option in [CloseChoice::Save, CloseChoice::Discard, CloseChoice::Cancel] {
+                    if ui.button(option.label()).clicked() {
+                        choice = Some(option);
+                    }
+                }
+            });
+        });
+        // 保存失败时保留询问框，状态栏里会显示原因。
+        let close = match choice {
+            Some(CloseChoice::Save) => self.save_document(index),
+            Some(CloseChoice::Discard) => true,
+            Some(CloseChoice::Cancel) => {
+                self.closing_document = None;
+                false
+            }
+            None => false,
+        };
+        if close {
+            self.closing_document = None;
+            self.close_document(index, ctx);
+        }
+    }
+}
+
+/// 关闭询问框里的选项。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CloseChoice {
+    Save,
+    Discard,
+    Cancel,
+}
+
+impl CloseChoice {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Save => "Save",
+            Self::Discard => "Don't Save",
+            Self::Cancel => "Cancel",
+        }
+    }
+}
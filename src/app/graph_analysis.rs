@@ -0,0 +1,558 @@
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+
+use egui::Rect;
+
+use super::context_menu::ContextAction;
+use super::{LinkKey, Node, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 图分析
+//
+// 侧边栏 `Analysis` 页对整张图（包括子图内部的节点，不含子图节点本身）计算：
+// - 每个节点的入度 / 出度
+// - 连通分量（不看连线方向）
+// - 源点（只有出边）、汇点（只有入边）、孤立节点
+// - 强连通分量（即环路）
+// - 关键路径：节点数最多、或按节点耗时之和最长的路径，只在无环时计算
+// 列出的每一项都可以点击，选中对应节点（必要时先进入所在的子图）。
+// 结果按图结构的指纹缓存，只在节点、连线或计算方式变化后重新分析；
+// 强连通分量用 Tarjan 算法，各项统计共用同一份邻接表。
+// ============================================================
+
+/// 一个节点的入度和出度。
+#[derive(Debug)]
+struct NodeDegree {
+    id: usize,
+    in_degree: usize,
+    out_degree: usize,
+}
+
+/// 关键路径及其长度（节点数或耗时之和）。
+#[derive(Debug)]
+struct CriticalPath {
+    nodes: Vec<usize>,
+    length: f32,
+}
+
+/// 一次分析的结果。
+#[derive(Debug, Default)]
+struct GraphAnalysis {
+    node_count: usize,
+    link_count: usize,
+    /// 按度数从大到小排序。
+    degrees: Vec<NodeDegree>,
+    /// 连通分量，从大到小排序。
+    components: Vec<Vec<usize>>,
+    sources: Vec<usize>,
+    sinks: Vec<usize>,
+    isolated: Vec<usize>,
+    /// 节点数大于 1 的强连通分量。
+    cycles: Vec<Vec<usize>>,
+    /// 图中有环或没有节点时为 `None`。
+    critical_path: Option<CriticalPath>,
+}
+
+/// 最近一次分析的结果；图结构没变时 `Analysis` 页直接复用。
+#[derive(Debug, Default)]
+pub(super) struct AnalysisCache {
+    /// 分析时图结构的指纹；和当前不同说明需要重新分析。
+    fingerprint: Option<u64>,
+    analysis: GraphAnalysis,
+}
+
+/// Tarjan 算法求强连通分量的状态。
+struct Tarjan<'a> {
+    successors: &'a BTreeMap<usize, Vec<usize>>,
+    /// 每个访问过的节点：访问序号，以及它能回溯到的最小序号。
+    order: BTreeMap<usize, (usize, usize)>,
+    stack: Vec<usize>,
+    on_stack: BTreeSet<usize>,
+    components: Vec<Vec<usize>>,
+}
+
+impl Tarjan<'_> {
+    fn open(&mut self, id: usize) {
+        let index = self.order.len();
+        self.order.insert(id, (index, index));
+        self.stack.push(id);
+        self.on_stack.insert(id);
+    }
+
+    fn lower(&mut self, id: usize, value: usize) {
+        if let Some(entry) = self.order.get_mut(&id) {
+            entry.1 = entry.1.min(value);
+        }
+    }
+
+    /// 从 `root` 开始深度优先遍历（用显式栈，长链不会爆栈）。
+    fn visit(&mut self, root: usize) {
+        self.open(root);
+        // 每层：当前节点，以及下一个要看的后继的下标。
+        let mut calls = vec![(root, 0)];
+        while let Some(frame) = calls.last_mut() {
+            let id = frame.0;
+            let next = self
+                .successors
+                .get(&id)
+                .and_then(|successors| successors.get(frame.1))
+                .copied();
+            frame.1 += 1;
+            let Some(next) = next else {
+                // 后继都看完了：回到上一层，必要时收起一个分量。
+                calls.pop();
+                let (index, low) = self.order.get(&id).copied().unwrap_or_default();
+                if let Some(&(parent, _)) = calls.last() {
+                    self.lower(parent, low);
+                }
+                if index == low {
+                    self.close_component(id);
+                }
+                continue;
+            };
+            match self.order.get(&next) {
+                None => {
+                    self.open(next);
+                    calls.push((next, 0));
+                }
+                Some(&(index, _)) if self.on_stack.contains(&next) => self.lower(id, index),
+                Some(_) => {}
+            }
+        }
+    }
+
+    /// `root` 是一个强连通分量的根：把栈上它以上的节点弹出，组成一个分量。
+    fn close_component(&mut self, root: usize) {
+        let mut component = Vec::new();
+        while let Some(id) = self.stack.pop() {
+            self.on_stack.remove(&id);
+            component.push(id);
+            if id == root {
+                break;
+            }
+        }
+        if component.len() > 1 {
+            component.sort_unstable();
+            self.components.push(component);
+        }
+    }
+}
+
+/// `links` 里每个节点的后继。
+fn successors(links: &[LinkKey]) -> BTreeMap<usize, Vec<usize>> {
+    let mut successors: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for &(from, to) in links {
+        successors.entry(from).or_default().push(to);
+    }
+    successors
+}
+
+/// 节点数大于 1 的强连通分量，按分量里最小的节点 ID 排序。
+fn find_strongly_connected(
+    ids: &BTreeSet<usize>,
+    successors: &BTreeMap<usize, Vec<usize>>,
+) -> Vec<Vec<usize>> {
+    let mut tarjan = Tarjan {
+        successors,
+        order: BTreeMap::new(),
+        stack: Vec::new(),
+        on_stack: BTreeSet::new(),
+        components: Vec::new(),
+    };
+    for &id in ids {
+        if !tarjan.order.contains_key(&id) {
+            tarjan.visit(id);
+        }
+    }
+    let mut components = tarjan.components;
+    components.sort_unstable_by_key(|component| component.first().copied());
+    components
+}
+
+impl Node {
+    /// 关键路径里这个节点占的长度：转接点为 0；
+    /// 按耗时计算时取节点的耗时（未设置为 0），否则每个节点记 1。
+    fn path_weight(&self, use_durations: bool) -> f32 {
+        if self.kind == NodeKind::Reroute {
+            0.0
+        } else if use_durations {
+            self.duration.unwrap_or(0.0)
+        } else {
+            1.0
+        }
+    }
+}
+
+impl NodeGraphApp {
+    /// 影响分析结果的那部分图数据的指纹（不含位置、标题等）。
+    fn analysis_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for node in &self.nodes {
+            (node.id, node.kind.label(), node.duration.map(f32::to_bits)).hash(&mut hasher);
+        }
+        for connection in &self.connections {
+            connection.key().hash(&mut hasher);
+        }
+        self.critical_path_durations.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// 图结构有变化时重新分析。
+    fn update_analysis(&mut self) {
+        let fingerprint = self.analysis_fingerprint();
+        if self.analysis_cache.fingerprint == Some(fingerprint) {
+            return;
+        }
+        self.analysis_cache = AnalysisCache {
+            fingerprint: Some(fingerprint),
+            analysis: self.analyze_graph(),
+        };
+    }
+
+    /// 两端都在 `ids` 里的连线。
+    fn links_between(&self, ids: &BTreeSet<usize>) -> Vec<LinkKey> {
+        self.connections
+            .iter()
+            .map(|connection| connection.key())
+            .filter(|(from, to)| ids.contains(from) && ids.contains(to))
+            .collect()
+    }
+
+    /// 对整张图做一次分析。
+    fn analyze_graph(&self) -> GraphAnalysis {
+        let ids: BTreeSet<usize> = self
+            .nodes
+            .iter()
+            .filter(|node| node.kind != NodeKind::Subgraph)
+            .map(|node| node.id)
+            .collect();
+        let links = self.links_between(&ids);
+        let successors = successors(&links);
+
+        let mut counts: BTreeMap<usize, (usize, usize)> =
+            ids.iter().map(|&id| (id, (0, 0))).collect();
+        for &(from, to) in &links {
+            if let Some(count) = counts.get_mut(&from) {
+                count.1 += 1;
+            }
+            if let Some(count) = counts.get_mut(&to) {
+                count.0 += 1;
+            }
+        }
+        let mut degrees: Vec<NodeDegree> = counts
+            .into_iter()
+            .map(|(id, (in_degree, out_degree))| NodeDegree {
+                id,
+                in_degree,
+                out_degree,
+            })
+            .collect();
+        let pick = |test: fn(&NodeDegree) -> bool| -> Vec<usize> {
+            degrees
+                .iter()
+                .filter(|degree| test(degree))
+                .map(|degree| degree.id)
+                .collect()
+        };
+        let sources = pick(|degree| degree.in_degree == 0 && degree.out_degree > 0);
+        let sinks = pick(|degree| degree.out_degree == 0 && degree.in_degree > 0);
+        let isolated = pick(|degree| degree.in_degree == 0 && degree.out_degree == 0);
+        degrees.sort_by_key(|degree| std::cmp::Reverse(degree.in_degree + degree.out_degree));
+
+        let cycles = find_strongly_connected(&ids, &successors);
+        let critical_path = if cycles.is_empty() {
+            self.critical_path(&ids, &links, &successors)
+        } else {
+            None
+        };
+        GraphAnalysis {
+            node_count: ids.len(),
+            link_count: links.len(),
+            degrees,
+            components: Self::connected_components(&ids, &links),
+            sources,
+            sinks,
+            isolated,
+            cycles,
+            critical_path,
+        }
+    }
+
+    /// 不看连线方向的连通分量，从大到小排序。
+    fn connected_components(ids: &BTreeSet<usize>, links: &[LinkKey]) -> Vec<Vec<usize>> {
+        let mut neighbors: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for &(from, to) in links {
+            neighbors.entry(from).or_default().push(to);
+            neighbors.entry(to).or_default().push(from);
+        }
+        let mut seen = BTreeSet::new();
+        let mut components = Vec::new();
+        for &id in ids {
+            if !seen.insert(id) {
+                continue;
+            }
+            let mut component = vec![id];
+            let mut queue = VecDeque::from([id]);
+            while let Some(current) = queue.pop_front() {
+                for &next in neighbors.get(&current).into_iter().flatten() {
+                    if seen.insert(next) {
+                        component.push(next);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            component.sort_unstable();
+            components.push(component);
+        }
+        components.sort_by_key(|component| std::cmp::Reverse(component.len()));
+        components
+    }
+
+    /// `ids` 之间节点数大于 1 的强连通分量：互相都能到达的节点归为一组。
+    pub(super) fn strongly_connected_components(&self, ids: &BTreeSet<usize>) -> Vec<Vec<usize>> {
+        find_strongly_connected(ids, &successors(&self.links_between(ids)))
+    }
+
+    /// 无环图里最长的路径（按拓扑序做动态规划）。
+    fn critical_path(
+        &self,
+        ids: &BTreeSet<usize>,
+        links: &[LinkKey],
+        successors: &BTreeMap<usize, Vec<usize>>,
+    ) -> Option<CriticalPath> {
+        let weight = |id: usize| {
+            self.node_by_id(id)
+                .map_or(0.0, |node| node.path_weight(self.critical_path_durations))
+        };
+        let mut in_degree: BTreeMap<usize, usize> = ids.iter().map(|&id| (id, 0)).collect();
+        for (_, to) in links {
+            *in_degree.entry(*to).or_default() += 1;
+        }
+        let mut queue: VecDeque<usize> = in_degree
+            .iter()
+            .filter(|&(_, &degree)| degree == 0)
+            .map(|(&id, _)| id)
+            .collect();
+
+        // 每个节点：以它结尾的最长路径长度，以及这条路径上的前一个节点。
+        let mut best: BTreeMap<usize, (f32, Option<usize>)> = BTreeMap::new();
+        while let Some(id) = queue.pop_front() {
+            let length = best.entry(id).or_insert((weight(id), None)).0;
+            for &to in successors.get(&id).into_iter().flatten() {
+                let candidate = length + weight(to);
+                let entry = best.entry(to).or_insert((f32::NEG_INFINITY, None));
+                if candidate > entry.0 {
+                    *entry = (candidate, Some(id));
+                }
+                if let Some(degree) = in_degree.get_mut(&to) {
+                    *degree -= 1;
+                    if *degree == 0 {
+                        queue.push_back(to);
+                    }
+                }
+            }
+        }
+
+        let (&end, &(length, _)) = best.iter().max_by(|a, b| a.1.0.total_cmp(&b.1.0))?;
+        let mut nodes = vec![end];
+        while let Some(previous) = nodes
+            .last()
+            .and_then(|id| best.get(id))
+            .and_then(|&(_, previous)| previous)
+        {
+            nodes.push(previous);
+        }
+        nodes.reverse();
+        Some(CriticalPath { nodes, length })
+    }
+
+    /// 选中一组节点并平移视图：它们都不在当前层级之下时，先进入第一个节点所在的层级；
+    /// 更深层子图里的节点由当前层的子图节点代表。
+    pub(super) fn select_nodes(&mut self, ids: &[usize], canvas_rect: Rect) {
+        if ids
+            .iter()
+            .all(|&id| self.scope_representative(id).is_none())
+            && let Some(parent) = ids
+                .first()
+                .and_then(|&id| self.node_by_id(id))
+                .map(|node| node.parent)
+        {
+            self.open_subgraph(parent, canvas_rect);
+        }
+        self.selected_nodes = ids
+            .iter()
+            .filter_map(|&id| self.scope_representative(id))
+            .collect();
+        self.selected_link = None;
+        if let Some(bounds) = self
+            .visible_nodes()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .map(Node::world_rect)
+            .reduce(|a, b| a.union(b))
+        {
+            self.center_view_on(bounds, canvas_rect);
+        }
+    }
+
+    /// 可点击的一组节点：点击后选中它们。
+    fn select_link(&mut self, ui: &mut egui::Ui, text: String, ids: &[usize]) {
+        if ui.link(text).clicked() {
+            self.pending_action = Some(ContextAction::SelectNodes(ids.to_vec()));
+        }
+    }
+
+    /// 一行可点击的节点名，前面是一个选中全部的链接。
+    fn node_list(&mut self, ui: &mut egui::Ui, label: &str, ids: &[usize]) {
+        ui.horizontal_wrapped(|ui| {
+            if ids.is_empty() {
+                ui.weak(format!("{label}: none"));
+                return;
+            }
+            self.select_link(ui, format!("{label} ({})", ids.len()), ids);
+            ui.label(":");
+            for &id in ids {
+                self.select_link(ui, self.node_name(id), &[id]);
+            }
+        });
+    }
+
+    /// 侧边栏“分析”页。
+    pub(super) fn analysis_panel_contents(&mut self, ui: &mut egui::Ui) {
+        self.update_analysis();
+        // 绘制时要可变借用 `self`，先把结果拿出来，画完放回去。
+        let analysis = std::mem::take(&mut self.analysis_cache.analysis);
+        ui.weak("Whole graph, including nodes inside subgraphs");
+        ui.label(format!(
+            "Nodes: {}  Links: {}",
+            analysis.node_count, analysis.link_count
+        ));
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.node_list(ui, "Sources", &analysis.sources);
+            self.node_list(ui, "Sinks", &analysis.sinks);
+            self.node_list(ui, "Isolated", &analysis.isolated);
+
+            ui.collapsing(format!("Degrees ({})", analysis.degrees.len()), |ui| {
+                egui::Grid::new("analysis_degrees")
+                    .num_columns(3)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Node");
+                        ui.strong("In");
+                        ui.strong("Out");
+                        ui.end_row();
+                        for degree in &analysis.degrees {
+                            self.select_link(ui, self.node_name(degree.id), &[degree.id]);
+                            ui.label(degree.in_degree.to_string());
+                            ui.label(degree.out_degree.to_string());
+                            ui.end_row();
+                        }
+                    });
+            });
+
+            ui.collapsing(
+                format!("Components ({})", analysis.components.len()),
+                |ui| {
+                    for (index, component) in analysis.components.iter().enumerate() {
+                        let text = format!("#{}: {} nodes", index + 1, component.len());
+                        self.select_link(ui, text, component);
+                    }
+                },
+            );
+
+            ui.collapsing(
+                format!("Strongly connected ({})", analysis.cycles.len()),
+                |ui| {
+                    if analysis.cycles.is_empty() {
+                        ui.weak("No cycles");
+                    }
+                    for cycle in &analysis.cycles {
+                        let names: Vec<String> =
+                            cycle.iter().map(|&id| self.node_name(id)).collect();
+                        self.select_link(ui, names.join(", "), cycle);
+                    }
+                },
+            );
+
+            ui.separator();
+            self.critical_path_contents(ui, &analysis);
+        });
+        self.analysis_cache.analysis = analysis;
+    }
+
+    /// 关键路径：有环时提示先看强连通分量。
+    fn critical_path_contents(&mut self, ui: &mut egui::Ui, analysis: &GraphAnalysis) {
+        ui.strong("Critical path");
+        ui.checkbox(&mut self.critical_path_durations, "Use node durations")
+            .on_hover_text("Sum node durations instead of counting nodes");
+        if !analysis.cycles.is_empty() {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "The graph has cycles, so there is no critical path",
+            );
+            return;
+        }
+        let Some(path) = &analysis.critical_path else {
+            ui.weak("No nodes");
+            return;
+        };
+        ui.label(format!("Length: {}", path.length));
+        let names: Vec<String> = path.nodes.iter().map(|&id| self.node_name(id)).collect();
+        ui.horizontal_wrapped(|ui| {
+            self.select_link(ui, names.join(" → "), &path.nodes);
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+
+    use super::{find_strongly_connected, successors};
+    use crate::app::NodeGraphApp;
+
+    #[test]
+    fn tarjan_finds_each_cycle_once() {
+        // 1 <-> 2 -> 3 -> 4 -> 5 -> 3，6 单独一个。
+        let ids: BTreeSet<usize> = (1..=6).collect();
+        let links = [(1, 2), (2, 1), (2, 3), (3, 4), (4, 5), (5, 3)];
+        assert_eq!(
+            find_strongly_connected(&ids, &successors(&links)),
+            vec![vec![1, 2], vec![3, 4, 5]],
+            "应该找到两个环，单个节点不算"
+        );
+        assert!(
+            find_strongly_connected(&ids, &successors(&[(1, 2), (2, 3)])).is_empty(),
+            "无环图没有强连通分量"
+        );
+    }
+
+    #[test]
+    fn analysis_counts_degrees_and_critical_path() {
+        let mut app = NodeGraphApp::empty();
+        let [a, b, c, d] = ["a", "b", "c", "d"].map(|name| app.add_graph_node(name));
+        for (from, to) in [(a, b), (b, c), (a, c)] {
+            assert!(app.add_graph_link(from, to), "连线应该能添加");
+        }
+        let analysis = app.analyze_graph();
+        let degree = |id: usize| {
+            analysis
+                .degrees
+                .iter()
+                .find(|degree| degree.id == id)
+                .map(|degree| (degree.in_degree, degree.out_degree))
+        };
+        assert_eq!(degree(a), Some((0, 2)), "a 有两条出边");
+        assert_eq!(degree(c), Some((2, 0)), "c 有两条入边");
+        assert_eq!(
+            (analysis.sources, analysis.sinks, analysis.isolated),
+            (vec![a], vec![c], vec![d]),
+            "源点、汇点、孤立节点"
+        );
+        assert_eq!(
+            analysis.critical_path.map(|path| path.nodes),
+            Some(vec![a, b, c]),
+            "关键路径应该经过 b"
+        );
+    }
+}
@@ -0,0 +1,109 @@
+use egui::Pos2;
+
+use super::documents::{Document, DocumentGraph};
+use super::link_style::LinkStyle;
+use super::undo::UndoHistory;
+use super::{Node, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 不带界面的图操作
+//
+// 给宿主程序和测试用：不打开窗口也能建图、读文档，再调用路径查询等公开方法。
+// - `empty` / `from_document_file` 创建 App
+// - `add_graph_node` / `add_graph_link` 建图，规则和画布上一样
+// - `set_link_label` / `set_link_width` 设置路径加权用到的连线属性
+// - 只读访问节点和连线
+// 这里添加的节点都在顶层，不进子图。
+// ============================================================
+
+// 添加的节点按顺序从左到右排开时的间距。
+const NODE_SPACING: f32 = 240.0;
+
+impl NodeGraphApp {
+    /// 不含演示节点的空图。
+    pub fn empty() -> Self {
+        let mut app = Self::default();
+        app.apply_graph(DocumentGraph::default());
+        app.clear_selection_and_history();
+        app
+    }
+
+    /// 读取侧边栏 “Save” 保存的文档文件，得到只打开了这一个文档的 App。
+    ///
+    /// # Errors
+    /// 文件读不到或内容不是文档格式时，返回错误说明。
+    pub fn from_document_file(path: &str) -> Result<Self, String> {
+        let mut document = Document::read(path)?;
+        let graph = std::mem::take(&mut document.graph);
+        let mut app = Self {
+            documents: vec![document],
+            active_document: 0,
+            ..Self::default()
+        };
+        app.apply_graph(graph);
+        app.clear_selection_and_history();
+        Ok(app)
+    }
+
+    /// 在顶层添加一个处理节点（有输入、输出端口），返回新节点的 ID。
+    pub fn add_graph_node(&mut self, title: &str) -> usize {
+        let id = self.next_node_id;
+        self.next_node_id += 1;
+        let column = self
+            .nodes
+            .iter()
+            .filter(|node| node.parent.is_none())
+            .count();
+        let position = Pos2::new(column as f32 * NODE_SPACING, 0.0);
+        self.nodes.push(Node {
+            title: title.to_owned(),
+            ..Node::new(id, NodeKind::Process, position)
+        });
+        id
+    }
+
+    /// 从 `from_node_id` 连到 `to_node_id`；节点不存在、自连或连线已存在时不添加，返回 `false`。
+    pub fn add_graph_link(&mut self, from_node_id: usize, to_node_id: usize) -> bool {
+        self.try_connect(from_node_id, to_node_id)
+    }
+
+    /// 设置连线标签（`PathWeight::LabelNumber` 读它）；连线不存在时返回 `false`。
+    pub fn set_link_label(&mut self, from_node_id: usize, to_node_id: usize, label: &str) -> bool {
+        self.connection_by_key_mut((from_node_id, to_node_id))
+            .map(|connection| connection.label = label.to_owned())
+            .is_some()
+    }
+
+    /// 设置连线线宽（`PathWeight::LineWidth` 读它），限制在检查器允许的范围内；连线不存在时返回 `false`。
+    pub fn set_link_width(&mut self, from_node_id: usize, to_node_id: usize, width: f32) -> bool {
+        self.connection_by_key_mut((from_node_id, to_node_id))
+            .map(|connection| {
+                connection.style.width = width.clamp(LinkStyle::MIN_WIDTH, LinkStyle::MAX_WIDTH);
+            })
+            .is_some()
+    }
+
+    /// 全部节点的 ID（含子图里的节点）。
+    pub fn node_ids(&self) -> Vec<usize> {
+        self.nodes.iter().map(|node| node.id).collect()
+    }
+
+    /// 节点标题；节点不存在时返回 `None`。
+    pub fn node_title(&self, node_id: usize) -> Option<&str> {
+        self.node_by_id(node_id).map(|node| node.title.as_str())
+    }
+
+    /// 全部连线（起点节点 ID，终点节点 ID）。
+    pub fn link_keys(&self) -> Vec<(usize, usize)> {
+        self.connections
+            .iter()
+            .map(|connection| connection.key())
+            .collect()
+    }
+
+    fn clear_selection_and_history(&mut self) {
+        self.selected_nodes.clear();
+        self.selected_link = None;
+        self.undo_history = UndoHistory::default();
+    }
+}
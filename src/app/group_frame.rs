@@ -0,0 +1,482 @@
+use egui::{
+    Color32, CornerRadius, CursorIcon, FontId, PointerButton, Rect, Sense, Stroke, StrokeKind, Vec2,
+};
+
+use super::NodeGraphApp;
+use super::context_menu::{ContextAction, HEADER_COLOR_PRESETS};
+
+// ============================================================
+// 分组框（背景框）
+//
+// 画在节点下面的彩色矩形，带标题，用来把相关节点圈在一起：
+// - 框住的节点 = 中心点落在框内的节点，拖动标题栏时一起移动
+// - 右下角可以拖动改变大小
+// - 折叠后只剩标题栏，框内节点隐藏，与外部相连的连线改接到标题栏两侧
+// 分组框属于某一子图层级，只在该层显示；随文档保存，也会写入 SVG 导出。
+// ============================================================
+
+// 标题栏高度。
+pub(super) const FRAME_HEADER_HEIGHT: f32 = 26.0;
+// 根据选中节点创建分组框时，四周留出的空白。
+const FRAME_PADDING: f32 = 24.0;
+const FRAME_MIN_SIZE: Vec2 = Vec2::new(160.0, 80.0);
+// 右下角缩放手柄的边长。
+const FRAME_RESIZE_HANDLE: f32 = 14.0;
+// 框体填充的不透明度（标题栏用原色）。
+pub(super) const FRAME_FILL_ALPHA: u8 = 40;
+
+/// 一个分组框（世界坐标）。
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(super) struct GroupFrame {
+    pub(super) id: usize,
+    pub(super) title: String,
+    pub(super) color: Color32,
+    /// 展开时的完整矩形（折叠后保持不变，展开时恢复）。
+    pub(super) rect: Rect,
+    pub(super) collapsed: bool,
+    /// 折叠时被隐藏的节点；展开时清空，成员改为按位置计算。
+    #[serde(default)]
+    members: Vec<usize>,
+    /// 分组框所在的子图层级；`None` 表示顶层。
+    #[serde(default)]
+    pub(super) parent: Option<usize>,
+}
+
+impl GroupFrame {
+    pub(super) fn header_rect(&self) -> Rect {
+        Rect::from_min_size(
+            self.rect.min,
+            Vec2::new(self.rect.width(), FRAME_HEADER_HEIGHT),
+        )
+    }
+
+    /// 当前可见的矩形：折叠时只有标题栏。
+    pub(super) fn visible_rect(&self) -> Rect {
+        if self.collapsed {
+            self.header_rect()
+        } else {
+            self.rect
+        }
+    }
+
+    /// 复制一份分组框（模板保存 / 插入时使用）：换成新 ID 和层级，平移 `offset`，
+    /// 折叠时记录的成员按 `node_id` 换成新的节点 ID，换不了的去掉。
+    pub(super) fn copied(
+        &self,
+        id: usize,
+        parent: Option<usize>,
+        offset: Vec2,
+        node_id: impl Fn(usize) -> Option<usize>,
+    ) -> Self {
+        Self {
+            id,
+            parent,
+            rect: self.rect.translate(offset),
+            members: self.members.iter().filter_map(|&id| node_id(id)).collect(),
+            ..self.clone()
+        }
+    }
+
+    /// 节点被删除后，从折叠时隐藏的成员里去掉它。
+    pub(super) fn remove_member(&mut self, node_id: usize) {
+        self.members.retain(|&id| id != node_id);
+    }
+
+    /// 折叠时框内节点的数量说明，例如 `Title (3)`。
+    pub(super) fn display_title(&self) -> String {
+        if self.collapsed {
+            format!("{} ({})", self.title, self.members.len())
+        } else {
+            self.title.clone()
+        }
+    }
+}
+
+/// 分组框相关的菜单操作。
+#[derive(Clone, Copy, Debug)]
+pub(super) enum FrameAction {
+    /// 用选中节点的包围盒创建分组框。
+    CreateFromSelection,
+    Rename(usize),
+    SetColor {
+        frame_id: usize,
+        color: Color32,
+    },
+    SetCollapsed {
+        frame_id: usize,
+        collapsed: bool,
+    },
+    /// 选中框内的全部节点。
+    SelectNodes(usize),
+    /// 删除分组框（框内节点保留）。
+    Delete(usize),
+}
+
+impl FrameAction {
+    pub(super) fn is_undoable(self) -> bool {
+        !matches!(self, Self::Rename(_) | Self::SelectNodes(_))
+    }
+}
+
+/// 正在拖动标题栏的分组框，以及开始拖动时框住的节点。
+pub(super) struct FrameDrag {
+    frame_id: usize,
+    nodes: Vec<usize>,
+}
+
+impl NodeGraphApp {
+    fn frame_by_id_mut(&mut self, frame_id: usize) -> Option<&mut GroupFrame> {
+        self.frames.iter_mut().find(|frame| frame.id == frame_id)
+    }
+
+    /// 当前子图层级中的分组框。
+    pub(super) fn frames_in_scope(&self) -> impl Iterator<Item = &GroupFrame> {
+        self.frames
+            .iter()
+            .filter(|frame| frame.parent == self.current_subgraph)
+    }
+
+    /// 分组框框住的节点：折叠时是记录下来的成员，展开时按节点中心是否在框内计算。
+    pub(super) fn frame_members(&self, frame: &GroupFrame) -> Vec<usize> {
+        if frame.collapsed {
+            return frame.members.clone();
+        }
+        self.nodes
+            .iter()
+            .filter(|node| node.parent == frame.parent)
+            .filter(|node| frame.rect.contains(node.world_rect().center()))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// 节点所在的已折叠分组框。
+    pub(super) fn collapsed_frame_of(&self, node_id: usize) -> Option<&GroupFrame> {
+        self.frames
+            .iter()
+            .find(|frame| frame.collapsed && frame.members.contains(&node_id))
+    }
+
+    pub(super) fn apply_frame_action(&mut self, action: FrameAction) {
+        match action {
+            FrameAction::CreateFromSelection => self.create_frame_from_selection(),
+            FrameAction::Rename(frame_id) => self.renaming_frame = Some(frame_id),
+            FrameAction::SetColor { frame_id, color } => {
+                if let Some(frame) = self.frame_by_id_mut(frame_id) {
+                    frame.color = color;
+                }
+            }
+            FrameAction::SetCollapsed {
+                frame_id,
+                collapsed,
+            } => self.set_frame_collapsed(frame_id, collapsed),
+            FrameAction::SelectNodes(frame_id) => {
+                if let Some(frame) = self.frames.iter().find(|frame| frame.id == frame_id) {
+                    self.selected_nodes = self.frame_members(frame).into_iter().collect();
+                }
+            }
+            FrameAction::Delete(frame_id) => {
+                self.set_frame_collapsed(frame_id, false);
+                self.frames.retain(|frame| frame.id != frame_id);
+            }
+        }
+    }
+
+    fn create_frame_from_selection(&mut self) {
+        let Some(bounds) = self
+            .nodes
+            .iter()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .map(|node| node.world_rect())
+            .reduce(|a, b| a.union(b))
+        else {
+            return;
+        };
+
+        let mut rect = bounds.expand(FRAME_PADDING);
+        rect.min.y -= FRAME_HEADER_HEIGHT;
+        let id = self.next_frame_id;
+        self.next_frame_id += 1;
+        self.frames.push(GroupFrame {
+            id,
+            title: format!("Frame {id}"),
+            color: self.theme.frame_default,
+            rect,
+            collapsed: false,
+            members: Vec::new(),
+            parent: self.current_subgraph,
+        });
+        self.renaming_frame = Some(id);
+    }
+
+    /// 折叠时记录并隐藏框内节点（同时取消它们的选中），展开时恢复。
+    fn set_frame_collapsed(&mut self, frame_id: usize, collapsed: bool) {
+        let Some(frame) = self.frames.iter().find(|frame| frame.id == frame_id) else {
+            return;
+        };
+        if frame.collapsed == collapsed {
+            return;
+        }
+
+        let members = if collapsed {
+            self.frame_members(frame)
+        } else {
+            Vec::new()
+        };
+        for id in &members {
+            self.selected_nodes.remove(id);
+        }
+        if let Some(frame) = self.frame_by_id_mut(frame_id) {
+            frame.collapsed = collapsed;
+            frame.members = members;
+        }
+    }
+
+    /// 绘制所有分组框并处理交互。
+    ///
+    /// 需要在画布响应之后、连线和节点之前调用：分组框在最底层，只有标题栏和缩放手柄可以交互，
+    /// 在框体空白处拖动仍然是平移画布。
+    pub(super) fn draw_frames(&mut self, ui: &mut egui::Ui) {
+        let mut action = None;
+
+        for index in 0..self.frames.len() {
+            let Some(frame) = self
+                .frames
+                .get(index)
+                .filter(|frame| frame.parent == self.current_subgraph)
+            else {
+                continue;
+            };
+            let frame_id = frame.id;
+            let rect = frame.visible_rect().translate(self.pan_offset);
+            let header_rect = frame.header_rect().translate(self.pan_offset);
+            let color = frame.color;
+            let collapsed = frame.collapsed;
+
+            Self::paint_frame(ui, rect, header_rect, color, collapsed);
+
+            // 标题栏左侧的折叠按钮。
+            let toggle_rect =
+                Rect::from_min_size(header_rect.min, Vec2::splat(FRAME_HEADER_HEIGHT));
+            let toggle = ui
+                .interact(
+                    toggle_rect,
+                    ui.make_persistent_id(("frame_toggle", frame_id)),
+                    Sense::click(),
+                )
+                .on_hover_cursor(CursorIcon::PointingHand);
+            ui.painter().text(
+                toggle_rect.center(),
+                egui::Align2::CENTER_CENTER,
+                if collapsed { "▶" } else { "▼" },
+                FontId::proportional(11.0),
+                self.theme.frame_title_text,
+            );
+            if toggle.clicked() {
+                action = Some(ContextAction::Frame(FrameAction::SetCollapsed {
+                    frame_id,
+                    collapsed: !collapsed,
+                }));
+            }
+
+            let title_rect = header_rect.with_min_x(toggle_rect.right());
+            let header = ui
+                .interact(
+                    title_rect,
+                    ui.make_persistent_id(("frame_header", frame_id)),
+                    Sense::click_and_drag(),
+                )
+                .on_hover_cursor(CursorIcon::Grab);
+            self.draw_frame_title(ui, index, title_rect);
+            if header.double_clicked() {
+                self.renaming_frame = Some(frame_id);
+            }
+            self.drag_frame(ui, frame_id, &header);
+            header.context_menu(|ui| {
+                if let Some(chosen) = self.frame_menu_contents(ui, frame_id) {
+                    action = Some(chosen);
+                }
+            });
+
+            if !collapsed {
+                self.resize_frame(ui, frame_id, rect);
+            }
+        }
+
+        if action.is_some() {
+            self.pending_action = action;
+        }
+    }
+
+    /// 框体半透明填充 + 描边，标题栏用原色。
+    fn paint_frame(ui: &egui::Ui, rect: Rect, header_rect: Rect, color: Color32, collapsed: bool) {
+        let fill =
+            Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), FRAME_FILL_ALPHA);
+        let painter = ui.painter();
+        if !collapsed {
+            painter.rect_filled(rect, CornerRadius::same(6), fill);
+        }
+        painter.rect_stroke(
+            rect,
+            CornerRadius::same(6),
+            Stroke::new(1.0, color),
+            StrokeKind::Inside,
+        );
+        painter.rect_filled(
+            header_rect,
+            if collapsed {
+                CornerRadius::same(6)
+            } else {
+                CornerRadius {
+                    nw: 6,
+                    ne: 6,
+                    sw: 0,
+                    se: 0,
+                }
+            },
+            color,
+        );
+    }
+
+    /// 标题：正在重命名时显示输入框，否则直接绘制文字。
+    fn draw_frame_title(&mut self, ui: &mut egui::Ui, index: usize, title_rect: Rect) {
+        let renaming = self.renaming_frame;
+        let text_color = self.theme.frame_title_text;
+        let Some(frame) = self.frames.get_mut(index) else {
+            return;
+        };
+
+        if renaming != Some(frame.id) {
+            ui.painter().text(
+                title_rect.left_center(),
+                egui::Align2::LEFT_CENTER,
+                frame.display_title(),
+                FontId::proportional(13.0),
+                text_color,
+            );
+            return;
+        }
+
+        let response = ui.put(
+            title_rect.shrink2(Vec2::new(0.0, 3.0)),
+            egui::TextEdit::singleline(&mut frame.title)
+                .font(FontId::proportional(13.0))
+                .desired_width(f32::INFINITY),
+        );
+        let title = frame.title.clone();
+        self.track_text_edit(&response, &title);
+        if response.lost_focus() {
+            self.renaming_frame = None;
+        } else if !response.has_focus() {
+            response.request_focus();
+        }
+    }
+
+    /// 拖动标题栏：分组框和开始拖动时框住的节点一起移动，整次拖动记为一步撤销。
+    fn drag_frame(&mut self, ui: &egui::Ui, frame_id: usize, header: &egui::Response) {
+        if header.drag_started_by(PointerButton::Primary) {
+            self.record_undo();
+            let nodes = self
+                .frames
+                .iter()
+                .find(|frame| frame.id == frame_id)
+                .map(|frame| self.frame_members(frame))
+                .unwrap_or_default();
+            self.frame_drag = Some(FrameDrag { frame_id, nodes });
+        }
+        if header.drag_stopped() {
+            self.frame_drag = None;
+        }
+
+        let Some(drag) = self
+            .frame_drag
+            .as_ref()
+            .filter(|drag| drag.frame_id == frame_id && header.dragged())
+        else {
+            return;
+        };
+        let motion = header.drag_motion();
+        ui.ctx().set_cursor_icon(CursorIcon::Grabbing);
+        for node in &mut self.nodes {
+            if drag.nodes.contains(&node.id) {
+                node.position += motion;
+            }
+        }
+        if let Some(frame) = self.frames.iter_mut().find(|frame| frame.id == frame_id) {
+            frame.rect = frame.rect.translate(motion);
+        }
+    }
+
+    /// 右下角缩放手柄。
+    fn resize_frame(&mut self, ui: &egui::Ui, frame_id: usize, rect: Rect) {
+        let handle_rect = Rect::from_min_max(rect.max - Vec2::splat(FRAME_RESIZE_HANDLE), rect.max);
+        let response = ui
+            .interact(
+                handle_rect,
+                ui.make_persistent_id(("frame_resize", frame_id)),
+                Sense::drag(),
+            )
+            .on_hover_cursor(CursorIcon::ResizeNwSe);
+
+        // 小三角提示可以拖动。
+        ui.painter().add(egui::Shape::convex_polygon(
+            vec![
+                handle_rect.right_top(),
+                handle_rect.right_bottom(),
+                handle_rect.left_bottom(),
+            ],
+            self.theme.frame_handle,
+            Stroke::NONE,
+        ));
+
+        if response.drag_started() {
+            self.record_undo();
+        }
+        if response.dragged()
+            && let Some(frame) = self.frame_by_id_mut(frame_id)
+        {
+            frame.rect.max += response.drag_delta();
+            frame.rect.max = frame.rect.max.max(frame.rect.min + FRAME_MIN_SIZE);
+        }
+    }
+
+    /// 分组框标题栏的右键菜单。
+    fn frame_menu_contents(&self, ui: &mut egui::Ui, frame_id: usize) -> Option<ContextAction> {
+        let frame = self.frames.iter().find(|frame| frame.id == frame_id)?;
+        let mut action = ui
+            .button("Rename")
+            .clicked()
+            .then_some(FrameAction::Rename(frame_id));
+        ui.menu_button("Color", |ui| {
+            ui.horizontal(|ui| {
+                for color in HEADER_COLOR_PRESETS {
+                    if Self::color_swatch(ui, &self.theme, color).clicked() {
+                        action = Some(FrameAction::SetColor { frame_id, color });
+                    }
+                }
+            });
+        });
+        let collapse_label = if frame.collapsed {
+            "Expand"
+        } else {
+            "Collapse"
+        };
+        if ui.button(collapse_label).clicked() {
+            action = Some(FrameAction::SetCollapsed {
+                frame_id,
+                collapsed: !frame.collapsed,
+            });
+        }
+        if ui
+            .add_enabled(!frame.collapsed, egui::Button::new("Select Nodes"))
+            .clicked()
+        {
+            action = Some(FrameAction::SelectNodes(frame_id));
+        }
+        ui.separator();
+        if ui.button("Delete Frame").clicked() {
+            action = Some(FrameAction::Delete(frame_id));
+        }
+
+        action.map(ContextAction::Frame)
+    }
+}
@@ -0,0 +1,585 @@
+use egui::{Color32, Vec2};
+
+use super::context_menu::{ContextAction, HEADER_COLOR_PRESETS};
+use super::link_routing::LinkRouting;
+use super::node_resize::NODE_MIN_SIZE;
+use super::node_tags::{ICON_PRESETS, same_tag};
+use super::subgraph::SubgraphAction;
+use super::{LinkKey, Node, NodeGraphApp, NodeKind, PortKind};
+
+// ============================================================
+// 属性检查器
+//
+// 侧边栏 `Inspector` 页，显示并编辑选中对象的全部属性：
+// - 单个节点：标题、正文、位置、尺寸、颜色、图标、标签、类型相关参数、端口列表
+// - 多个节点：只显示共有字段（尺寸、颜色、自动高度、标签），修改后应用到全部选中节点
+// - 单条连线（点击连线选中）：两端节点、标签、样式、走线方式
+// 每个控件的一次连续编辑（输入框获得焦点期间、拖动数值期间）记为一步撤销。
+// ============================================================
+
+// 节点尺寸输入框的上限，防止误输入过大的数值。
+const MAX_NODE_EXTENT: f32 = 4000.0;
+
+impl NodeGraphApp {
+    /// 检查器里的一次修改：同一控件的连续修改只记录一步撤销。
+    fn inspector_commit(&mut self, widget: egui::Id) {
+        if self.inspector_edit != Some(widget) {
+            self.record_undo();
+            self.inspector_edit = Some(widget);
+        }
+        self.inspector_error = None;
+    }
+
+    /// 修改选中的若干节点（先记录撤销）。
+    fn edit_nodes(&mut self, ids: &[usize], widget: egui::Id, edit: impl Fn(&mut Node)) {
+        self.inspector_commit(widget);
+        for node in &mut self.nodes {
+            if ids.contains(&node.id) {
+                edit(node);
+            }
+        }
+    }
+
+    /// 侧边栏“检查器”页。
+    pub(super) fn inspector_panel_contents(&mut self, ui: &mut egui::Ui) {
+        // 控件失去焦点、鼠标也松开后，下一次修改重新记录撤销。
+        if let Some(widget) = self.inspector_edit
+            && !ui.ctx().memory(|memory| memory.has_focus(widget))
+            && !ui.input(|i| i.pointer.any_down())
+        {
+            self.inspector_edit = None;
+        }
+
+        let selected: Vec<usize> = self
+            .visible_nodes()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .map(|node| node.id)
+            .collect();
+        egui::ScrollArea::vertical().show(ui, |ui| match selected.as_slice() {
+            [] => match self
+                .selected_link
+                .filter(|&link| self.connection_by_key(link).is_some())
+            {
+                Some(link) => self.link_inspector(ui, link),
+                None => {
+                    ui.weak("Select a node or click a link to inspect it");
+                }
+            },
+            [node_id] => self.node_inspector(ui, *node_id),
+            ids => self.multi_node_inspector(ui, ids),
+        });
+
+        if let Some(error) = &self.inspector_error {
+            ui.colored_label(self.theme.error, error);
+        }
+    }
+
+    /// 单个节点的属性。
+    fn node_inspector(&mut self, ui: &mut egui::Ui, node_id: usize) {
+        let Some(node) = self.node_by_id(node_id).cloned() else {
+            return;
+        };
+        let ids = [node_id];
+        let is_reroute = node.kind == NodeKind::Reroute;
+        ui.strong(format!("{} #{}", node.kind.label(), node.id));
+
+        egui::Grid::new("inspector_node")
+            .num_columns(2)
+            .show(ui, |ui| {
+                if !is_reroute {
+                    ui.label("Title");
+                    self.title_field(ui, &node);
+                    ui.end_row();
+                }
+
+                ui.label("Kind");
+                self.kind_field(ui, &node);
+                ui.end_row();
+
+                ui.label("Position");
+                ui.horizontal(|ui| {
+                    let mut position = node.position;
+                    let x = ui.add(egui::DragValue::new(&mut position.x).prefix("x "));
+                    let y = ui.add(egui::DragValue::new(&mut position.y).prefix("y "));
+                    for response in [x, y] {
+                        if response.changed() {
+                            self.edit_nodes(&ids, response.id, |node| node.position = position);
+                        }
+                    }
+                });
+                ui.end_row();
+
+                if !is_reroute {
+                    ui.label("Size");
+                    self.size_fields(ui, &ids, node.size, !node.auto_height);
+                    ui.end_row();
+
+                    ui.label("Auto height");
+                    let mut auto_height = node.auto_height;
+                    let response = ui.checkbox(&mut auto_height, "");
+                    if response.changed() {
+                        self.edit_nodes(&ids, response.id, |node| node.auto_height = auto_height);
+                    }
+                    ui.end_row();
+                }
+
+                ui.label("Color");
+                self.color_field(ui, &ids, node.header_color, false);
+                ui.end_row();
+
+                if !is_reroute {
+                    ui.label("Icon");
+                    self.icon_field(ui, &ids, node.icon.as_deref());
+                    ui.end_row();
+
+                    ui.label("Tags");
+                    self.tags_field(ui, &ids, &node.tags);
+                    ui.end_row();
+
+                    ui.label("Duration");
+                    self.duration_field(ui, &ids, node.duration);
+                    ui.end_row();
+                }
+            });
+
+        if !matches!(node.kind, NodeKind::Reroute | NodeKind::Subgraph) {
+            ui.label("Content");
+            let mut content = node.content.clone();
+            let response = ui.add(
+                egui::TextEdit::multiline(&mut content)
+                    .desired_rows(4)
+                    .desired_width(f32::INFINITY),
+            );
+            if response.changed() {
+                self.edit_nodes(&ids, response.id, |node| node.content.clone_from(&content));
+            }
+        }
+
+        if node.kind == NodeKind::Subgraph {
+            ui.separator();
+            ui.label(self.subgraph_summary(node_id).replace('\n', " — "));
+            if ui.button("Open Subgraph").clicked() {
+                self.pending_action =
+                    Some(ContextAction::Subgraph(SubgraphAction::Open(Some(node_id))));
+            }
+        }
+
+        ui.separator();
+        self.port_list(ui, &node);
+    }
+
+    /// 标题：不能为空，清空后失去焦点时恢复为类型默认标题。
+    fn title_field(&mut self, ui: &mut egui::Ui, node: &Node) {
+        let mut title = node.title.clone();
+        let response = ui.text_edit_singleline(&mut title);
+        if response.changed() {
+            self.edit_nodes(&[node.id], response.id, |node| {
+                node.title.clone_from(&title);
+            });
+        }
+        if title.trim().is_empty() {
+            if response.lost_focus() {
+                let fallback = format!("{} {}", node.kind.label(), node.id);
+                self.edit_nodes(&[node.id], response.id, |node| {
+                    node.title.clone_from(&fallback);
+                });
+            } else {
+                self.inspector_error = Some("Title cannot be empty".to_owned());
+            }
+        }
+    }
+
+    /// 类型：数据源 / 处理 / 终点之间可以切换，切换后不再存在的端口上的连线会被删除。
+    /// 转接点和子图节点的类型不能修改。
+    fn kind_field(&mut self, ui: &mut egui::Ui, node: &Node) {
+        const SWITCHABLE: [NodeKind; 3] = [NodeKind::Source, NodeKind::Process, NodeKind::Sink];
+        if !SWITCHABLE.contains(&node.kind) {
+            ui.label(node.kind.label());
+            return;
+        }
+
+        let mut kind = node.kind;
+        let response = egui::ComboBox::from_id_salt(("inspector_kind", node.id))
+            .selected_text(kind.label())
+            .show_ui(ui, |ui| {
+                for option in SWITCHABLE {
+                    ui.selectable_value(&mut kind, option, option.label());
+                }
+            })
+            .response;
+        if kind == node.kind {
+            return;
+        }
+
+        let node_id = node.id;
+        self.edit_nodes(&[node_id], response.id, |node| node.kind = kind);
+        self.connections.retain(|connection| {
+            (connection.from_node_id != node_id || kind.has_port(PortKind::Output))
+                && (connection.to_node_id != node_id || kind.has_port(PortKind::Input))
+        });
+    }
+
+    /// 宽 / 高输入框；`height_enabled` 为假时（自动高度）高度只读。
+    fn size_fields(&mut self, ui: &mut egui::Ui, ids: &[usize], size: Vec2, height_enabled: bool) {
+        ui.horizontal(|ui| {
+            let mut width = size.x;
+            let response = ui.add(
+                egui::DragValue::new(&mut width)
+                    .range(NODE_MIN_SIZE.x..=MAX_NODE_EXTENT)
+                    .prefix("w "),
+            );
+            if response.changed() {
+                self.edit_nodes(ids, response.id, |node| node.size.x = width);
+            }
+
+            let mut height = size.y;
+            let response = ui.add_enabled(
+                height_enabled,
+                egui::DragValue::new(&mut height)
+                    .range(NODE_MIN_SIZE.y..=MAX_NODE_EXTENT)
+                    .prefix("h "),
+            );
+            if response.changed() {
+                self.edit_nodes(ids, response.id, |node| node.size.y = height);
+            }
+        });
+    }
+
+    /// 标题栏颜色色块，高亮当前颜色；`mixed` 表示多个节点的颜色不一致。
+    fn color_field(
+        &mut self,
+        ui: &mut egui::Ui,
+        ids: &[usize],
+        current: Option<Color32>,
+        mixed: bool,
+    ) {
+        let default_color = self.theme.node_header;
+        ui.horizontal_wrapped(|ui| {
+            let mut chosen = None;
+            for color in std::iter::once(None).chain(HEADER_COLOR_PRESETS.map(Some)) {
+                let response = Self::color_swatch(ui, &self.theme, color.unwrap_or(default_color));
+                let response = if !mixed && current == color {
+                    response.highlight()
+                } else {
+                    response
+                };
+                let response = if color.is_none() {
+                    response.on_hover_text("Default")
+                } else {
+                    response
+                };
+                if response.clicked() {
+                    chosen = Some((color, ui.id().with("inspector_color")));
+                }
+            }
+            if let Some((color, widget)) = chosen {
+                self.edit_nodes(ids, widget, |node| node.header_color = color);
+            }
+        });
+    }
+
+    /// 图标：可以直接输入任意字符，也可以从预设里选；清空表示不显示。
+    fn icon_field(&mut self, ui: &mut egui::Ui, ids: &[usize], current: Option<&str>) {
+        ui.horizontal(|ui| {
+            let mut text = current.unwrap_or_default().to_owned();
+            let response = ui.add(egui::TextEdit::singleline(&mut text).desired_width(32.0));
+            if response.changed() {
+                let icon = (!text.trim().is_empty()).then(|| text.trim().to_owned());
+                self.edit_nodes(ids, response.id, |node| node.icon.clone_from(&icon));
+            }
+
+            let mut chosen = None;
+            ui.menu_button("Presets", |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    for glyph in ICON_PRESETS {
+                        if ui.button(glyph).clicked() {
+                            chosen = Some(glyph);
+                        }
+                    }
+                });
+            });
+            if let Some(glyph) = chosen {
+                let widget = ui.id().with("inspector_icon_preset");
+                self.edit_nodes(ids, widget, |node| node.icon = Some(glyph.to_owned()));
+            }
+        });
+    }
+
+    /// 耗时：勾选后才计入关键路径分析。
+    fn duration_field(&mut self, ui: &mut egui::Ui, ids: &[usize], current: Option<f32>) {
+        ui.horizontal(|ui| {
+            let mut enabled = current.is_some();
+            let mut value = current.unwrap_or(1.0);
+            let toggle = ui.checkbox(&mut enabled, "");
+            let drag = ui.add_enabled(
+                enabled,
+                egui::DragValue::new(&mut value)
+                    .range(0.0..=f32::MAX)
+                    .speed(0.1),
+            );
+            for response in [toggle, drag] {
+                if response.changed() {
+                    let duration = enabled.then_some(value);
+                    self.edit_nodes(ids, response.id, |node| node.duration = duration);
+                }
+            }
+        });
+    }
+
+    /// 标签：点 × 移除，输入新标签后回车或点 Add 添加；多选时对全部节点生效。
+    fn tags_field(&mut self, ui: &mut egui::Ui, ids: &[usize], tags: &[String]) {
+        ui.vertical(|ui| {
+            let mut removed = None;
+            ui.horizontal_wrapped(|ui| {
+                for tag in tags {
+                    if ui
+                        .small_button(format!("#{tag} ×"))
+                        .on_hover_text("Remove tag")
+                        .clicked()
+                    {
+                        removed = Some(tag.clone());
+                    }
+                }
+            });
+            let added = self.new_tag_input(ui);
+
+            let widget = ui.id().with("inspector_tags");
+            if let Some(tag) = removed {
+                self.edit_nodes(ids, widget, |node| {
+                    node.tags.retain(|own| !same_tag(own, &tag));
+                });
+            }
+            if let Some(tag) = added {
+                self.edit_nodes(ids, widget, |node| {
+                    if !node.has_tag(&tag) {
+                        node.tags.push(tag.clone());
+                    }
+                });
+            }
+        });
+    }
+
+    /// 端口列表：每个端口上的连线，点击对端切换到该连线。
+    fn port_list(&mut self, ui: &mut egui::Ui, node: &Node) {
+        if node.kind == NodeKind::Subgraph {
+            for (port, inner) in self.exposed_port_nodes(node.id) {
+                let title = self
+                    .node_by_id(inner)
+                    .map_or_else(|| format!("#{inner}"), |inner| inner.title.clone());
+                let arrow = match port {
+                    PortKind::Input => "in",
+                    PortKind::Output => "out",
+                };
+                ui.label(format!("{arrow}: {title}"));
+            }
+            return;
+        }
+
+        for port in [PortKind::Input, PortKind::Output] {
+            if !node.kind.has_port(port) {
+                continue;
+            }
+            let links: Vec<LinkKey> = self
+                .connections
+                .iter()
+                .filter(|connection| match port {
+                    PortKind::Input => connection.to_node_id == node.id,
+                    PortKind::Output => connection.from_node_id == node.id,
+                })
+                .map(|connection| connection.key())
+                .collect();
+            let heading = match port {
+                PortKind::Input => "Input",
+                PortKind::Output => "Output",
+            };
+            ui.label(format!("{heading} ({})", links.len()));
+            for link in links {
+                if ui.link(self.connection_label(link)).clicked() {
+                    self.selected_nodes.clear();
+                    self.selected_link = Some(link);
+                }
+            }
+        }
+    }
+
+    /// 多个节点的共有字段：数值不一致时显示第一个节点的值并标注 “mixed”。
+    fn multi_node_inspector(&mut self, ui: &mut egui::Ui, ids: &[usize]) {
+        let nodes: Vec<Node> = ids
+            .iter()
+            .filter_map(|&id| self.node_by_id(id).cloned())
+            .collect();
+        let Some(first) = nodes.first() else {
+            return;
+        };
+        let shared = |value: fn(&Node) -> f32| nodes.iter().all(|node| value(node) == value(first));
+        let mixed = |same: bool| if same { "" } else { " (mixed)" };
+        ui.strong(format!("{} nodes selected", nodes.len()));
+
+        // 转接点没有可调的尺寸，只改其他节点。
+        let resizable: Vec<usize> = nodes
+            .iter()
+            .filter(|node| node.kind != NodeKind::Reroute)
+            .map(|node| node.id)
+            .collect();
+        egui::Grid::new("inspector_nodes")
+            .num_columns(2)
+            .show(ui, |ui| {
+                if !resizable.is_empty() {
+                    let same_size = shared(|node| node.size.x) && shared(|node| node.size.y);
+                    ui.label(format!("Size{}", mixed(same_size)));
+                    let height_enabled = nodes.iter().all(|node| !node.auto_height);
+                    self.size_fields(ui, &resizable, first.size, height_enabled);
+                    ui.end_row();
+
+                    let auto_heights = nodes
+                        .iter()
+                        .filter(|node| resizable.contains(&node.id))
+                        .map(|node| node.auto_height);
+                    let all = auto_heights.clone().all(|auto| auto);
+                    let any = auto_heights.clone().any(|auto| auto);
+                    ui.label("Auto height");
+                    let mut auto_height = all;
+                    let response = ui
+                        .add(egui::Checkbox::new(&mut auto_height, "").indeterminate(any && !all));
+                    if response.changed() {
+                        self.edit_nodes(&resizable, response.id, |node| {
+                            node.auto_height = auto_height;
+                        });
+                    }
+                    ui.end_row();
+                }
+
+                let same_color = nodes
+                    .iter()
+                    .all(|node| node.header_color == first.header_color);
+                ui.label(format!("Color{}", mixed(same_color)));
+                self.color_field(ui, ids, first.header_color, !same_color);
+                ui.end_row();
+
+                if !resizable.is_empty() {
+                    // 只列出所有选中节点都有的标签。
+                    let shared_tags: Vec<String> = first
+                        .tags
+                        .iter()
+                        .filter(|tag| nodes.iter().all(|node| node.has_tag(tag)))
+                        .cloned()
+                        .collect();
+                    ui.label("Tags");
+                    self.tags_field(ui, &resizable, &shared_tags);
+                    ui.end_row();
+                }
+            });
+    }
+
+    /// 单条连线的属性。
+    fn link_inspector(&mut self, ui: &mut egui::Ui, link: LinkKey) {
+        let Some(connection) = self.connection_by_key(link).cloned() else {
+            return;
+        };
+        ui.strong(self.connection_label(link));
+
+        egui::Grid::new("inspector_link")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("From");
+                self.endpoint_field(ui, link, PortKind::Output);
+                ui.end_row();
+
+                ui.label("To");
+                self.endpoint_field(ui, link, PortKind::Input);
+                ui.end_row();
+
+                ui.label("Label");
+                let mut label = connection.label.clone();
+                let response = ui.text_edit_singleline(&mut label);
+                if response.changed() {
+                    self.inspector_commit(response.id);
+                    if let Some(connection) = self.connection_by_key_mut(link) {
+                        connection.label = label;
+                    }
+                }
+                ui.end_row();
+
+                ui.label("Routing");
+                let mut routing = connection.routing;
+                let response = egui::ComboBox::from_id_salt("inspector_routing")
+                    .selected_text(routing.map_or_else(
+                        || format!("Default ({})", self.link_routing.label()),
+                        |routing| routing.label().to_owned(),
+                    ))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut routing, None, "Default");
+                        for option in LinkRouting::ALL {
+                            ui.selectable_value(&mut routing, Some(option), option.label());
+                        }
+                    })
+                    .response;
+                if routing != connection.routing {
+                    self.inspector_commit(response.id);
+                    if let Some(connection) = self.connection_by_key_mut(link) {
+                        connection.routing = routing;
+                    }
+                }
+                ui.end_row();
+            });
+
+        ui.label("Style");
+        if let Some(style) = Self::link_style_menu_contents(ui, connection.style, &self.theme) {
+            self.inspector_commit(ui.id().with("inspector_link_style"));
+            if let Some(connection) = self.connection_by_key_mut(link) {
+                connection.style = style;
+            }
+        }
+
+        ui.separator();
+        if ui.button("Delete Link").clicked() {
+            self.pending_action = Some(ContextAction::DeleteLink(link));
+            self.selected_link = None;
+        }
+    }
+
+    /// 连线一端的节点选择框。只列出当前层带有对应端口的节点；
+    /// 自连接和重复连线不会被接受，改为显示错误提示。
+    fn endpoint_field(&mut self, ui: &mut egui::Ui, link: LinkKey, port: PortKind) {
+        let (from, to) = link;
+        let current = match port {
+            PortKind::Output => from,
+            PortKind::Input => to,
+        };
+        let candidates: Vec<(usize, String)> = self
+            .visible_nodes()
+            .filter(|node| node.kind.has_port(port) || node.id == current)
+            .map(|node| (node.id, node.title.clone()))
+            .collect();
+
+        let mut chosen = current;
+        let response = egui::ComboBox::from_id_salt(("inspector_endpoint", port as u8))
+            .selected_text(self.node_name(current))
+            .show_ui(ui, |ui| {
+                for (id, title) in &candidates {
+                    ui.selectable_value(&mut chosen, *id, title);
+                }
+            })
+            .response;
+        if chosen == current {
+            return;
+        }
+
+        let new_link = match port {
+            PortKind::Output => (chosen, to),
+            PortKind::Input => (from, chosen),
+        };
+        if new_link.0 == new_link.1 {
+            self.inspector_error = Some("A link cannot connect a node to itself".to_owned());
+        } else if self.connection_by_key(new_link).is_some() {
+            self.inspector_error = Some("These nodes are already linked".to_owned());
+        } else {
+            self.inspector_commit(response.id);
+            if let Some(connection) = self.connection_by_key_mut(link) {
+                connection.from_node_id = new_link.0;
+                connection.to_node_id = new_link.1;
+            }
+            self.selected_link = Some(new_link);
+        }
+    }
+}
@@ -0,0 +1,87 @@
+use egui::{CursorIcon, PointerButton, Pos2, Stroke};
+
+use super::{LinkKey, NodeGraphApp};
+
+// ============================================================
+// 切线（Knife）手势
+//
+// 按住 Ctrl 在画布上用右键拖出一条轨迹，松开后删除所有与轨迹相交的连线。
+// 相交判断把连线与轨迹都当作折线处理，复用命中测试用的连线采样。
+// ============================================================
+
+// 轨迹点之间的最小间距（屏幕像素），避免每帧都记录几乎重合的点。
+const CUT_POINT_SPACING: f32 = 3.0;
+
+impl NodeGraphApp {
+    /// 记录、绘制并结算切线手势。
+    pub(super) fn handle_cut_gesture(&mut self, ui: &egui::Ui) {
+        let (pressed, down, command, pointer_pos) = ui.input(|i| {
+            (
+                i.pointer.button_pressed(PointerButton::Secondary),
+                i.pointer.button_down(PointerButton::Secondary),
+                i.modifiers.command,
+                i.pointer.interact_pos(),
+            )
+        });
+
+        match &mut self.cut_stroke {
+            None => {
+                if pressed
+                    && command
+                    && let Some(pos) = pointer_pos.filter(|pos| self.canvas_rect.contains(*pos))
+                {
+                    self.cut_stroke = Some(vec![pos]);
+                }
+            }
+            Some(stroke) if down => {
+                if let Some(pos) = pointer_pos
+                    && stroke
+                        .last()
+                        .is_none_or(|last| last.distance(pos) >= CUT_POINT_SPACING)
+                {
+                    stroke.push(pos);
+                }
+            }
+            Some(_) => {
+                if let Some(stroke) = self.cut_stroke.take() {
+                    self.cut_connections_crossing(&stroke);
+                }
+            }
+        }
+
+        if let Some(stroke) = &self.cut_stroke {
+            ui.ctx().set_cursor_icon(CursorIcon::Crosshair);
+            ui.painter().extend(egui::Shape::dashed_line(
+                stroke,
+                Stroke::new(1.5, self.theme.cut_stroke),
+                6.0,
+                4.0,
+            ));
+            ui.ctx().request_repaint();
+        }
+    }
+
+    /// 删除所有与轨迹（屏幕坐标折线）相交的连线。
+    fn cut_connections_crossing(&mut self, stroke: &[Pos2]) {
+        if stroke.len() < 2 {
+            return;
+        }
+
+        let cut: Vec<LinkKey> = self
+            .connections
+            .iter()
+            .filter(|connection| {
+                self.connection_path_screen(connection)
+                    .is_some_and(|path| Self::polylines_intersect(&path.polyline(), stroke))
+            })
+            .map(|connection| connection.key())
+            .collect();
+
+        if !cut.is_empty() {
+            log::debug!("切断 {} 条连线", cut.len());
+            self.record_undo();
+            self.connections
+                .retain(|connection| !cut.contains(&connection.key()));
+        }
+    }
+}
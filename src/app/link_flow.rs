@@ -0,0 +1,154 @@
+use egui::{Pos2, Stroke};
+
+use super::{LinkKey, NodeGraphApp};
+
+// ============================================================
+// 连线数据流动画
+//
+// 求值引擎或宿主程序通过 `set_link_activity` 给连线设置活跃度（0..=1），
+// 活跃的连线上会有虚线段或圆点从输出端流向输入端：
+// 活跃度越高，流动越快、颜色越暖。
+// 只有存在活跃连线且动画开启时才请求重绘，空闲时不占用 CPU。
+// ============================================================
+
+// 活跃度为 0 / 1 时的流动速度（屏幕像素每秒）。
+const FLOW_MIN_SPEED: f32 = 30.0;
+const FLOW_MAX_SPEED: f32 = 220.0;
+// 虚线段长度与间隔。
+const FLOW_DASH_LENGTH: f32 = 10.0;
+const FLOW_GAP_LENGTH: f32 = 14.0;
+// 圆点间距。
+const FLOW_DOT_SPACING: f32 = 24.0;
+
+/// 数据流动画样式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(super) enum FlowAnimation {
+    Off,
+    #[default]
+    Dashes,
+    Dots,
+}
+
+impl FlowAnimation {
+    pub(super) const ALL: [Self; 3] = [Self::Off, Self::Dashes, Self::Dots];
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Off => "Off",
+            Self::Dashes => "Dashes",
+            Self::Dots => "Dots",
+        }
+    }
+}
+
+/// 折线上从起点算起、每隔 `spacing` 一个的点，第一个点距起点 `offset`。
+fn points_along(points: &[Pos2], offset: f32, spacing: f32) -> Vec<Pos2> {
+    let mut result = Vec::new();
+    let mut next = offset;
+    let mut walked = 0.0;
+
+    for segment in points.windows(2) {
+        let [a, b] = segment else {
+            continue;
+        };
+        let length = a.distance(*b);
+        while next <= walked + length {
+            let t = if length > f32::EPSILON {
+                (next - walked) / length
+            } else {
+                0.0
+            };
+            result.push(a.lerp(*b, t));
+            next += spacing;
+        }
+        walked += length;
+    }
+    result
+}
+
+impl NodeGraphApp {
+    /// 设置某条连线的活跃度（0..=1，超出范围会被截断），供求值引擎或宿主程序调用。
+    ///
+    /// 活跃度为 0 时清除该连线的动画。
+    pub fn set_link_activity(&mut self, from_node_id: usize, to_node_id: usize, activity: f32) {
+        let key = (from_node_id, to_node_id);
+        let activity = activity.clamp(0.0, 1.0);
+        if activity > 0.0 {
+            self.link_activity.insert(key, activity);
+        } else {
+            self.link_activity.remove(&key);
+        }
+    }
+
+    /// 清除所有连线的活跃度，停止动画。
+    pub fn clear_link_activity(&mut self) {
+        self.link_activity.clear();
+    }
+
+    /// 没有求值引擎时的演示：给每条连线设置一个不同的活跃度。
+    pub(super) fn simulate_link_activity(&mut self) {
+        let keys: Vec<LinkKey> = self
+            .connections
+            .iter()
+            .map(|connection| connection.key())
+            .collect();
+        for (index, (from, to)) in keys.into_iter().enumerate() {
+            self.set_link_activity(from, to, ((index * 7) % 10 + 1) as f32 / 10.0);
+        }
+    }
+
+    /// 在活跃连线上叠加流动动画，并在需要时请求下一帧重绘。
+    pub(super) fn draw_link_flow(&self, ui: &egui::Ui) {
+        if self.flow_animation == FlowAnimation::Off || self.link_activity.is_empty() {
+            return;
+        }
+
+        let time = ui.input(|i| i.time);
+        let painter = ui.painter();
+        let mut animating = false;
+
+        for connection in &self.connections {
+            let Some(&activity) = self.link_activity.get(&connection.key()) else {
+                continue;
+            };
+            let Some(path) = self.connection_path_screen(connection) else {
+                continue;
+            };
+            animating = true;
+
+            let points = path.polyline();
+            let speed = FLOW_MIN_SPEED + (FLOW_MAX_SPEED - FLOW_MIN_SPEED) * activity;
+            // 时间很大时 f32 精度不够，先用 f64 取余再转换。
+            let phase = |period: f32| (time * f64::from(speed) % f64::from(period)) as f32;
+            let color = self
+                .theme
+                .flow_low
+                .lerp_to_gamma(self.theme.flow_high, activity);
+            let width = connection.style.width + 1.0;
+
+            match self.flow_animation {
+                FlowAnimation::Off => {}
+                FlowAnimation::Dashes => {
+                    let period = FLOW_DASH_LENGTH + FLOW_GAP_LENGTH;
+                    painter.extend(egui::Shape::dashed_line_with_offset(
+                        &points,
+                        Stroke::new(width, color),
+                        &[FLOW_DASH_LENGTH],
+                        &[FLOW_GAP_LENGTH],
+                        phase(period),
+                    ));
+                }
+                FlowAnimation::Dots => {
+                    let offset = phase(FLOW_DOT_SPACING);
+                    for center in points_along(&points, offset, FLOW_DOT_SPACING) {
+                        painter.circle_filled(center, width + 1.0, color);
+                    }
+                }
+            }
+        }
+
+        if animating {
+            ui.ctx().request_repaint();
+        }
+    }
+}
@@ -0,0 +1,595 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap};
+
+use egui::{Color32, Pos2, Rect, Stroke, Vec2, epaint::CubicBezierShape};
+
+use super::{BEZIER_SAMPLES, Connection, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 连线走线方式
+//
+// - Bezier：默认的水平展开贝塞尔曲线
+// - Straight：两端直连
+// - Orthogonal：只走水平 / 竖直线段，并绕开节点矩形
+//
+// 直角走线在世界坐标中计算并缓存，只有端点移动、
+// 或有节点在这条线附近移动 / 增删时才重新计算。
+// ============================================================
+
+// 直角走线离开 / 进入端口时先水平走出的长度。
+const ROUTE_STUB: f32 = 20.0;
+// 节点矩形向外扩出的避让距离。
+const ROUTE_MARGIN: f32 = 12.0;
+// 每拐一次弯额外增加的代价，让路径尽量少拐弯。
+const ROUTE_BEND_PENALTY: f32 = 30.0;
+
+/// 连线走线方式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(super) enum LinkRouting {
+    #[default]
+    Bezier,
+    Straight,
+    Orthogonal,
+}
+
+impl LinkRouting {
+    pub(super) const ALL: [Self; 3] = [Self::Bezier, Self::Straight, Self::Orthogonal];
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Bezier => "Bezier",
+            Self::Straight => "Straight",
+            Self::Orthogonal => "Orthogonal",
+        }
+    }
+}
+
+/// 一条连线的几何形状（世界坐标或屏幕坐标，取决于调用方）。
+pub(super) enum LinkPath {
+    /// 三次贝塞尔曲线的四个控制点。
+    Bezier([Pos2; 4]),
+    /// 折线（直连或直角走线）。
+    Polyline(Vec<Pos2>),
+}
+
+impl LinkPath {
+    /// 整体平移（世界坐标 -> 屏幕坐标）。
+    pub(super) fn translate(self, offset: Vec2) -> Self {
+        match self {
+            Self::Bezier(points) => Self::Bezier(points.map(|p| p + offset)),
+            Self::Polyline(points) => {
+                Self::Polyline(points.into_iter().map(|p| p + offset).collect())
+            }
+        }
+    }
+
+    /// 把整条连线采样成折线。
+    pub(super) fn polyline(&self) -> Vec<Pos2> {
+        self.tail_polyline(0.0)
+    }
+
+    /// 只取连线后段（按参数 / 长度比例 `t_min..=1`）的折线。
+    pub(super) fn tail_polyline(&self, t_min: f32) -> Vec<Pos2> {
+        match self {
+            Self::Bezier(points) => NodeGraphApp::bezier_polyline(*points, t_min, BEZIER_SAMPLES),
+            Self::Polyline(points) => polyline_tail(points, t_min),
+        }
+    }
+
+    /// 连线的中点（贝塞尔取参数 0.5，折线取长度一半处），用于放置标签。
+    pub(super) fn midpoint(&self) -> Pos2 {
+        match self {
+            Self::Bezier([p0, p1, p2, p3]) => {
+                NodeGraphApp::cubic_bezier_point(*p0, *p1, *p2, *p3, 0.5)
+            }
+            Self::Polyline(points) => polyline_tail(points, 0.5)
+                .first()
+                .copied()
+                .unwrap_or_default(),
+        }
+    }
+
+    /// 点到连线后段（`t_min..=1`）的最近距离。
+    pub(super) fn distance_to(&self, pointer: Pos2, t_min: f32) -> f32 {
+        self.tail_polyline(t_min)
+            .windows(2)
+            .filter_map(|segment| match segment {
+                [a, b] => Some(NodeGraphApp::point_to_segment_distance(pointer, *a, *b)),
+                _ => None,
+            })
+            .fold(f32::MAX, f32::min)
+    }
+
+    /// 绘制连线。
+    pub(super) fn paint(&self, painter: &egui::Painter, stroke: Stroke) {
+        match self {
+            Self::Bezier(points) => {
+                painter.add(CubicBezierShape::from_points_stroke(
+                    *points,
+                    false,
+                    Color32::TRANSPARENT,
+                    stroke,
+                ));
+            }
+            Self::Polyline(points) => {
+                painter.add(egui::Shape::line(points.clone(), stroke));
+            }
+        }
+    }
+
+    /// SVG `<path>` 的 `d` 属性。
+    pub(super) fn svg_path_data(&self) -> String {
+        match self {
+            Self::Bezier([p0, p1, p2, p3]) => format!(
+                "M {} {} C {} {}, {} {}, {} {}",
+                p0.x, p0.y, p1.x, p1.y, p2.x, p2.y, p3.x, p3.y
+            ),
+            Self::Polyline(points) => points
+                .iter()
+                .enumerate()
+                .map(|(index, p)| {
+                    let command = if index == 0 { 'M' } else { 'L' };
+                    format!("{command} {} {}", p.x, p.y)
+                })
+                .collect::<Vec<_>>()
+                .join(" "),
+        }
+    }
+}
+
+/// 折线按长度比例截取后段。
+fn polyline_tail(points: &[Pos2], t_min: f32) -> Vec<Pos2> {
+    if t_min <= 0.0 {
+        return points.to_vec();
+    }
+
+    let segment_lengths: Vec<f32> = points
+        .windows(2)
+        .map(|segment| match segment {
+            [a, b] => a.distance(*b),
+            _ => 0.0,
+        })
+        .collect();
+    let mut remaining = segment_lengths.iter().sum::<f32>() * t_min;
+
+    for (index, (segment, length)) in points.windows(2).zip(&segment_lengths).enumerate() {
+        let [a, b] = segment else {
+            continue;
+        };
+        if remaining <= *length {
+            let t = if *length > f32::EPSILON {
+                remaining / length
+            } else {
+                0.0
+            };
+            let mut tail = vec![a.lerp(*b, t)];
+            tail.extend(points.iter().skip(index + 1));
+            return tail;
+        }
+        remaining -= length;
+    }
+
+    points.last().map(|last| vec![*last]).unwrap_or_default()
+}
+
+/// 缓存的一条直角走线（世界坐标）。
+pub(super) struct CachedRoute {
+    /// 计算时的端点，端点变化就需要重算。
+    from: Pos2,
+    to: Pos2,
+    points: Vec<Pos2>,
+    /// 路径包围盒：附近有节点变化时才重算。
+    bounds: Rect,
+}
+
+impl CachedRoute {
+    fn new(from: Pos2, to: Pos2, points: Vec<Pos2>) -> Self {
+        let bounds = Rect::from_points(&points).expand(ROUTE_MARGIN);
+        Self {
+            from,
+            to,
+            points,
+            bounds,
+        }
+    }
+}
+
+/// 直角走线缓存：上次计算时的障碍物（按节点 ID）和每条连线的路径。
+#[derive(Default)]
+pub(super) struct RouteCache {
+    obstacles: BTreeMap<usize, Rect>,
+    routes: BTreeMap<(usize, usize), CachedRoute>,
+}
+
+impl NodeGraphApp {
+    /// 某条连线实际使用的走线方式：连线自己的设置优先，否则用文档默认值。
+    pub(super) fn effective_routing(&self, connection: &Connection) -> LinkRouting {
+        connection.routing.unwrap_or(self.link_routing)
+    }
+
+    /// 连线在世界坐标中的形状；任一端节点不存在时返回 `None`。
+    pub(super) fn connection_path_world(&self, connection: &Connection) -> Option<LinkPath> {
+        let (from, to) = self.connection_endpoints_world(connection)?;
+
+        Some(match self.effective_routing(connection) {
+            LinkRouting::Bezier => LinkPath::Bezier(Self::bezier_control_points(from, to)),
+            LinkRouting::Straight => LinkPath::Polyline(vec![from, to]),
+            LinkRouting::Orthogonal => LinkPath::Polyline(
+                self.route_cache
+                    .routes
+                    .get(&connection.key())
+                    .filter(|route| route.from == from && route.to == to)
+                    .map_or_else(
+                        || simple_orthogonal_route(from, to),
+                        |route| route.points.clone(),
+                    ),
+            ),
+        })
+    }
+
+    /// 连线在屏幕坐标中的形状。
+    pub(super) fn connection_path_screen(&self, connection: &Connection) -> Option<LinkPath> {
+        self.connection_path_world(connection)
+            .map(|path| path.translate(self.pan_offset))
+    }
+
+    /// 增量更新直角走线缓存，每帧绘制连线前调用。
+    ///
+    /// 只有以下情况才重新计算某条连线：
+    /// - 端点位置变了
+    /// - 有节点矩形发生变化（移动 / 缩放 / 增删），且新旧矩形与这条路径的包围盒相交
+    pub(super) fn update_link_routes(&mut self) {
+        // 被折叠分组框隐藏的节点不算障碍。
+        let obstacles: BTreeMap<usize, Rect> = self
+            .visible_nodes()
+            .filter(|node| node.kind != NodeKind::Reroute)
+            .map(|node| (node.id, node.world_rect().expand(ROUTE_MARGIN)))
+            .collect();
+
+        let mut dirty: Vec<Rect> = Vec::new();
+        for (id, rect) in &obstacles {
+            match self.route_cache.obstacles.get(id) {
+                Some(old) if old == rect => {}
+                Some(old) => dirty.extend([*old, *rect]),
+                None => dirty.push(*rect),
+            }
+        }
+        dirty.extend(
+            self.route_cache
+                .obstacles
+                .iter()
+                .filter(|(id, _)| !obstacles.contains_key(id))
+                .map(|(_, rect)| *rect),
+        );
+
+        let obstacle_list: Vec<Rect> = obstacles.values().copied().collect();
+        let mut routes = BTreeMap::new();
+        for connection in &self.connections {
+            if self.effective_routing(connection) != LinkRouting::Orthogonal {
+                continue;
+            }
+            let Some((from, to)) = self.connection_endpoints_world(connection) else {
+                continue;
+            };
+
+            let key = connection.key();
+            let route = match self.route_cache.routes.remove(&key) {
+                Some(cached)
+                    if cached.from == from
+                        && cached.to == to
+                        && !dirty.iter().any(|rect| rect.intersects(cached.bounds)) =>
+                {
+                    cached
+                }
+                _ => CachedRoute::new(from, to, route_orthogonal(from, to, &obstacle_list)),
+            };
+            routes.insert(key, route);
+        }
+
+        self.route_cache = RouteCache { obstacles, routes };
+    }
+}
+
+/// 不考虑障碍物的简单直角走线：水平出 -> 竖直 -> 水平进。
+fn simple_orthogonal_route(from: Pos2, to: Pos2) -> Vec<Pos2> {
+    let start = from + Vec2::new(ROUTE_STUB, 0.0);
+    let end = to - Vec2::new(ROUTE_STUB, 0.0);
+    let mid_x = (start.x + end.x) * 0.5;
+
+    simplify_polyline(vec![
+        from,
+        start,
+        Pos2::new(mid_x, start.y),
+        Pos2::new(mid_x, end.y),
+        end,
+        to,
+    ])
+}
+
+/// 去掉重合点和共线的中间点。
+fn simplify_polyline(points: Vec<Pos2>) -> Vec<Pos2> {
+    let mut result: Vec<Pos2> = Vec::with_capacity(points.len());
+    for point in points {
+        if result
+            .last()
+            .is_some_and(|last| last.distance(point) < 0.01)
+        {
+            continue;
+        }
+        if let [.., a, b] = result.as_slice() {
+            let collinear = ((a.x - b.x).abs() < 0.01 && (b.x - point.x).abs() < 0.01)
+                || ((a.y - b.y).abs() < 0.01 && (b.y - point.y).abs() < 0.01);
+            if collinear {
+                result.pop();
+            }
+        }
+        result.push(point);
+    }
+    result
+}
+
+/// 点是否严格位于矩形内部（落在边上不算）。
+fn strictly_inside(rect: &Rect, p: Pos2) -> bool {
+    p.x > rect.min.x && p.x < rect.max.x && p.y > rect.min.y && p.y < rect.max.y
+}
+
+/// 排序并去掉几乎相等的坐标。
+fn sorted_coordinates(mut values: Vec<f32>) -> Vec<f32> {
+    values.sort_by(f32::total_cmp);
+    values.dedup_by(|a, b| (*a - *b).abs() < 0.5);
+    values
+}
+
+/// 稀疏网格的 x / y 坐标：两端、两端中点以及所有障碍物矩形的边。
+fn route_grid(start: Pos2, end: Pos2, obstacles: &[Rect]) -> (Vec<f32>, Vec<f32>) {
+    let xs = [start.x, end.x, (start.x + end.x) * 0.5]
+        .into_iter()
+        .chain(obstacles.iter().flat_map(|rect| [rect.min.x, rect.max.x]));
+    let ys = [start.y, end.y, (start.y + end.y) * 0.5]
+        .into_iter()
+        .chain(obstacles.iter().flat_map(|rect| [rect.min.y, rect.max.y]));
+    (
+        sorted_coordinates(xs.collect()),
+        sorted_coordinates(ys.collect()),
+    )
+}
+
+/// 与 `v` 最接近的坐标下标。
+fn nearest_index(values: &[f32], v: f32) -> usize {
+    values
+        .iter()
+        .enumerate()
+        .min_by(|a, b| (a.1 - v).abs().total_cmp(&(b.1 - v).abs()))
+        .map_or(0, |(index, _)| index)
+}
+
+/// 在稀疏网格上搜索绕开障碍物的直角路径。
+///
+/// 网格线取自端点和所有障碍物矩形的边，因此相邻网格点之间的线段
+/// 要么完全在某个障碍物内部、要么完全在外部，只需检查线段中点。
+/// 搜索状态带上“当前方向”，拐弯额外加 `ROUTE_BEND_PENALTY`，用 Dijkstra 求代价最小的路径。
+/// 找不到路径时退回 `simple_orthogonal_route`。
+fn route_orthogonal(from: Pos2, to: Pos2, obstacles: &[Rect]) -> Vec<Pos2> {
+    // 方向编号：0 右、1 左、2 下、3 上；`d ^ 1` 为反方向。
+    const STEPS: [(isize, isize); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+    const RIGHT: usize = 0;
+
+    let start = from + Vec2::new(ROUTE_STUB, 0.0);
+    let end = to - Vec2::new(ROUTE_STUB, 0.0);
+    let blocked = |p: Pos2| obstacles.iter().any(|rect| strictly_inside(rect, p));
+    if blocked(start) || blocked(end) {
+        return simple_orthogonal_route(from, to);
+    }
+
+    let (xs, ys) = route_grid(start, end, obstacles);
+    let (start_x, start_y) = (nearest_index(&xs, start.x), nearest_index(&ys, start.y));
+    let (end_x, end_y) = (nearest_index(&xs, end.x), nearest_index(&ys, end.y));
+
+    let (nx, ny) = (xs.len(), ys.len());
+    let point_at = |x: usize, y: usize| {
+        Pos2::new(
+            xs.get(x).copied().unwrap_or_default(),
+            ys.get(y).copied().unwrap_or_default(),
+        )
+    };
+    let state_index = |x: usize, y: usize, dir: usize| (y * nx + x) * 4 + dir;
+
+    let mut dist = vec![f32::INFINITY; nx * ny * 4];
+    let mut prev = vec![usize::MAX; nx * ny * 4];
+    // 代价都是非负 f32，其位模式的大小顺序与数值顺序一致，可直接放进整数堆里比较。
+    let mut heap = BinaryHeap::new();
+    let start_state = state_index(start_x, start_y, RIGHT);
+    if let Some(d) = dist.get_mut(start_state) {
+        *d = 0.0;
+    }
+    heap.push(Reverse((0.0_f32.to_bits(), start_state)));
+
+    let mut best_goal: Option<(f32, usize)> = None;
+    while let Some(Reverse((cost_bits, state))) = heap.pop() {
+        let cost = f32::from_bits(cost_bits);
+        if dist.get(state).is_some_and(|d| cost > *d) {
+            continue;
+        }
+        let dir = state % 4;
+        let cell = state / 4;
+        let (x, y) = (cell % nx, cell / nx);
+
+        if (x, y) == (end_x, end_y) {
+            // 最后要水平向右进入输入端口，方向不对需要再拐一次。
+            let total = if dir == RIGHT {
+                cost
+            } else {
+                cost + ROUTE_BEND_PENALTY
+            };
+            if best_goal.is_none_or(|(best, _)| total < best) {
+                best_goal = Some((total, state));
+            }
+            continue;
+        }
+        if best_goal.is_some_and(|(best, _)| cost >= best) {
+            break;
+        }
+
+        for (next_dir, (step_x, step_y)) in STEPS.iter().enumerate() {
+            if next_dir == dir ^ 1 {
+                continue;
+            }
+            let (Some(next_x), Some(next_y)) =
+                (x.checked_add_signed(*step_x), y.checked_add_signed(*step_y))
+            else {
+                continue;
+            };
+            if next_x >= nx || next_y >= ny {
+                continue;
+            }
+
+            let here = point_at(x, y);
+            let there = point_at(next_x, next_y);
+            if blocked(there) || blocked(here.lerp(there, 0.5)) {
+                continue;
+            }
+
+            let bend = if next_dir == dir {
+                0.0
+            } else {
+                ROUTE_BEND_PENALTY
+            };
+            let next_cost = cost + here.distance(there) + bend;
+            let next_state = state_index(next_x, next_y, next_dir);
+            if let Some(d) = dist.get_mut(next_state)
+                && next_cost < *d
+            {
+                *d = next_cost;
+                if let Some(p) = prev.get_mut(next_state) {
+                    *p = state;
+                }
+                heap.push(Reverse((next_cost.to_bits(), next_state)));
+            }
+        }
+    }
+
+    let Some((_, goal_state)) = best_goal else {
+        return simple_orthogonal_route(from, to);
+    };
+
+    let mut cells = Vec::new();
+    let mut state = goal_state;
+    while state != usize::MAX {
+        let cell = state / 4;
+        cells.push(point_at(cell % nx, cell / nx));
+        state = prev.get(state).copied().unwrap_or(usize::MAX);
+    }
+    cells.reverse();
+
+    let mut points = vec![from];
+    points.extend(cells);
+    points.push(to);
+    simplify_polyline(points)
+}
+
+#[cfg(test)]
+mod tests {
+    use egui::{Pos2, Rect};
+
+    use super::{route_orthogonal, simplify_polyline};
+
+    /// 每一段都是水平或竖直的。
+    fn is_orthogonal(points: &[Pos2]) -> bool {
+        points
+            .windows(2)
+            .all(|pair| matches!(pair, [a, b] if a.x == b.x || a.y == b.y))
+    }
+
+    /// 折线上有没有点落在矩形内部（按 1 像素步长采样）。
+    /// 沿矩形边走不算穿过，所以先把矩形缩小一点，避开采样的浮点误差。
+    fn crosses(points: &[Pos2], rect: Rect) -> bool {
+        let rect = rect.shrink(0.1);
+        points.windows(2).any(|pair| match pair {
+            [a, b] => {
+                let steps = a.distance(*b).ceil().max(1.0) as usize;
+                (0..=steps).any(|i| {
+                    let p = a.lerp(*b, i as f32 / steps as f32);
+                    super::strictly_inside(&rect, p)
+                })
+            }
+            _ => false,
+        })
+    }
+
+    #[test]
+    fn simplify_removes_duplicates_and_collinear_points() {
+        let points = vec![
+            Pos2::new(0.0, 0.0),
+            Pos2::new(0.0, 0.0),
+            Pos2::new(5.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 5.0),
+            Pos2::new(10.0, 10.0),
+        ];
+        assert_eq!(
+            simplify_polyline(points),
+            vec![
+                Pos2::new(0.0, 0.0),
+                Pos2::new(10.0, 0.0),
+                Pos2::new(10.0, 10.0)
+            ],
+            "只保留拐点"
+        );
+    }
+
+    #[test]
+    fn simplify_keeps_corners() {
+        let points = vec![
+            Pos2::new(0.0, 0.0),
+            Pos2::new(10.0, 0.0),
+            Pos2::new(10.0, 10.0),
+            Pos2::new(20.0, 10.0),
+        ];
+        assert_eq!(simplify_polyline(points.clone()), points, "拐点不能去掉");
+    }
+
+    #[test]
+    fn route_without_obstacles_is_straight() {
+        let from = Pos2::new(0.0, 50.0);
+        let to = Pos2::new(200.0, 50.0);
+        assert_eq!(
+            route_orthogonal(from, to, &[]),
+            vec![from, to],
+            "同一高度且没有障碍物时是一条直线"
+        );
+    }
+
+    #[test]
+    fn route_connects_endpoints_orthogonally() {
+        let from = Pos2::new(0.0, 0.0);
+        let to = Pos2::new(300.0, 120.0);
+        let route = route_orthogonal(from, to, &[]);
+        assert_eq!(route.first(), Some(&from), "从输出端口出发");
+        assert_eq!(route.last(), Some(&to), "到输入端口结束");
+        assert!(is_orthogonal(&route), "只有水平和竖直的线段：{route:?}");
+    }
+
+    #[test]
+    fn route_goes_around_obstacle() {
+        let from = Pos2::new(0.0, 100.0);
+        let to = Pos2::new(400.0, 100.0);
+        let obstacle = Rect::from_min_max(Pos2::new(150.0, 50.0), Pos2::new(250.0, 150.0));
+        let route = route_orthogonal(from, to, &[obstacle]);
+        assert_eq!(route.first(), Some(&from), "从输出端口出发");
+        assert_eq!(route.last(), Some(&to), "到输入端口结束");
+        assert!(is_orthogonal(&route), "只有水平和竖直的线段：{route:?}");
+        assert!(!crosses(&route, obstacle), "不穿过障碍物：{route:?}");
+    }
+
+    #[test]
+    fn route_backwards_link_goes_around_obstacle() {
+        // 输入端在输出端左边，连线需要绕回来。
+        let from = Pos2::new(300.0, 100.0);
+        let to = Pos2::new(0.0, 100.0);
+        let obstacle = Rect::from_min_max(Pos2::new(100.0, 60.0), Pos2::new(200.0, 140.0));
+        let route = route_orthogonal(from, to, &[obstacle]);
+        assert_eq!(route.last(), Some(&to), "到输入端口结束");
+        assert!(is_orthogonal(&route), "只有水平和竖直的线段：{route:?}");
+        assert!(!crosses(&route, obstacle), "不穿过障碍物：{route:?}");
+    }
+}
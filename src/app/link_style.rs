@@ -0,0 +1,257 @@
+use egui::{Color32, CornerRadius, FontId, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2};
+
+use super::context_menu::CanvasMenuTarget;
+use super::link_routing::LinkPath;
+use super::{LinkKey, NodeGraphApp, PORT_RADIUS};
+
+// ============================================================
+// 连线标签与样式
+//
+// 每条连线可以单独设置颜色、线宽、虚线样式和箭头，
+// 还可以在中点处显示一段文字标签（双击标签即可编辑）。
+// 这些设置都保存在 `Connection` 里，随文档持久化并写入 SVG 导出。
+// ============================================================
+
+// 标签文字字号。
+const LABEL_FONT_SIZE: f32 = 12.0;
+// 标签背景相对文字的内边距。
+const LABEL_PADDING: Vec2 = Vec2::new(6.0, 3.0);
+// 正在编辑的标签输入框宽度。
+const LABEL_EDIT_WIDTH: f32 = 120.0;
+// 箭头尖端离输入端口中心的距离，避免被端口圆环盖住。
+const ARROW_TIP_INSET: f32 = PORT_RADIUS + 1.0;
+
+/// 连线的虚线样式。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(super) enum LinkDash {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LinkDash {
+    pub(super) const ALL: [Self; 3] = [Self::Solid, Self::Dashed, Self::Dotted];
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Solid => "Solid",
+            Self::Dashed => "Dashed",
+            Self::Dotted => "Dotted",
+        }
+    }
+}
+
+/// 单条连线的外观。
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct LinkStyle {
+    /// 线条颜色，`None` 表示使用主题的默认连线颜色。
+    pub(super) color: Option<Color32>,
+    pub(super) width: f32,
+    pub(super) dash: LinkDash,
+    /// 是否在输入端画箭头。
+    pub(super) arrow: bool,
+}
+
+impl Default for LinkStyle {
+    fn default() -> Self {
+        Self {
+            color: None,
+            width: 2.0,
+            dash: LinkDash::Solid,
+            arrow: false,
+        }
+    }
+}
+
+impl LinkStyle {
+    pub(super) const MIN_WIDTH: f32 = 1.0;
+    pub(super) const MAX_WIDTH: f32 = 6.0;
+
+    /// 实际使用的颜色；没有单独设置时用 `default`（主题的连线颜色）。
+    pub(super) fn color(&self, default: Color32) -> Color32 {
+        self.color.unwrap_or(default)
+    }
+
+    /// 虚线的（实线段长度，间隔长度），随线宽缩放。
+    pub(super) fn dash_pattern(&self) -> (f32, f32) {
+        (self.width * 4.0 + 2.0, self.width * 2.0 + 2.0)
+    }
+
+    /// 点线中相邻两点的间距，随线宽缩放。
+    pub(super) fn dot_spacing(&self) -> f32 {
+        self.width * 3.0 + 2.0
+    }
+
+    /// 输入端箭头的三个顶点（尖端在前）；连线太短无法确定方向时返回 `None`。
+    pub(super) fn arrow_points(&self, path: &LinkPath) -> Option<[Pos2; 3]> {
+        let points = path.polyline();
+        let (tail, end) = match points.as_slice() {
+            [.., tail, end] => (*tail, *end),
+            _ => return None,
+        };
+        let direction = (end - tail).normalized();
+        if !direction.is_finite() || direction == Vec2::ZERO {
+            return None;
+        }
+
+        let length = 6.0 + self.width * 2.0;
+        let half_width = 3.0 + self.width;
+        let tip = end - direction * ARROW_TIP_INSET;
+        let base = tip - direction * length;
+        let normal = direction.rot90() * half_width;
+        Some([tip, base + normal, base - normal])
+    }
+
+    /// 按样式绘制一条连线（含箭头）；`default_color` 是主题的连线颜色。
+    pub(super) fn paint(&self, painter: &egui::Painter, path: &LinkPath, default_color: Color32) {
+        let color = self.color(default_color);
+        let stroke = Stroke::new(self.width, color);
+        match self.dash {
+            LinkDash::Solid => path.paint(painter, stroke),
+            LinkDash::Dashed => {
+                let (dash, gap) = self.dash_pattern();
+                painter.extend(egui::Shape::dashed_line(
+                    &path.polyline(),
+                    stroke,
+                    dash,
+                    gap,
+                ));
+            }
+            LinkDash::Dotted => {
+                painter.extend(egui::Shape::dotted_line(
+                    &path.polyline(),
+                    color,
+                    self.dot_spacing(),
+                    self.width * 0.75,
+                ));
+            }
+        }
+
+        if self.arrow
+            && let Some(points) = self.arrow_points(path)
+        {
+            painter.add(egui::Shape::convex_polygon(
+                points.to_vec(),
+                color,
+                Stroke::NONE,
+            ));
+        }
+    }
+}
+
+/// 一个需要绘制的标签（先收集，再逐个绘制，避免边遍历连线边修改它们）。
+struct LabelItem {
+    link: LinkKey,
+    center: Pos2,
+    text: String,
+    color: Color32,
+}
+
+impl NodeGraphApp {
+    /// 在连线中点绘制标签；正在编辑的标签显示为输入框。
+    ///
+    /// 需要在画布响应之后、节点之前调用：标签可以点击，但不应盖住节点。
+    pub(super) fn draw_link_labels(&mut self, ui: &mut egui::Ui) {
+        let items: Vec<LabelItem> = self
+            .connections
+            .iter()
+            .filter(|connection| {
+                !connection.label.is_empty() || self.editing_link_label == Some(connection.key())
+            })
+            .filter_map(|connection| {
+                Some(LabelItem {
+                    link: connection.key(),
+                    center: self.connection_path_screen(connection)?.midpoint(),
+                    text: connection.label.clone(),
+                    color: connection.style.color(self.theme.link),
+                })
+            })
+            .collect();
+
+        for item in items {
+            if self.editing_link_label == Some(item.link) {
+                self.draw_link_label_editor(ui, &item);
+            } else {
+                self.draw_link_label(ui, &item);
+            }
+        }
+    }
+
+    fn draw_link_label(&mut self, ui: &egui::Ui, item: &LabelItem) {
+        let galley = ui.painter().layout_no_wrap(
+            item.text.clone(),
+            FontId::proportional(LABEL_FONT_SIZE),
+            self.theme.link_label_text,
+        );
+        let rect = Rect::from_center_size(item.center, galley.size() + LABEL_PADDING * 2.0);
+
+        ui.painter()
+            .rect_filled(rect, CornerRadius::same(4), self.theme.canvas_bg);
+        ui.painter().rect_stroke(
+            rect,
+            CornerRadius::same(4),
+            Stroke::new(1.0, item.color),
+            StrokeKind::Inside,
+        );
+        ui.painter()
+            .galley(rect.min + LABEL_PADDING, galley, self.theme.link_label_text);
+
+        // 标签盖在连线上方，右键时打开这条连线的菜单，双击编辑。
+        let response = ui.interact(
+            rect,
+            ui.id().with(("link_label", item.link)),
+            Sense::click(),
+        );
+        if response.double_clicked() {
+            self.editing_link_label = Some(item.link);
+        }
+        if response.secondary_clicked() {
+            self.canvas_menu = Some(CanvasMenuTarget::Link {
+                link: item.link,
+                world_pos: item.center - self.pan_offset,
+            });
+        }
+        response.context_menu(|ui| self.canvas_menu_contents(ui));
+    }
+
+    fn draw_link_label_editor(&mut self, ui: &mut egui::Ui, item: &LabelItem) {
+        let Some(connection) = self
+            .connections
+            .iter_mut()
+            .find(|connection| connection.key() == item.link)
+        else {
+            self.editing_link_label = None;
+            return;
+        };
+
+        let rect = Rect::from_center_size(
+            item.center,
+            Vec2::new(
+                LABEL_EDIT_WIDTH,
+                LABEL_FONT_SIZE + LABEL_PADDING.y * 2.0 + 4.0,
+            ),
+        );
+        ui.painter()
+            .rect_filled(rect, CornerRadius::same(4), self.theme.canvas_bg);
+        let response = ui.put(
+            rect,
+            egui::TextEdit::singleline(&mut connection.label)
+                .hint_text("Label")
+                .font(FontId::proportional(LABEL_FONT_SIZE))
+                .horizontal_align(egui::Align::Center),
+        );
+
+        // 回车、Esc 或点击别处都会让输入框失去焦点，此时结束编辑。
+        if response.lost_focus() {
+            connection.label = connection.label.trim().to_owned();
+            self.editing_link_label = None;
+        } else if !response.has_focus() {
+            response.request_focus();
+        }
+        // 去掉首尾空白后和原来一样时不记撤销。
+        let label = connection.label.clone();
+        self.track_text_edit(&response, &label);
+    }
+}
@@ -0,0 +1,217 @@
+use egui::{Key, KeyboardShortcut, Modifiers, Pos2, Rect};
+
+use super::NodeGraphApp;
+
+// ============================================================
+// 对齐与分布
+//
+// 作用于选中节点的世界坐标矩形：
+// - 对齐：左 / 右 / 上 / 下边对齐，或水平 / 竖直中线对齐（至少 2 个节点）
+// - 分布：按当前顺序让相邻节点之间的间距相等，首尾节点不动（至少 3 个节点）
+// 每条命令都记录为一步撤销。
+// ============================================================
+
+// 对齐 / 分布快捷键统一用 Alt + Shift + 字母，避免与文字输入冲突。
+const ALIGN_MODIFIERS: Modifiers = Modifiers::ALT.plus(Modifiers::SHIFT);
+
+/// 对齐 / 分布命令。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum AlignCommand {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    /// 竖直中线对齐（x 方向居中）。
+    CenterHorizontal,
+    /// 水平中线对齐（y 方向居中）。
+    CenterVertical,
+    DistributeHorizontal,
+    DistributeVertical,
+}
+
+impl AlignCommand {
+    pub(super) const ALL: [Self; 8] = [
+        Self::Left,
+        Self::Right,
+        Self::Top,
+        Self::Bottom,
+        Self::CenterHorizontal,
+        Self::CenterVertical,
+        Self::DistributeHorizontal,
+        Self::DistributeVertical,
+    ];
+
+    pub(super) fn label(self) -> &'static str {
+        match self {
+            Self::Left => "Align Left",
+            Self::Right => "Align Right",
+            Self::Top => "Align Top",
+            Self::Bottom => "Align Bottom",
+            Self::CenterHorizontal => "Align Centers",
+            Self::CenterVertical => "Align Middles",
+            Self::DistributeHorizontal => "Distribute Horizontally",
+            Self::DistributeVertical => "Distribute Vertically",
+        }
+    }
+
+    pub(super) fn shortcut(self) -> KeyboardShortcut {
+        let key = match self {
+            Self::Left => Key::A,
+            Self::Right => Key::D,
+            Self::Top => Key::W,
+            Self::Bottom => Key::S,
+            Self::CenterHorizontal => Key::C,
+            Self::CenterVertical => Key::M,
+            Self::DistributeHorizontal => Key::H,
+            Self::DistributeVertical => Key::V,
+        };
+        KeyboardShortcut::new(ALIGN_MODIFIERS, key)
+    }
+
+    /// 执行命令至少需要几个节点。
+    pub(super) fn min_nodes(self) -> usize {
+        match self {
+            Self::DistributeHorizontal | Self::DistributeVertical => 3,
+            _ => 2,
+        }
+    }
+
+    /// 计算每个矩形的新左上角（顺序与输入一致）。
+    fn arrange(self, rects: &[Rect]) -> Vec<Pos2> {
+        let Some(bounds) = rects.iter().copied().reduce(|a, b| a.union(b)) else {
+            return Vec::new();
+        };
+
+        match self {
+            Self::DistributeHorizontal | Self::DistributeVertical => {
+                return distribute(rects, self == Self::DistributeHorizontal);
+            }
+            _ => {}
+        }
+
+        rects
+            .iter()
+            .map(|rect| {
+                let mut min = rect.min;
+                match self {
+                    Self::Left => min.x = bounds.left(),
+                    Self::Right => min.x = bounds.right() - rect.width(),
+                    Self::Top => min.y = bounds.top(),
+                    Self::Bottom => min.y = bounds.bottom() - rect.height(),
+                    Self::CenterHorizontal => min.x = bounds.center().x - rect.width() * 0.5,
+                    Self::CenterVertical => min.y = bounds.center().y - rect.height() * 0.5,
+                    Self::DistributeHorizontal | Self::DistributeVertical => {}
+                }
+                min
+            })
+            .collect()
+    }
+}
+
+/// 沿一个方向等间距分布：按中心排序，首尾不动，中间节点依次排开。
+fn distribute(rects: &[Rect], horizontal: bool) -> Vec<Pos2> {
+    let axis = |rect: &Rect| {
+        if horizontal {
+            (rect.left(), rect.width())
+        } else {
+            (rect.top(), rect.height())
+        }
+    };
+
+    let mut order: Vec<usize> = (0..rects.len()).collect();
+    order.sort_by(|&a, &b| {
+        let center = |index: usize| {
+            rects
+                .get(index)
+                .map_or(0.0, |rect| axis(rect).0 + axis(rect).1 * 0.5)
+        };
+        center(a).total_cmp(&center(b))
+    });
+
+    let sorted: Vec<Rect> = order
+        .iter()
+        .filter_map(|&index| rects.get(index).copied())
+        .collect();
+    let (Some(first), Some(last)) = (sorted.first(), sorted.last()) else {
+        return Vec::new();
+    };
+    let start = axis(first).0;
+    let end = axis(last).0 + axis(last).1;
+    let total_size: f32 = sorted.iter().map(|rect| axis(rect).1).sum();
+    let gap = (end - start - total_size) / (sorted.len().saturating_sub(1).max(1)) as f32;
+
+    let mut positions: Vec<Pos2> = rects.iter().map(|rect| rect.min).collect();
+    let mut cursor = start;
+    for (&index, rect) in order.iter().zip(&sorted) {
+        if let Some(position) = positions.get_mut(index) {
+            if horizontal {
+                position.x = cursor;
+            } else {
+                position.y = cursor;
+            }
+        }
+        cursor += axis(rect).1 + gap;
+    }
+    positions
+}
+
+impl NodeGraphApp {
+    /// 当前选中的节点能否执行该命令。
+    pub(super) fn can_align(&self, command: AlignCommand) -> bool {
+        self.selected_nodes.len() >= command.min_nodes()
+    }
+
+    /// 对选中节点执行对齐 / 分布命令，记录为一步撤销。
+    pub(super) fn align_selected(&mut self, command: AlignCommand) {
+        if !self.can_align(command) {
+            return;
+        }
+
+        let (ids, rects): (Vec<usize>, Vec<Rect>) = self
+            .nodes
+            .iter()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .map(|node| (node.id, node.world_rect()))
+            .unzip();
+        let positions = command.arrange(&rects);
+
+        self.record_undo();
+        for (id, position) in ids.into_iter().zip(positions) {
+            if let Some(node) = self.node_by_id_mut(id) {
+                node.position = position;
+            }
+        }
+    }
+
+    /// 对齐 / 分布快捷键（Alt + Shift + 字母），正在输入文字时不处理。
+    pub(super) fn handle_align_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        let command = ctx.input_mut(|i| {
+            AlignCommand::ALL
+                .into_iter()
+                .find(|command| i.consume_shortcut(&command.shortcut()))
+        });
+        if let Some(command) = command {
+            self.align_selected(command);
+        }
+    }
+
+    /// 侧边栏 / 右键菜单里的对齐按钮，返回被点击的命令。
+    pub(super) fn align_buttons(&self, ui: &mut egui::Ui) -> Option<AlignCommand> {
+        let mut chosen = None;
+        for command in AlignCommand::ALL {
+            let button = egui::Button::new(command.label())
+                .shortcut_text(ui.ctx().format_shortcut(&command.shortcut()));
+            if ui.add_enabled(self.can_align(command), button).clicked() {
+                chosen = Some(command);
+            }
+            if command == AlignCommand::CenterVertical {
+                ui.separator();
+            }
+        }
+        chosen
+    }
+}
@@ -0,0 +1,275 @@
+use egui::{Key, Pos2, Rect};
+
+use super::{NodeGraphApp, NodeKind, PortKind};
+
+// ============================================================
+// “添加节点”命令面板
+//
+// 在画布上按 Tab / Space 打开，输入关键字模糊搜索节点类型，
+// 回车或点击后在打开位置创建节点。
+// 如果打开时正在拖拽连线（或连线被拖到空白处松开），
+// 候选项只保留能接上的类型，新节点会自动接到这条连线上。
+// ============================================================
+
+// 最近使用列表最多保留几项。
+const MAX_RECENT_KINDS: usize = 5;
+// 面板宽度。
+const PALETTE_WIDTH: f32 = 220.0;
+
+/// 命令面板的运行时状态（打开时才存在）。
+pub(super) struct NodePalette {
+    /// 搜索关键字。
+    query: String,
+    /// 面板显示位置（屏幕坐标）。
+    screen_pos: Pos2,
+    /// 新节点中心位置（世界坐标）。
+    world_pos: Pos2,
+    /// 打开面板时正在拖拽的连线起点，选中节点后自动连接。
+    link_from: Option<(usize, PortKind)>,
+    /// 当前高亮的候选项下标（上下方向键切换）。
+    selected: usize,
+}
+
+impl NodePalette {
+    /// 在指针位置打开面板。
+    pub(super) fn new(
+        screen_pos: Pos2,
+        pan_offset: egui::Vec2,
+        link_from: Option<(usize, PortKind)>,
+    ) -> Self {
+        Self {
+            query: String::new(),
+            screen_pos,
+            world_pos: screen_pos - pan_offset,
+            link_from,
+            selected: 0,
+        }
+    }
+}
+
+/// 面板中的一行候选项。
+struct PaletteEntry {
+    kind: NodeKind,
+    recent: bool,
+}
+
+/// 简单的子序列模糊匹配：`query` 的字符需按顺序出现在 `candidate` 中。
+///
+/// 连续命中和从开头命中会加分，跳过的字符会扣分；不匹配返回 `None`。
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut next_index = 0;
+    let mut last_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let (found, _) = candidate
+            .iter()
+            .enumerate()
+            .skip(next_index)
+            .find(|&(_, &c)| c == query_char)?;
+
+        score += 1;
+        if found == 0 {
+            score += 10;
+        }
+        if last_match.is_some_and(|last| last + 1 == found) {
+            score += 5;
+        }
+        score -= (found - next_index) as i32;
+
+        last_match = Some(found);
+        next_index = found + 1;
+    }
+
+    Some(score)
+}
+
+impl NodeGraphApp {
+    /// 处理打开面板的快捷键（Tab / Space）。
+    ///
+    /// 需要在绘制任何控件之前调用：
+    /// 没有控件获得焦点时按 Tab，egui 会把焦点交给第一个控件，这里要把这次焦点移动取消掉。
+    pub(super) fn handle_palette_shortcut(&mut self, ctx: &egui::Context) {
+        if self.node_palette.is_some() || ctx.memory(|m| m.focused().is_some()) {
+            return;
+        }
+
+        let pressed = ctx.input(|i| {
+            i.modifiers.is_none() && (i.key_pressed(Key::Tab) || i.key_pressed(Key::Space))
+        });
+        let Some(pointer_pos) = ctx
+            .input(|i| i.pointer.hover_pos())
+            .filter(|pos| self.canvas_rect.contains(*pos))
+        else {
+            return;
+        };
+        if !pressed {
+            return;
+        }
+
+        ctx.memory_mut(|m| m.move_focus(egui::FocusDirection::None));
+
+        // 正在拖拽连线时打开：把连线交给面板，松开鼠标后不再丢弃。
+        // 拖线到此结束；改接时被拿起的原连线已经删掉，这一步要能撤销。
+        let link_from = self
+            .dragging_link
+            .take()
+            .map(|link| (link.from_node, link.from_port));
+        self.finish_link_edit();
+        self.node_palette = Some(NodePalette::new(pointer_pos, self.pan_offset, link_from));
+    }
+
+    /// 绘制命令面板并处理选择。
+    pub(super) fn show_node_palette(&mut self, ctx: &egui::Context) {
+        let Some(palette) = &mut self.node_palette else {
+            return;
+        };
+
+        let entries = Self::palette_entries(palette, &self.recent_node_kinds);
+        palette.selected = palette.selected.min(entries.len().saturating_sub(1));
+
+        // 方向键 / 回车 / Esc 先于文本框处理，避免被文本框吃掉。
+        let (move_down, move_up, confirm, cancel) = ctx.input_mut(|i| {
+            (
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowDown),
+                i.consume_key(egui::Modifiers::NONE, Key::ArrowUp),
+                i.consume_key(egui::Modifiers::NONE, Key::Enter),
+                i.consume_key(egui::Modifiers::NONE, Key::Escape),
+            )
+        });
+        if move_down && palette.selected + 1 < entries.len() {
+            palette.selected += 1;
+        }
+        if move_up {
+            palette.selected = palette.selected.saturating_sub(1);
+        }
+
+        let mut chosen = confirm
+            .then(|| entries.get(palette.selected).map(|entry| entry.kind))
+            .flatten();
+
+        let area_response = egui::Area::new(egui::Id::new("node_palette"))
+            .fixed_pos(palette.screen_pos)
+            .order(egui::Order::Foreground)
+            .show(ctx, |ui| {
+                egui::Frame::popup(ui.style()).show(ui, |ui| {
+                    ui.set_width(PALETTE_WIDTH);
+
+                    let search = ui.add(
+                        egui::TextEdit::singleline(&mut palette.query)
+                            .hint_text("Search nodes…")
+                            .desired_width(f32::INFINITY),
+                    );
+                    if !search.has_focus() {
+                        search.request_focus();
+                    }
+                    if search.changed() {
+                        palette.selected = 0;
+                    }
+                    ui.separator();
+
+                    if entries.is_empty() {
+                        ui.weak("No matching node types");
+                    }
+                    let mut shown_all_heading = false;
+                    for (index, entry) in entries.iter().enumerate() {
+                        if index == 0 && entry.recent {
+                            ui.weak("Recent");
+                        } else if !entry.recent
+                            && !shown_all_heading
+                            && palette.query.trim().is_empty()
+                        {
+                            ui.weak("All");
+                            shown_all_heading = true;
+                        }
+                        let response =
+                            ui.selectable_label(index == palette.selected, entry.kind.label());
+                        if response.hovered() {
+                            palette.selected = index;
+                        }
+                        if response.clicked() {
+                            chosen = Some(entry.kind);
+                        }
+                    }
+                });
+            });
+
+        if cancel || area_response.response.clicked_elsewhere() {
+            self.node_palette = None;
+        } else if let Some(kind) = chosen {
+            self.insert_from_palette(kind);
+        }
+    }
+
+    /// 根据搜索关键字生成候选列表。
+    ///
+    /// 关键字为空时先列出最近使用，再列出全部类型；否则按模糊匹配得分排序。
+    /// 如果面板带着一条连线，只保留能接上这条连线的类型。
+    fn palette_entries(palette: &NodePalette, recent: &[NodeKind]) -> Vec<PaletteEntry> {
+        let compatible = |kind: NodeKind| {
+            palette
+                .link_from
+                .is_none_or(|(_, from_port)| kind.has_port(from_port.opposite()))
+        };
+        let query = palette.query.trim();
+
+        if query.is_empty() {
+            let recent_entries = recent
+                .iter()
+                .copied()
+                .filter(|&kind| compatible(kind))
+                .map(|kind| PaletteEntry { kind, recent: true });
+            let other_entries = NodeKind::ALL
+                .into_iter()
+                .filter(|&kind| compatible(kind) && !recent.contains(&kind))
+                .map(|kind| PaletteEntry {
+                    kind,
+                    recent: false,
+                });
+            return recent_entries.chain(other_entries).collect();
+        }
+
+        let mut scored: Vec<(i32, NodeKind)> = NodeKind::ALL
+            .into_iter()
+            .filter(|&kind| compatible(kind))
+            .filter_map(|kind| fuzzy_score(query, kind.label()).map(|score| (score, kind)))
+            .collect();
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        scored
+            .into_iter()
+            .map(|(_, kind)| PaletteEntry {
+                kind,
+                recent: false,
+            })
+            .collect()
+    }
+
+    /// 在面板位置创建所选类型的节点，必要时接上面板携带的连线。
+    fn insert_from_palette(&mut self, kind: NodeKind) {
+        let Some(palette) = self.node_palette.take() else {
+            return;
+        };
+        self.record_undo();
+
+        match palette.link_from {
+            Some((from_node, from_port)) => {
+                // 带着连线时，让新节点上要接的端口正好落在面板位置（连线松开处）。
+                let port = from_port.opposite();
+                let size = kind.default_size();
+                let port_offset =
+                    Self::port_pos_in_rect(Rect::from_min_size(Pos2::ZERO, size), kind, port);
+                let new_id =
+                    self.add_node_at(kind, palette.world_pos - port_offset.to_vec2() + size * 0.5);
+                self.connect_ports(from_node, from_port, new_id);
+            }
+            None => {
+                self.add_node_at(kind, palette.world_pos);
+            }
+        }
+
+        self.recent_node_kinds.retain(|&recent| recent != kind);
+        self.recent_node_kinds.insert(0, kind);
+        self.recent_node_kinds.truncate(MAX_RECENT_KINDS);
+    }
+}
@@ -0,0 +1,123 @@
+use egui::{CursorIcon, Pos2, Rect, Sense, Vec2};
+
+use super::node_snap::NodeDrag;
+use super::{HEADER_HEIGHT, NODE_INNER_PADDING_Y, Node, NodeGraphApp};
+
+// ============================================================
+// 节点缩放
+//
+// 普通节点的四条边和四个角都可以拖动改变尺寸（不小于 `NODE_MIN_SIZE`）。
+// 开启“自动高度”后，高度随正文内容增长，只能拖动左右两侧改变宽度。
+// ============================================================
+
+// 节点最小尺寸。
+pub(super) const NODE_MIN_SIZE: Vec2 = Vec2::new(120.0, 80.0);
+// 边缘拖拽区域的厚度（一半在节点内，一半在节点外）。
+const RESIZE_HANDLE_THICKNESS: f32 = 6.0;
+// 正文区域上下额外留白：正文背景内边距 + 输入框自身的边距。
+const CONTENT_EXTRA_HEIGHT: f32 = 16.0;
+
+/// 缩放手柄：`(水平方向, 竖直方向)`，-1 表示左 / 上边，1 表示右 / 下边，0 表示不动。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ResizeHandle(i8, i8);
+
+impl ResizeHandle {
+    const ALL: [Self; 8] = [
+        Self(-1, 0),
+        Self(1, 0),
+        Self(0, -1),
+        Self(0, 1),
+        Self(-1, -1),
+        Self(1, -1),
+        Self(-1, 1),
+        Self(1, 1),
+    ];
+
+    /// 手柄在屏幕上的命中区域：边为一条细带（不含两端），角为一个小方块。
+    fn rect(self, node_rect: Rect) -> Rect {
+        let half = RESIZE_HANDLE_THICKNESS * 0.5;
+        let span = |side: i8, min: f32, max: f32| match side {
+            -1 => (min - half, min + half),
+            1 => (max - half, max + half),
+            _ => (min + half, max - half),
+        };
+        let (x0, x1) = span(self.0, node_rect.left(), node_rect.right());
+        let (y0, y1) = span(self.1, node_rect.top(), node_rect.bottom());
+        Rect::from_min_max(Pos2::new(x0, y0), Pos2::new(x1, y1))
+    }
+
+    fn cursor(self) -> CursorIcon {
+        match self {
+            Self(0, _) => CursorIcon::ResizeVertical,
+            Self(_, 0) => CursorIcon::ResizeHorizontal,
+            Self(x, y) if x == y => CursorIcon::ResizeNwSe,
+            _ => CursorIcon::ResizeNeSw,
+        }
+    }
+}
+
+impl NodeGraphApp {
+    /// 分配缩放手柄并处理拖动；返回是否正在缩放（缩放时节点边框高亮）。
+    ///
+    /// 新尺寸先记在 `drag` 里，吸附后再写回节点（见 `apply_node_drag`）。
+    /// 需要在端口交互之前调用，让端口的命中区域盖在手柄上面。
+    pub(super) fn handle_node_resize(
+        ui: &egui::Ui,
+        node: &Node,
+        node_rect: Rect,
+        drag: &mut Option<NodeDrag>,
+    ) -> bool {
+        let mut resizing = false;
+
+        for handle in ResizeHandle::ALL {
+            // 自动高度时，高度由内容决定，只保留左右两条边。
+            if node.auto_height && handle.1 != 0 {
+                continue;
+            }
+
+            let response = ui
+                .interact(
+                    handle.rect(node_rect),
+                    ui.make_persistent_id(("node_resize", node.id, handle.0, handle.1)),
+                    Sense::drag(),
+                )
+                .on_hover_cursor(handle.cursor());
+            if !response.dragged() {
+                continue;
+            }
+
+            resizing = true;
+            ui.ctx().set_cursor_icon(handle.cursor());
+            let delta = response.drag_delta();
+            let rect = &mut NodeDrag::for_node(drag, node, Some((handle.0, handle.1))).raw;
+            match handle.0 {
+                -1 => rect.min.x = (rect.min.x + delta.x).min(rect.max.x - NODE_MIN_SIZE.x),
+                1 => rect.max.x = (rect.max.x + delta.x).max(rect.min.x + NODE_MIN_SIZE.x),
+                _ => {}
+            }
+            match handle.1 {
+                -1 => rect.min.y = (rect.min.y + delta.y).min(rect.max.y - NODE_MIN_SIZE.y),
+                1 => rect.max.y = (rect.max.y + delta.y).max(rect.min.y + NODE_MIN_SIZE.y),
+                _ => {}
+            }
+        }
+
+        resizing
+    }
+
+    /// 自动高度：按正文在当前宽度下排版后的高度调整节点高度。
+    pub(super) fn fit_node_height_to_content(ui: &egui::Ui, node: &mut Node, text_width: f32) {
+        let galley = ui.painter().layout(
+            node.content.clone(),
+            egui::FontId::proportional(12.0),
+            egui::Color32::PLACEHOLDER,
+            text_width,
+        );
+        let height = HEADER_HEIGHT
+            + NODE_INNER_PADDING_Y * 2.0
+            + CONTENT_EXTRA_HEIGHT
+            + galley.size().y
+            + node.tag_row_height();
+        node.size.y = height.max(NODE_MIN_SIZE.y);
+    }
+}
@@ -0,0 +1,254 @@
+use std::collections::BTreeSet;
+
+use egui::{Color32, CornerRadius, Key, Rect, Stroke, StrokeKind};
+
+use super::{Node, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 搜索与筛选
+//
+// Ctrl + F 在画布右上角打开搜索栏，按标题、正文、标签和节点类型查找节点
+// （不区分大小写，多个词之间是“且”的关系，`#tag` 只匹配标签）：
+// - 命中的节点加描边高亮，当前命中项描边更粗
+// - 回车 / Shift + 回车（或 ◀ ▶ 按钮）在命中项之间跳转：
+//   跳转时选中该节点并平移视图，节点在子图里时先进入对应层级
+// - “Dim others” 打开后，没有命中的节点和连线变暗
+// ============================================================
+
+// 搜索栏离画布右上角的距离。
+const SEARCH_BAR_MARGIN: f32 = 10.0;
+// 筛选模式下盖在未命中节点上的遮罩不透明度。
+const DIM_ALPHA: u8 = 170;
+
+/// 搜索栏状态（打开时才有值）。
+#[derive(Debug, Default)]
+pub(super) struct NodeSearch {
+    query: String,
+    /// 命中的节点 ID，按层级和位置（从上到下、从左到右）排序。
+    matches: Vec<usize>,
+    /// 当前跳转到的命中项在 `matches` 里的下标。
+    current: Option<usize>,
+    /// 未命中的节点和连线变暗。
+    dim_others: bool,
+    /// 刚打开时让输入框获得焦点。
+    focus_query: bool,
+}
+
+impl NodeSearch {
+    fn new() -> Self {
+        Self {
+            focus_query: true,
+            ..Self::default()
+        }
+    }
+}
+
+/// 节点是否命中全部搜索词。
+fn node_matches(node: &Node, terms: &[String]) -> bool {
+    terms.iter().all(|term| {
+        if let Some(tag) = term.strip_prefix('#') {
+            return node.tags.iter().any(|own| own.to_lowercase().contains(tag));
+        }
+        node.title.to_lowercase().contains(term)
+            || node.content.to_lowercase().contains(term)
+            || node.kind.label().to_lowercase().contains(term)
+            || node
+                .tags
+                .iter()
+                .any(|own| own.to_lowercase().contains(term))
+    })
+}
+
+impl NodeGraphApp {
+    /// Ctrl + F 打开搜索栏（已打开时重新聚焦输入框）。
+    pub(super) fn handle_search_shortcut(&mut self, ctx: &egui::Context) {
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::COMMAND, Key::F)) {
+            self.node_search
+                .get_or_insert_with(NodeSearch::new)
+                .focus_query = true;
+        }
+    }
+
+    /// 按当前搜索词重新计算命中的节点；每帧绘制之前调用。
+    pub(super) fn update_search_matches(&mut self) {
+        let Some(search) = &self.node_search else {
+            return;
+        };
+        let terms: Vec<String> = search
+            .query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        let mut matches: Vec<&Node> = if terms.is_empty() {
+            Vec::new()
+        } else {
+            self.nodes
+                .iter()
+                .filter(|node| node.kind != NodeKind::Reroute && node_matches(node, &terms))
+                .collect()
+        };
+        matches.sort_by(|a, b| {
+            (a.parent, a.position.y, a.position.x)
+                .partial_cmp(&(b.parent, b.position.y, b.position.x))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let matches: Vec<usize> = matches.into_iter().map(|node| node.id).collect();
+
+        if let Some(search) = &mut self.node_search {
+            if search.matches != matches {
+                search.current = None;
+            }
+            search.matches = matches;
+        }
+    }
+
+    /// 筛选模式下命中的节点；没有开启筛选或搜索词为空时返回 `None`。
+    pub(super) fn search_filter(&self) -> Option<BTreeSet<usize>> {
+        self.node_search
+            .as_ref()
+            .filter(|search| search.dim_others && !search.query.trim().is_empty())
+            .map(|search| search.matches.iter().copied().collect())
+    }
+
+    /// 在节点上方画命中高亮；筛选模式下给未命中的节点盖一层遮罩。
+    pub(super) fn draw_search_highlights(&self, ui: &egui::Ui) {
+        let Some(search) = &self.node_search else {
+            return;
+        };
+        let current = search
+            .current
+            .and_then(|index| search.matches.get(index))
+            .copied();
+        let filter = self.search_filter();
+        let painter = ui.painter();
+
+        for node in self.visible_nodes() {
+            let rect = node.world_rect().translate(self.pan_offset);
+            if search.matches.contains(&node.id) {
+                let width = if current == Some(node.id) { 3.0 } else { 1.5 };
+                painter.rect_stroke(
+                    rect.expand(4.0),
+                    CornerRadius::same(10),
+                    Stroke::new(width, self.theme.search_match),
+                    StrokeKind::Outside,
+                );
+            } else if filter.is_some() {
+                let bg = self.theme.canvas_bg;
+                painter.rect_filled(
+                    rect.expand(2.0),
+                    CornerRadius::same(9),
+                    Color32::from_rgba_unmultiplied(bg.r(), bg.g(), bg.b(), DIM_ALPHA),
+                );
+            }
+        }
+    }
+
+    /// 画布右上角的搜索栏。
+    pub(super) fn draw_search_bar(&mut self, ctx: &egui::Context, canvas_rect: Rect) {
+        let Some(search) = &mut self.node_search else {
+            return;
+        };
+
+        let mut step = None;
+        let mut close = false;
+        // 画布一直延伸到窗口右边缘，所以直接贴着窗口右上角放。
+        egui::Area::new(egui::Id::new("node_search_bar"))
+            .anchor(
+                egui::Align2::RIGHT_TOP,
+                egui::vec2(-SEARCH_BAR_MARGIN, canvas_rect.top() + SEARCH_BAR_MARGIN),
+            )
+            .show(ctx, |ui| {
+                egui::Frame::new()
+                    .fill(self.theme.side_panel_bg)
+                    .corner_radius(CornerRadius::same(6))
+                    .inner_margin(egui::Margin::symmetric(8, 4))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            let response = ui.add(
+                                egui::TextEdit::singleline(&mut search.query)
+                                    .hint_text("Search nodes")
+                                    .desired_width(160.0),
+                            );
+                            if std::mem::take(&mut search.focus_query) {
+                                response.request_focus();
+                            }
+                            // 回车跳到下一个（Shift 反向）并保持焦点，Esc 关闭。
+                            if response.lost_focus() {
+                                if ui.input(|i| i.key_pressed(Key::Enter)) {
+                                    let back = ui.input(|i| i.modifiers.shift);
+                                    step = Some(if back { -1 } else { 1 });
+                                    response.request_focus();
+                                } else if ui.input(|i| i.key_pressed(Key::Escape)) {
+                                    close = true;
+                                }
+                            }
+
+                            let position = search
+                                .current
+                                .map_or_else(|| "-".to_owned(), |index| (index + 1).to_string());
+                            ui.weak(format!("{position}/{}", search.matches.len()));
+                            let has_matches = !search.matches.is_empty();
+                            if ui
+                                .add_enabled(has_matches, egui::Button::new("◀"))
+                                .clicked()
+                            {
+                                step = Some(-1);
+                            }
+                            if ui
+                                .add_enabled(has_matches, egui::Button::new("▶"))
+                                .clicked()
+                            {
+                                step = Some(1);
+                            }
+                            ui.checkbox(&mut search.dim_others, "Dim others");
+                            if ui.button("×").on_hover_text("Close (Esc)").clicked() {
+                                close = true;
+                            }
+                        });
+                    });
+            });
+
+        if close {
+            self.node_search = None;
+        } else if let Some(step) = step {
+            self.goto_search_match(step, canvas_rect);
+        }
+    }
+
+    /// 跳到上一个 / 下一个命中项：进入它所在的层级，选中它并平移视图。
+    fn goto_search_match(&mut self, step: isize, canvas_rect: Rect) {
+        let Some(search) = &mut self.node_search else {
+            return;
+        };
+        let count = search.matches.len();
+        if count == 0 {
+            return;
+        }
+        let index = match search.current {
+            Some(current) => (current + count).wrapping_add_signed(step) % count,
+            None if step < 0 => count - 1,
+            None => 0,
+        };
+        search.current = Some(index);
+        let Some(node) = search
+            .matches
+            .get(index)
+            .copied()
+            .and_then(|id| self.node_by_id(id))
+            .cloned()
+        else {
+            return;
+        };
+
+        if node.parent != self.current_subgraph {
+            self.open_subgraph(node.parent, canvas_rect);
+        }
+        // 被折叠分组框藏起来的节点，平移到分组框上。
+        let bounds = self
+            .collapsed_frame_of(node.id)
+            .map_or_else(|| node.world_rect(), |frame| frame.visible_rect());
+        self.center_view_on(bounds, canvas_rect);
+        self.selected_nodes = std::iter::once(node.id).collect();
+        self.selected_link = None;
+    }
+}
@@ -0,0 +1,231 @@
+use egui::{Pos2, Rangef, Rect, Stroke, Vec2};
+
+use super::node_resize::NODE_MIN_SIZE;
+use super::{GRID_SPACING, Node, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 网格吸附与对齐参考线
+//
+// 拖动或缩放节点时，先在 `NodeDrag` 里累积“未吸附”的矩形，
+// 等所有节点绘制完，再统一计算吸附后的位置（这时才能看到其他节点）：
+// - 对齐参考线：移动中的边 / 中线靠近其他节点的边 / 中线时对齐，并画出参考线
+// - 网格吸附：没有对齐到参考线的方向，吸附到小网格
+// 按住 Alt 临时反转这两个开关。
+// ============================================================
+
+// 对齐参考线的吸附距离。
+const GUIDE_SNAP_DISTANCE: f32 = 6.0;
+
+/// 拖动 / 缩放中的节点，`raw` 为没有吸附时的世界坐标矩形。
+#[derive(Clone, Copy, Debug)]
+pub(super) struct NodeDrag {
+    node_id: usize,
+    pub(super) raw: Rect,
+    /// 缩放手柄 `(水平, 竖直)`；`None` 表示整体移动。
+    resize: Option<(i8, i8)>,
+    /// 这次拖动是否已经记录了撤销。
+    recorded_undo: bool,
+}
+
+impl NodeDrag {
+    /// 取出该节点的拖动状态；不存在或属于别的节点时重新开始。
+    pub(super) fn for_node<'a>(
+        slot: &'a mut Option<Self>,
+        node: &Node,
+        resize: Option<(i8, i8)>,
+    ) -> &'a mut Self {
+        let drag = slot
+            .take()
+            .filter(|drag| drag.node_id == node.id && drag.resize == resize)
+            .unwrap_or(Self {
+                node_id: node.id,
+                raw: node.world_rect(),
+                resize,
+                recorded_undo: false,
+            });
+        slot.insert(drag)
+    }
+}
+
+/// 某个方向上参与吸附的边。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum SnapEdges {
+    /// 整体移动：两条边和中线都参与对齐，网格吸附用 `anchor_center` 决定对齐中心还是起始边。
+    Both { anchor_center: bool },
+    /// 缩放时只移动较小的一边。
+    Min,
+    /// 缩放时只移动较大的一边。
+    Max,
+    /// 这个方向不动。
+    None,
+}
+
+impl SnapEdges {
+    fn for_resize_side(side: i8) -> Self {
+        match side {
+            -1 => Self::Min,
+            1 => Self::Max,
+            _ => Self::None,
+        }
+    }
+}
+
+/// 吸附到网格。
+fn snap_to_grid(value: f32) -> f32 {
+    (value / GRID_SPACING).round() * GRID_SPACING
+}
+
+/// 在一个方向上吸附一段区间；返回吸附后的区间和命中的参考线位置。
+fn snap_span(
+    raw: Rangef,
+    edges: SnapEdges,
+    lines: &[f32],
+    grid: bool,
+    min_length: f32,
+) -> (Rangef, Option<f32>) {
+    let candidates: Vec<f32> = match edges {
+        SnapEdges::Both { .. } => vec![raw.min, raw.center(), raw.max],
+        SnapEdges::Min => vec![raw.min],
+        SnapEdges::Max => vec![raw.max],
+        SnapEdges::None => return (raw, None),
+    };
+
+    let guide = candidates
+        .iter()
+        .flat_map(|&candidate| lines.iter().map(move |&line| (line - candidate, line)))
+        .filter(|(delta, _)| delta.abs() <= GUIDE_SNAP_DISTANCE)
+        .min_by(|a, b| a.0.abs().total_cmp(&b.0.abs()));
+
+    let (delta, line) = match (guide, edges) {
+        (Some((delta, line)), _) => (delta, Some(line)),
+        (None, _) if !grid => (0.0, None),
+        (None, SnapEdges::Both { anchor_center }) => {
+            let anchor = if anchor_center { raw.center() } else { raw.min };
+            (snap_to_grid(anchor) - anchor, None)
+        }
+        (None, SnapEdges::Min) => (snap_to_grid(raw.min) - raw.min, None),
+        (None, _) => (snap_to_grid(raw.max) - raw.max, None),
+    };
+
+    let snapped = match edges {
+        SnapEdges::Min => Rangef::new(raw.min + delta, raw.max),
+        SnapEdges::Max => Rangef::new(raw.min, raw.max + delta),
+        _ => Rangef::new(raw.min + delta, raw.max + delta),
+    };
+    // 缩放吸附后不能小于最小尺寸，否则放弃这次吸附。
+    if snapped.span() < min_length {
+        (raw, None)
+    } else {
+        (snapped, line)
+    }
+}
+
+impl NodeGraphApp {
+    /// 把拖动 / 缩放中的节点移到吸附后的位置，并画出命中的对齐参考线。
+    ///
+    /// 需要在所有节点绘制之后调用。
+    pub(super) fn apply_node_drag(&mut self, ui: &egui::Ui) {
+        if !ui.input(|i| i.pointer.primary_down()) {
+            self.node_drag = None;
+            return;
+        }
+        let Some(drag) = self.node_drag.as_mut() else {
+            return;
+        };
+        // 整次拖动记为一步撤销：第一次应用之前，节点还在原位。
+        if !std::mem::replace(&mut drag.recorded_undo, true) {
+            self.record_undo();
+        }
+        let Some(drag) = self.node_drag else {
+            return;
+        };
+        let Some(node) = self.node_by_id(drag.node_id) else {
+            self.node_drag = None;
+            return;
+        };
+
+        let invert = ui.input(|i| i.modifiers.alt);
+        let grid = self.snap_to_grid != invert;
+        let guides = self.alignment_guides != invert;
+
+        // 整体移动时转接点很小，按中心对齐网格更自然。
+        let moving = SnapEdges::Both {
+            anchor_center: node.kind == NodeKind::Reroute,
+        };
+        let (x_edges, y_edges) = drag.resize.map_or((moving, moving), |(x, y)| {
+            (SnapEdges::for_resize_side(x), SnapEdges::for_resize_side(y))
+        });
+        let min_size = if drag.resize.is_some() {
+            NODE_MIN_SIZE
+        } else {
+            Vec2::ZERO
+        };
+
+        let others: Vec<Rect> = self
+            .visible_nodes()
+            .filter(|other| other.id != drag.node_id)
+            .map(|other| other.world_rect())
+            .collect();
+        let lines = |range: fn(&Rect) -> Rangef| -> Vec<f32> {
+            if !guides {
+                return Vec::new();
+            }
+            others
+                .iter()
+                .flat_map(|rect| {
+                    let range = range(rect);
+                    [range.min, range.center(), range.max]
+                })
+                .collect()
+        };
+
+        let (x_range, x_guide) = snap_span(
+            drag.raw.x_range(),
+            x_edges,
+            &lines(Rect::x_range),
+            grid,
+            min_size.x,
+        );
+        let (y_range, y_guide) = snap_span(
+            drag.raw.y_range(),
+            y_edges,
+            &lines(Rect::y_range),
+            grid,
+            min_size.y,
+        );
+
+        let rect = Rect::from_x_y_ranges(x_range, y_range);
+        let moved_by = rect.min - node.position;
+        if let Some(node) = self.node_by_id_mut(drag.node_id) {
+            node.position = rect.min;
+            node.size = rect.size();
+        }
+        // 拖动选中节点之一时，其余选中节点一起移动。
+        if drag.resize.is_none() && self.selected_nodes.contains(&drag.node_id) {
+            for node in &mut self.nodes {
+                if node.id != drag.node_id && self.selected_nodes.contains(&node.id) {
+                    node.position += moved_by;
+                }
+            }
+        }
+
+        // 参考线贯穿整个画布。
+        let painter = ui.painter();
+        let canvas = self.canvas_rect;
+        let stroke = Stroke::new(1.0, self.theme.snap_guide);
+        if let Some(x) = x_guide {
+            let x = x + self.pan_offset.x;
+            painter.line_segment(
+                [Pos2::new(x, canvas.top()), Pos2::new(x, canvas.bottom())],
+                stroke,
+            );
+        }
+        if let Some(y) = y_guide {
+            let y = y + self.pan_offset.y;
+            painter.line_segment(
+                [Pos2::new(canvas.left(), y), Pos2::new(canvas.right(), y)],
+                stroke,
+            );
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::BTreeMap;
+
+use egui::{Align2, Color32, CornerRadius, FontId, Rect, Vec2};
+
+use super::context_menu::{ContextAction, HEADER_COLOR_PRESETS};
+use super::{NODE_INNER_PADDING_X, Node, NodeGraphApp, Theme};
+
+// ============================================================
+// 节点图标与标签
+//
+// 每个节点可以在标题前显示一个图标字符，并带若干自由填写的标签。
+// 标签以彩色小胶囊显示在正文下方，同名标签颜色相同；
+// 可以在节点右键菜单或检查器里修改，随文档保存，
+// 侧边栏按标签选中节点，搜索时也会匹配标签。
+// ============================================================
+
+// 右键菜单 / 检查器里可直接选用的图标。
+pub(super) const ICON_PRESETS: [&str; 10] = ["★", "⚙", "⚡", "⏱", "✉", "☁", "⚠", "🔒", "📄", "🔍"];
+// 标题栏里图标占用的宽度。
+pub(super) const ICON_WIDTH: f32 = 20.0;
+// 正文下方标签行的高度。
+const TAG_ROW_HEIGHT: f32 = 18.0;
+const TAG_FONT_SIZE: f32 = 10.5;
+// 标签胶囊相对文字的内边距，以及相邻胶囊的间距。
+const TAG_PADDING: Vec2 = Vec2::new(5.0, 1.5);
+const TAG_SPACING: f32 = 4.0;
+// 浅色主题下胶囊用标签色的浅色底（不透明度），配深色文字。
+const TAG_LIGHT_FILL_ALPHA: u8 = 70;
+
+/// 规范化输入的标签：去掉首尾空白和开头的 `#`，空标签返回 `None`。
+pub(super) fn normalize_tag(text: &str) -> Option<String> {
+    let tag = text.trim().trim_start_matches('#').trim();
+    (!tag.is_empty()).then(|| tag.to_owned())
+}
+
+/// 两个标签是否相同（不区分大小写）。
+pub(super) fn same_tag(a: &str, b: &str) -> bool {
+    a.to_lowercase() == b.to_lowercase()
+}
+
+/// 标签胶囊的颜色：按标签文字固定取一个预设色，同名标签颜色一致。
+pub(super) fn tag_color(tag: &str) -> Color32 {
+    let hash = tag.to_lowercase().bytes().fold(0usize, |hash, byte| {
+        hash.wrapping_mul(31).wrapping_add(usize::from(byte))
+    });
+    HEADER_COLOR_PRESETS
+        .get(hash % HEADER_COLOR_PRESETS.len())
+        .copied()
+        .unwrap_or(Color32::GRAY)
+}
+
+/// 标签胶囊的填充色：深色主题用标签色本身，浅色主题用它的浅色底，文字颜色见 `Theme::tag_text`。
+pub(super) fn tag_fill(theme: &Theme, tag: &str) -> Color32 {
+    let color = tag_color(tag);
+    if theme.dark {
+        color
+    } else {
+        Color32::from_rgba_unmultiplied(color.r(), color.g(), color.b(), TAG_LIGHT_FILL_ALPHA)
+    }
+}
+
+impl Node {
+    /// 节点是否带有某个标签（不区分大小写）。
+    pub(super) fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|own| same_tag(own, tag))
+    }
+
+    /// 正文下方标签行占用的高度；没有标签时为 0。
+    pub(super) fn tag_row_height(&self) -> f32 {
+        if self.tags.is_empty() {
+            0.0
+        } else {
+            TAG_ROW_HEIGHT
+        }
+    }
+}
+
+/// 图标 / 标签相关的操作。
+#[derive(Clone, Debug)]
+pub(super) enum TagAction {
+    /// 设置标题前的图标，`None` 表示不显示。
+    SetIcon {
+        node_id: usize,
+        icon: Option<String>,
+    },
+    AddTag {
+        node_id: usize,
+        tag: String,
+    },
+    RemoveTag {
+        node_id: usize,
+        tag: String,
+    },
+    /// 选中当前层级里带有该标签的全部节点。
+    SelectTagged(String),
+}
+
+impl TagAction {
+    pub(super) fn is_undoable(&self) -> bool {
+        !matches!(self, Self::SelectTagged(_))
+    }
+}
+
+impl NodeGraphApp {
+    pub(super) fn apply_tag_action(&mut self, action: TagAction) {
+        match action {
+            TagAction::SetIcon { node_id, icon } => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.icon = icon;
+                }
+            }
+            TagAction::AddTag { node_id, tag } => {
+                if let Some(node) = self.node_by_id_mut(node_id)
+                    && !node.has_tag(&tag)
+                {
+                    node.tags.push(tag);
+                }
+            }
+            TagAction::RemoveTag { node_id, tag } => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.tags.retain(|own| !same_tag(own, &tag));
+                }
+            }
+            TagAction::SelectTagged(tag) => {
+                self.selected_nodes = self
+                    .visible_nodes()
+                    .filter(|node| node.has_tag(&tag))
+                    .map(|node| node.id)
+                    .collect();
+                self.selected_link = None;
+            }
+        }
+    }
+
+    /// 当前层级里出现过的标签及带有它的节点数。
+    pub(super) fn tags_in_scope(&self) -> BTreeMap<String, usize> {
+        let mut tags = BTreeMap::new();
+        for node in self.visible_nodes() {
+            for tag in &node.tags {
+                *tags.entry(tag.to_lowercase()).or_insert(0) += 1;
+            }
+        }
+        tags
+    }
+
+    /// 在标题栏左侧画图标。
+    pub(super) fn draw_node_icon(ui: &egui::Ui, theme: &Theme, icon: &str, header_rect: Rect) {
+        ui.painter().text(
+            egui::pos2(
+                header_rect.left() + NODE_INNER_PADDING_X + ICON_WIDTH * 0.5 - 2.0,
+                header_rect.center().y,
+            ),
+            Align2::CENTER_CENTER,
+            icon,
+            FontId::proportional(14.0),
+            theme.node_title_text,
+        );
+    }
+
+    /// 在节点底部画一行标签胶囊；放不下的部分显示为 `+N`。
+    pub(super) fn draw_tag_chips(ui: &egui::Ui, theme: &Theme, tags: &[String], node_rect: Rect) {
+        let row = Rect::from_min_max(
+            egui::pos2(
+                node_rect.left() + NODE_INNER_PADDING_X,
+                node_rect.bottom() - TAG_ROW_HEIGHT - 4.0,
+            ),
+            egui::pos2(
+                node_rect.right() - NODE_INNER_PADDING_X,
+                node_rect.bottom() - 4.0,
+            ),
+        );
+        let painter = ui.painter().with_clip_rect(row);
+        let font = FontId::proportional(TAG_FONT_SIZE);
+        let mut x = row.left();
+        for (index, tag) in tags.iter().enumerate() {
+            let galley = painter.layout_no_wrap(tag.clone(), font.clone(), theme.tag_text);
+            let width = galley.size().x + TAG_PADDING.x * 2.0;
+            // 最后留出 `+N` 的位置。
+            let remaining = tags.len() - index - 1;
+            let reserve = if remaining > 0 { 24.0 } else { 0.0 };
+            if x + width + reserve > row.right() && index > 0 {
+                painter.text(
+                    egui::pos2(x, row.center().y),
+                    Align2::LEFT_CENTER,
+                    format!("+{}", tags.len() - index),
+                    font,
+                    theme.tag_overflow,
+                );
+                return;
+            }
+
+            let chip = Rect::from_min_size(
+                egui::pos2(x, row.center().y - galley.size().y * 0.5 - TAG_PADDING.y),
+                Vec2::new(width, galley.size().y + TAG_PADDING.y * 2.0),
+            );
+            painter.rect_filled(chip, CornerRadius::same(7), tag_fill(theme, tag));
+            painter.galley(chip.min + TAG_PADDING, galley, theme.tag_text);
+            x += width + TAG_SPACING;
+        }
+    }
+
+    /// 节点右键菜单里的“图标”子菜单。
+    pub(super) fn icon_menu_contents(ui: &mut egui::Ui, node_id: usize) -> Option<ContextAction> {
+        let mut action = None;
+        ui.horizontal_wrapped(|ui| {
+            for glyph in ICON_PRESETS {
+                if ui.button(glyph).clicked() {
+                    action = Some(TagAction::SetIcon {
+                        node_id,
+                        icon: Some(glyph.to_owned()),
+                    });
+                }
+            }
+        });
+        if ui.button("None").clicked() {
+            action = Some(TagAction::SetIcon {
+                node_id,
+                icon: None,
+            });
+        }
+        action.map(ContextAction::Tag)
+    }
+
+    /// 节点右键菜单里的“标签”子菜单：移除已有标签，输入新标签后回车或点 Add 添加。
+    pub(super) fn tags_menu_contents(
+        &mut self,
+        ui: &mut egui::Ui,
+        node_id: usize,
+    ) -> Option<ContextAction> {
+        let tags = self
+            .node_by_id(node_id)
+            .map(|node| node.tags.clone())
+            .unwrap_or_default();
+        let mut action = None;
+        for tag in tags {
+            ui.horizontal(|ui| {
+                ui.label(format!("#{tag}"));
+                if ui.small_button("×").on_hover_text("Remove tag").clicked() {
+                    action = Some(TagAction::RemoveTag { node_id, tag });
+                }
+            });
+        }
+        if let Some(tag) = self.new_tag_input(ui) {
+            action = Some(TagAction::AddTag { node_id, tag });
+        }
+        action.map(ContextAction::Tag)
+    }
+
+    /// 新标签输入框：回车或点 Add 时返回规范化后的标签。
+    pub(super) fn new_tag_input(&mut self, ui: &mut egui::Ui) -> Option<String> {
+        ui.horizontal(|ui| {
+            let response = ui.add(
+                egui::TextEdit::singleline(&mut self.tag_input)
+                    .hint_text("New tag")
+                    .desired_width(100.0),
+            );
+            let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+            if ui.button("Add").clicked() || submitted {
+                let tag = normalize_tag(&self.tag_input);
+                self.tag_input.clear();
+                return tag;
+            }
+            None
+        })
+        .inner
+    }
+
+    /// 侧边栏里的标签列表：点击一个标签选中带有它的全部节点。
+    pub(super) fn tag_filter_contents(&mut self, ui: &mut egui::Ui) {
+        let tags = self.tags_in_scope();
+        if tags.is_empty() {
+            ui.weak("No tags in this graph");
+            return;
+        }
+        ui.horizontal_wrapped(|ui| {
+            for (tag, count) in tags {
+                if ui
+                    .link(format!("#{tag} ({count})"))
+                    .on_hover_text("Select tagged nodes")
+                    .clicked()
+                {
+                    self.pending_action = Some(ContextAction::Tag(TagAction::SelectTagged(tag)));
+                }
+            }
+        });
+    }
+}
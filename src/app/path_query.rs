@@ -0,0 +1,674 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use egui::{Align2, Color32, CornerRadius, FontId, Stroke, StrokeKind};
+
+use super::context_menu::ContextAction;
+use super::dependency_highlight::FlowDirection;
+use super::{LinkKey, NodeGraphApp, NodeKind};
+
+// ============================================================
+// 路径与可达性查询
+//
+// 回答“A 能不能到达 B、经过哪些节点”：
+// - 最短路径：按跳数，或按连线属性（标签里的数字 / 线宽）加权
+// - 列出 A 到 B 的全部简单路径（不重复经过节点），数量有上限
+// - 检查一组节点里哪些节点对互相都到达不了
+// 查询本身是 `NodeGraphApp` 上的公开方法，不依赖界面，宿主程序可以直接调用；
+// 侧边栏 “Paths” 和节点右键菜单 “Path Start / Path End” 是它们的界面。
+// ============================================================
+
+// 枚举简单路径时最多尝试走的连线数，避免稠密的图卡住界面。
+const PATH_SEARCH_BUDGET: usize = 20_000;
+// 侧边栏里“最多列出几条路径”的默认值和上限。
+const DEFAULT_PATH_LIMIT: usize = 10;
+const MAX_PATH_LIMIT: usize = 100;
+
+/// 计算路径长度时每条连线的代价。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PathWeight {
+    /// 每条连线记 1，即跳数。
+    #[default]
+    Hops,
+    /// 连线标签开头的数字（如 `3`、`2.5 ms`）；标签不是数字或为负数时记 1。
+    LabelNumber,
+    /// 连线线宽。
+    LineWidth,
+}
+
+impl PathWeight {
+    const ALL: [Self; 3] = [Self::Hops, Self::LabelNumber, Self::LineWidth];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::Hops => "Hop count",
+            Self::LabelNumber => "Label number",
+            Self::LineWidth => "Line width",
+        }
+    }
+}
+
+/// 一条沿连线方向的路径。
+#[derive(Clone, Debug, PartialEq)]
+pub struct GraphPath {
+    /// 依次经过的节点 ID，含起点和终点。
+    pub nodes: Vec<usize>,
+    /// 按查询时的 `PathWeight` 累计的代价。
+    pub cost: f32,
+}
+
+impl GraphPath {
+    /// 路径经过的连线（起点节点 ID，终点节点 ID）。
+    pub fn links(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.nodes
+            .iter()
+            .zip(self.nodes.iter().skip(1))
+            .map(|(&from, &to)| (from, to))
+    }
+
+    /// 经过的连线数。
+    pub fn hops(&self) -> usize {
+        self.nodes.len().saturating_sub(1)
+    }
+}
+
+/// 深度优先枚举简单路径。
+struct PathSearch<'a> {
+    adjacency: &'a BTreeMap<usize, Vec<(usize, f32)>>,
+    target: usize,
+    limit: usize,
+    budget: usize,
+    stack: Vec<usize>,
+    found: Vec<GraphPath>,
+}
+
+impl PathSearch<'_> {
+    fn visit(&mut self, current: usize, cost: f32) {
+        if current == self.target {
+            self.found.push(GraphPath {
+                nodes: self.stack.clone(),
+                cost,
+            });
+            return;
+        }
+        let adjacency = self.adjacency;
+        for &(next, link_cost) in adjacency.get(&current).into_iter().flatten() {
+            if self.found.len() >= self.limit || self.budget == 0 {
+                return;
+            }
+            if self.stack.contains(&next) {
+                continue;
+            }
+            self.budget -= 1;
+            self.stack.push(next);
+            self.visit(next, cost + link_cost);
+            self.stack.pop();
+        }
+    }
+}
+
+/// 路径查询的哪一端。
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) enum PathEndpoint {
+    Start,
+    End,
+}
+
+/// 侧边栏路径查询的状态与本帧结果。
+#[derive(Debug)]
+pub(super) struct PathQuery {
+    start: Option<usize>,
+    end: Option<usize>,
+    weight: PathWeight,
+    /// 最多列出几条简单路径。
+    limit: usize,
+    shortest: Option<GraphPath>,
+    paths: Vec<GraphPath>,
+    /// 列表里点中的路径；`None` 时高亮最短路径。
+    shown: Option<usize>,
+    /// 最近一次 “Check Selection” 找到的互不可达节点对。
+    unreachable: Option<Vec<(usize, usize)>>,
+}
+
+impl Default for PathQuery {
+    fn default() -> Self {
+        Self {
+            start: None,
+            end: None,
+            weight: PathWeight::default(),
+            limit: DEFAULT_PATH_LIMIT,
+            shortest: None,
+            paths: Vec::new(),
+            shown: None,
+            unreachable: None,
+        }
+    }
+}
+
+impl PathQuery {
+    /// 画布上高亮的路径。
+    fn highlighted(&self) -> Option<&GraphPath> {
+        self.shown
+            .and_then(|index| self.paths.get(index))
+            .or(self.shortest.as_ref())
+    }
+}
+
+impl NodeGraphApp {
+    /// 沿连线方向，`from_node_id` 能否到达 `to_node_id`（节点到达自身）。
+    pub fn is_reachable(&self, from_node_id: usize, to_node_id: usize) -> bool {
+        from_node_id == to_node_id
+            || self
+                .reachable_from(
+                    &std::iter::once(from_node_id).collect(),
+                    FlowDirection::Downstream,
+                )
+                .0
+                .contains(&to_node_id)
+    }
+
+    /// 从 `from_node_id` 到 `to_node_id` 代价最小的路径；到达不了时返回 `None`。
+    ///
+    /// 起点和终点相同时返回只含该节点、代价为 0 的路径。
+    pub fn shortest_path(
+        &self,
+        from_node_id: usize,
+        to_node_id: usize,
+        weight: PathWeight,
+    ) -> Option<GraphPath> {
+        let adjacency = self.path_adjacency(weight);
+        let mut costs: BTreeMap<usize, f32> = std::iter::once((from_node_id, 0.0)).collect();
+        let mut previous: BTreeMap<usize, usize> = BTreeMap::new();
+        let mut settled = BTreeSet::new();
+        // 每次取出还没确定的节点里代价最小的一个（图不大，线性查找即可）。
+        while let Some((current, cost)) = costs
+            .iter()
+            .filter(|(id, _)| !settled.contains(*id))
+            .min_by(|a, b| a.1.total_cmp(b.1))
+            .map(|(&id, &cost)| (id, cost))
+        {
+            if current == to_node_id {
+                let mut nodes = vec![current];
+                while let Some(prev) = nodes.last().and_then(|id| previous.get(id)).copied() {
+                    nodes.push(prev);
+                }
+                nodes.reverse();
+                return Some(GraphPath { nodes, cost });
+            }
+            settled.insert(current);
+            for &(next, link_cost) in adjacency.get(&current).into_iter().flatten() {
+                let candidate = cost + link_cost;
+                if !settled.contains(&next)
+                    && costs.get(&next).is_none_or(|&known| candidate < known)
+                {
+                    costs.insert(next, candidate);
+                    previous.insert(next, current);
+                }
+            }
+        }
+        None
+    }
+
+    /// 从 `from_node_id` 到 `to_node_id` 的简单路径（不重复经过节点），最多 `limit` 条，按代价从小到大排序。
+    ///
+    /// 路径很多时只返回最先找到的 `limit` 条，不保证是代价最小的几条。
+    pub fn simple_paths(
+        &self,
+        from_node_id: usize,
+        to_node_id: usize,
+        weight: PathWeight,
+        limit: usize,
+    ) -> Vec<GraphPath> {
+        if limit == 0 {
+            return Vec::new();
+        }
+        let adjacency = self.path_adjacency(weight);
+        let mut search = PathSearch {
+            adjacency: &adjacency,
+            target: to_node_id,
+            limit,
+            budget: PATH_SEARCH_BUDGET,
+            stack: vec![from_node_id],
+            found: Vec::new(),
+        };
+        search.visit(from_node_id, 0.0);
+        let mut paths = search.found;
+        paths.sort_by(|a, b| {
+            a.cost
+                .total_cmp(&b.cost)
+                .then(a.nodes.len().cmp(&b.nodes.len()))
+        });
+        paths
+    }
+
+    /// `node_ids` 里两两互相都到达不了的节点对，按在 `node_ids` 里的先后顺序给出。
+    pub fn unreachable_pairs(&self, node_ids: &[usize]) -> Vec<(usize, usize)> {
+        let reachable: BTreeMap<usize, BTreeSet<usize>> = node_ids
+            .iter()
+            .map(|&id| {
+                let roots = std::iter::once(id).collect();
+                (id, self.reachable_from(&roots, FlowDirection::Downstream).0)
+            })
+            .collect();
+        let reaches = |from: usize, to: usize| {
+            reachable
+                .get(&from)
+                .is_some_and(|targets| targets.contains(&to))
+        };
+        let mut pairs = Vec::new();
+        for (index, &a) in node_ids.iter().enumerate() {
+            for &b in node_ids.iter().skip(index + 1) {
+                if a != b && !reaches(a, b) && !reaches(b, a) {
+                    pairs.push((a, b));
+                }
+            }
+        }
+        pairs
+    }
+
+    /// 每个节点的出边及其代价。
+    fn path_adjacency(&self, weight: PathWeight) -> BTreeMap<usize, Vec<(usize, f32)>> {
+        let mut adjacency: BTreeMap<usize, Vec<(usize, f32)>> = BTreeMap::new();
+        for connection in &self.connections {
+            let cost = match weight {
+                PathWeight::Hops => 1.0,
+                PathWeight::LabelNumber => connection
+                    .label
+                    .split_whitespace()
+                    .next()
+                    .and_then(|token| token.parse::<f32>().ok())
+                    .filter(|cost| cost.is_finite() && *cost >= 0.0)
+                    .unwrap_or(1.0),
+                PathWeight::LineWidth => connection.style.width,
+            };
+            adjacency
+                .entry(connection.from_node_id)
+                .or_default()
+                .push((connection.to_node_id, cost));
+        }
+        adjacency
+    }
+
+    /// 设置路径查询的起点或终点。
+    pub(super) fn set_path_endpoint(&mut self, node_id: usize, endpoint: PathEndpoint) {
+        let query = &mut self.path_query;
+        match endpoint {
+            PathEndpoint::Start => query.start = Some(node_id),
+            PathEndpoint::End => query.end = Some(node_id),
+        }
+        query.shown = None;
+    }
+
+    /// 每帧绘制之前重新计算起点到终点的路径；端点被删除时清空它。
+    pub(super) fn update_path_query(&mut self) {
+        let exists = |id: &usize| self.node_by_id(*id).is_some();
+        let start = self.path_query.start.filter(exists);
+        let end = self.path_query.end.filter(exists);
+        let (shortest, paths) = match (start, end) {
+            (Some(start), Some(end)) => (
+                self.shortest_path(start, end, self.path_query.weight),
+                self.simple_paths(start, end, self.path_query.weight, self.path_query.limit),
+            ),
+            _ => (None, Vec::new()),
+        };
+
+        let query = &mut self.path_query;
+        query.start = start;
+        query.end = end;
+        if query.shown.is_some_and(|index| index >= paths.len()) {
+            query.shown = None;
+        }
+        query.shortest = shortest;
+        query.paths = paths;
+    }
+
+    /// 连线是否在高亮的路径上，是则返回高亮色。
+    pub(super) fn path_link_color(&self, link: LinkKey) -> Option<Color32> {
+        self.path_query
+            .highlighted()
+            .is_some_and(|path| path.links().any(|key| key == link))
+            .then_some(self.theme.path)
+    }
+
+    /// 给高亮路径上的节点（或代表它们的子图节点）画描边，并标出起点和终点。
+    pub(super) fn draw_path_highlights(&self, ui: &egui::Ui) {
+        let painter = ui.painter();
+        let query = &self.path_query;
+        if let Some(path) = query.highlighted() {
+            let representatives: BTreeSet<usize> = path
+                .nodes
+                .iter()
+                .filter_map(|&id| self.scope_representative(id))
+                .collect();
+            for node in self
+                .visible_nodes()
+                .filter(|node| representatives.contains(&node.id))
+            {
+                painter.rect_stroke(
+                    self.node_rect_screen(node).expand(3.0),
+                    CornerRadius::same(10),
+                    Stroke::new(2.5, self.theme.path),
+                    StrokeKind::Outside,
+                );
+            }
+        }
+
+        for (id, text) in [(query.start, "Start"), (query.end, "End")] {
+            let Some(node) = id
+                .and_then(|id| self.scope_representative(id))
+                .and_then(|id| self.node_by_id(id))
+                .filter(|node| node.parent == self.current_subgraph)
+            else {
+                continue;
+            };
+            let rect = self.node_rect_screen(node);
+            painter.text(
+                rect.left_top() + egui::vec2(2.0, -6.0),
+                Align2::LEFT_BOTTOM,
+                text,
+                FontId::proportional(11.0),
+                self.theme.path,
+            );
+        }
+    }
+
+    /// 节点右键菜单里的 “Path Start / Path End”。
+    pub(super) fn path_menu_buttons(ui: &mut egui::Ui, node_id: usize) -> Option<ContextAction> {
+        let mut action = None;
+        for (label, endpoint) in [
+            ("Path Start", PathEndpoint::Start),
+            ("Path End", PathEndpoint::End),
+        ] {
+            if ui.button(label).clicked() {
+                action = Some(ContextAction::SetPathEndpoint { node_id, endpoint });
+            }
+        }
+        action
+    }
+
+    /// 侧边栏 “Paths”：选择起点 / 终点和代价，显示最短路径与全部简单路径。
+    pub(super) fn path_panel_contents(&mut self, ui: &mut egui::Ui) {
+        // 只选中一个节点时，可以直接把它设为起点或终点。
+        let selected = (self.selected_nodes.len() == 1)
+            .then(|| self.selected_nodes.first().copied())
+            .flatten()
+            .filter(|&id| {
+                self.node_by_id(id)
+                    .is_some_and(|node| node.kind != NodeKind::Subgraph)
+            });
+        for (label, endpoint) in [("From", PathEndpoint::Start), ("To", PathEndpoint::End)] {
+            let current = match endpoint {
+                PathEndpoint::Start => self.path_query.start,
+                PathEndpoint::End => self.path_query.end,
+            };
+            ui.horizontal(|ui| {
+                let name = current.map_or_else(|| "-".to_owned(), |id| self.node_name(id));
+                ui.label(format!("{label}: {name}"));
+                if ui
+                    .add_enabled(selected.is_some(), egui::Button::new("Use selected"))
+                    .clicked()
+                    && let Some(id) = selected
+                {
+                    self.set_path_endpoint(id, endpoint);
+                }
+            });
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Swap").clicked() {
+                let query = &mut self.path_query;
+                std::mem::swap(&mut query.start, &mut query.end);
+                query.shown = None;
+            }
+            if ui.button("Clear").clicked() {
+                self.path_query = PathQuery::default();
+            }
+        });
+
+        let query = &mut self.path_query;
+        egui::ComboBox::from_label("Cost")
+            .selected_text(query.weight.label())
+            .show_ui(ui, |ui| {
+                for weight in PathWeight::ALL {
+                    ui.selectable_value(&mut query.weight, weight, weight.label());
+                }
+            });
+        ui.add(
+            egui::DragValue::new(&mut query.limit)
+                .range(1..=MAX_PATH_LIMIT)
+                .prefix("Max paths: "),
+        );
+
+        self.path_results(ui);
+        ui.separator();
+        self.unreachable_selection_contents(ui);
+    }
+
+    /// 起点到终点的查询结果：到达不了时给出提示，否则列出路径，点击一条在画布上高亮它。
+    fn path_results(&mut self, ui: &mut egui::Ui) {
+        let query = &self.path_query;
+        if query.start.is_none() || query.end.is_none() {
+            ui.weak("Pick a start and an end node");
+            return;
+        }
+        let Some(shortest) = &query.shortest else {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "End is not reachable from start",
+            );
+            return;
+        };
+        ui.label(format!(
+            "Shortest: {} hops, cost {}",
+            shortest.hops(),
+            shortest.cost
+        ));
+
+        let mut shown = query.shown;
+        for (index, path) in query.paths.iter().enumerate() {
+            let names: Vec<String> = path.nodes.iter().map(|&id| self.node_name(id)).collect();
+            let text = format!("{}. {} ({})", index + 1, names.join(" → "), path.cost);
+            if ui.selectable_label(shown == Some(index), text).clicked() {
+                shown = (shown != Some(index)).then_some(index);
+            }
+        }
+        if query.paths.len() >= query.limit {
+            ui.weak("More paths may exist");
+        }
+        self.path_query.shown = shown;
+    }
+
+    /// 检查选中的节点里哪些节点对互相都到达不了。
+    fn unreachable_selection_contents(&mut self, ui: &mut egui::Ui) {
+        if ui
+            .add_enabled(
+                self.selected_nodes.len() > 1,
+                egui::Button::new("Check Selection"),
+            )
+            .on_hover_text("List selected node pairs that cannot reach each other")
+            .clicked()
+        {
+            let ids: Vec<usize> = self.selected_nodes.iter().copied().collect();
+            self.path_query.unreachable = Some(self.unreachable_pairs(&ids));
+        }
+        let Some(pairs) = &self.path_query.unreachable else {
+            return;
+        };
+        if pairs.is_empty() {
+            ui.weak("Every selected pair is connected");
+        }
+        for &(a, b) in pairs {
+            ui.label(format!("{} ↮ {}", self.node_name(a), self.node_name(b)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{GraphPath, PATH_SEARCH_BUDGET, PathWeight};
+    use crate::app::NodeGraphApp;
+
+    /// 建一张空图，按名称添加节点，返回 App 和按添加顺序排列的节点 ID。
+    fn graph(names: &[&str]) -> (NodeGraphApp, Vec<usize>) {
+        let mut app = NodeGraphApp::empty();
+        let ids = names.iter().map(|name| app.add_graph_node(name)).collect();
+        (app, ids)
+    }
+
+    fn link(app: &mut NodeGraphApp, from: usize, to: usize, label: &str) {
+        assert!(
+            app.add_graph_link(from, to),
+            "连线 {from} -> {to} 应该能添加"
+        );
+        assert!(
+            app.set_link_label(from, to, label),
+            "连线 {from} -> {to} 应该存在"
+        );
+    }
+
+    /// a -> b -> d 跳数少但标签代价大，a -> c -> e -> d 跳数多但标签代价小。
+    fn two_routes() -> (NodeGraphApp, [usize; 5]) {
+        let (mut app, ids) = graph(&["a", "b", "c", "d", "e"]);
+        let [a, b, c, d, e] = ids[..] else {
+            panic!("应该有 5 个节点");
+        };
+        link(&mut app, a, b, "5");
+        link(&mut app, b, d, "5 ms");
+        link(&mut app, a, c, "1");
+        link(&mut app, c, e, "1");
+        link(&mut app, e, d, "1");
+        (app, [a, b, c, d, e])
+    }
+
+    #[test]
+    fn shortest_path_by_hops_and_by_label_number() {
+        let (app, [a, b, c, d, e]) = two_routes();
+        let hops = app.shortest_path(a, d, PathWeight::Hops);
+        assert_eq!(
+            hops,
+            Some(GraphPath {
+                nodes: vec![a, b, d],
+                cost: 2.0
+            }),
+            "按跳数应该走 a -> b -> d"
+        );
+        let labels = app.shortest_path(a, d, PathWeight::LabelNumber);
+        assert_eq!(
+            labels,
+            Some(GraphPath {
+                nodes: vec![a, c, e, d],
+                cost: 3.0
+            }),
+            "按标签数字应该走 a -> c -> e -> d"
+        );
+    }
+
+    #[test]
+    fn shortest_path_unreachable_and_same_endpoint() {
+        let (app, [a, _, _, d, _]) = two_routes();
+        assert_eq!(
+            app.shortest_path(d, a, PathWeight::Hops),
+            None,
+            "逆着连线方向到达不了"
+        );
+        assert_eq!(
+            app.shortest_path(a, a, PathWeight::LabelNumber),
+            Some(GraphPath {
+                nodes: vec![a],
+                cost: 0.0
+            }),
+            "起点即终点时是只含该节点、代价为 0 的路径"
+        );
+    }
+
+    #[test]
+    fn simple_paths_respects_limit_and_sorts_by_cost() {
+        let (app, [a, b, c, d, e]) = two_routes();
+        let all = app.simple_paths(a, d, PathWeight::LabelNumber, 10);
+        let nodes: Vec<_> = all.iter().map(|path| path.nodes.clone()).collect();
+        assert_eq!(
+            nodes,
+            vec![vec![a, c, e, d], vec![a, b, d]],
+            "两条路径都应该列出，代价小的在前"
+        );
+        assert_eq!(
+            app.simple_paths(a, d, PathWeight::Hops, 1).len(),
+            1,
+            "只要 1 条时只返回 1 条"
+        );
+        assert!(
+            app.simple_paths(a, d, PathWeight::Hops, 0).is_empty(),
+            "上限为 0 时不返回路径"
+        );
+    }
+
+    #[test]
+    fn simple_paths_do_not_loop_on_cycles() {
+        let (mut app, ids) = graph(&["a", "b", "c"]);
+        let [a, b, c] = ids[..] else {
+            panic!("应该有 3 个节点");
+        };
+        link(&mut app, a, b, "");
+        link(&mut app, b, a, "");
+        link(&mut app, b, c, "");
+        let paths = app.simple_paths(a, c, PathWeight::Hops, 10);
+        assert_eq!(
+            paths,
+            vec![GraphPath {
+                nodes: vec![a, b, c],
+                cost: 2.0
+            }],
+            "环上的节点不应重复经过"
+        );
+    }
+
+    #[test]
+    fn simple_paths_stop_at_search_budget() {
+        // 起点 -> 8 层、每层 4 个两两全连的节点 -> 终点，共 4^8 条路径，超出搜索预算。
+        const LAYERS: u32 = 8;
+        const WIDTH: usize = 4;
+        let mut app = NodeGraphApp::empty();
+        let start = app.add_graph_node("start");
+        let mut previous = vec![start];
+        for layer in 0..LAYERS {
+            let current: Vec<usize> = (0..WIDTH)
+                .map(|index| app.add_graph_node(&format!("{layer}-{index}")))
+                .collect();
+            for &from in &previous {
+                for &to in &current {
+                    assert!(app.add_graph_link(from, to), "层间连线应该能添加");
+                }
+            }
+            previous = current;
+        }
+        let end = app.add_graph_node("end");
+        for &from in &previous {
+            assert!(app.add_graph_link(from, end), "连到终点的连线应该能添加");
+        }
+
+        let total = WIDTH.pow(LAYERS);
+        assert!(total > PATH_SEARCH_BUDGET, "测试图的路径数应该超出搜索预算");
+        let found = app
+            .simple_paths(start, end, PathWeight::Hops, usize::MAX)
+            .len();
+        assert!(found > 0, "预算内应该能找到一些路径");
+        assert!(found < total, "超出预算后应该停止搜索，实际找到 {found} 条");
+    }
+
+    #[test]
+    fn unreachable_pairs_lists_disconnected_nodes() {
+        let (mut app, ids) = graph(&["a", "b", "c", "d"]);
+        let [a, b, c, d] = ids[..] else {
+            panic!("应该有 4 个节点");
+        };
+        link(&mut app, a, b, "");
+        link(&mut app, d, b, "");
+        assert_eq!(
+            app.unreachable_pairs(&[a, b, c, d]),
+            vec![(a, c), (a, d), (b, c), (c, d)],
+            "只有 a、b 和 d、b 之间能到达"
+        );
+        assert!(
+            app.unreachable_pairs(&[b, a]).is_empty(),
+            "反过来列出时方向也不影响结果"
+        );
+    }
+}
@@ -0,0 +1,540 @@
+use std::collections::BTreeMap;
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::documents::{Document, DocumentGraph};
+use super::template_library::draw_graph_preview;
+use super::{APP_NAME, NodeGraphApp};
+
+// ============================================================
+// 自动保存与崩溃恢复
+//
+// 有未保存修改的文档会写进恢复目录（默认在 eframe 保存应用状态的目录下）：
+// - 每隔 `AUTOSAVE_INTERVAL` 秒把每个修改过的文档完整写成一个快照文件
+// - 两次快照之间，有输入后（最多每秒一次）检查每个修改过的文档，内容有变化的
+//   往快照日志末尾追加一行该文档的完整快照；日志记的是整份文档，不是逐条编辑，
+//   写快照文件后日志清空
+// - 文档保存或关闭时追加一条“已无需恢复”的记录，并删除它的快照
+// 正常退出时清空恢复目录；下次启动时目录里还有内容，说明上次没有正常退出，
+// 这时弹出恢复窗口，列出每个可恢复文档的预览；勾选的文档如果已经随应用状态
+// 打开（同一个恢复 ID），就用恢复的内容替换它，否则在新标签页里打开。
+// ============================================================
+
+// 两次完整快照之间的间隔（秒）。
+const AUTOSAVE_INTERVAL: f64 = 30.0;
+// 两次检查文档是否需要写快照日志之间的最短间隔（秒）。
+const SNAPSHOT_LOG_INTERVAL: f64 = 1.0;
+const SNAPSHOT_LOG_FILE: &str = "snapshots.log";
+// 取不到 eframe 的存储目录时使用的恢复目录（相对当前目录）。
+const FALLBACK_RECOVERY_DIR: &str = "renode-recovery";
+const SNAPSHOT_EXTENSION: &str = "ron";
+
+/// 快照文件和快照日志里的一条记录。
+#[derive(Clone, serde::Deserialize, serde::Serialize)]
+struct RecoveryRecord {
+    /// 文档的恢复 ID。
+    document: String,
+    name: String,
+    path: String,
+    /// 记录时间（Unix 时间戳，秒）。
+    time: u64,
+    /// 文档内容；`None` 表示文档已保存或关闭，不需要恢复。
+    graph: Option<DocumentGraph>,
+}
+
+/// 启动时发现的一个可恢复文档。
+struct RecoveryCandidate {
+    record: RecoveryRecord,
+    /// 是否勾选恢复。
+    restore: bool,
+}
+
+/// 自动保存的计时和启动时的恢复状态。
+#[derive(Default)]
+pub(super) struct Recovery {
+    last_autosave: f64,
+    last_log_check: f64,
+    /// 有新的输入，还没检查文档是否需要写快照日志。
+    check_pending: bool,
+    /// 每个文档最近一次写入快照日志的内容的哈希，内容没变时不重复写。
+    logged: BTreeMap<String, u64>,
+    /// 启动时发现的可恢复文档；恢复窗口关闭后为 `None`。
+    candidates: Option<Vec<RecoveryCandidate>>,
+}
+
+/// 当前的 Unix 时间戳（秒）。
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// “多久以前”，用于恢复窗口。
+fn time_ago(time: u64) -> String {
+    match unix_now().saturating_sub(time) {
+        0..60 => "just now".to_owned(),
+        seconds @ 60..3600 => format!("{} min ago", seconds / 60),
+        seconds @ 3600..86400 => format!("{} h ago", seconds / 3600),
+        seconds => format!("{} days ago", seconds / 86400),
+    }
+}
+
+/// 默认的恢复目录：eframe 保存应用状态的目录下的 `recovery`，取不到时为当前目录下的 `renode-recovery`。
+pub(super) fn default_recovery_dir() -> String {
+    eframe::storage_dir(APP_NAME).map_or_else(
+        || FALLBACK_RECOVERY_DIR.to_owned(),
+        |dir| dir.join("recovery").display().to_string(),
+    )
+}
+
+/// 读取恢复目录：快照为底，再按顺序应用日志里更新的记录；只保留仍需恢复的文档，最新的在前。
+fn scan_recovery_dir(dir: &Path) -> Vec<RecoveryCandidate> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut records: Vec<RecoveryRecord> = Vec::new();
+    let mut apply = |record: RecoveryRecord| {
+        records.retain(|known| known.document != record.document);
+        records.push(record);
+    };
+
+    for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SNAPSHOT_EXTENSION) {
+            continue;
+        }
+        match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| ron::from_str::<RecoveryRecord>(&text).map_err(|err| err.to_string()))
+        {
+            Ok(record) => apply(record),
+            Err(err) => log::warn!("读取恢复快照失败 '{}'：{err}", path.display()),
+        }
+    }
+    // 日志最后一行可能在崩溃时只写了一半，解析失败的行直接跳过。
+    if let Ok(log) = std::fs::read_to_string(dir.join(SNAPSHOT_LOG_FILE)) {
+        for line in log.lines().filter(|line| !line.trim().is_empty()) {
+            if let Ok(record) = ron::from_str::<RecoveryRecord>(line) {
+                apply(record);
+            }
+        }
+    }
+
+    records.retain(|record| record.graph.is_some());
+    records.sort_by_key(|record| std::cmp::Reverse(record.time));
+    records
+        .into_iter()
+        .map(|record| RecoveryCandidate {
+            record,
+            restore: true,
+        })
+        .collect()
+}
+
+impl NodeGraphApp {
+    fn recovery_path(&self) -> PathBuf {
+        PathBuf::from(&self.recovery_dir)
+    }
+
+    fn snapshot_path(&self, document_id: &str) -> PathBuf {
+        self.recovery_path()
+            .join(format!("{document_id}.{SNAPSHOT_EXTENSION}"))
+    }
+
+    /// 生成一个还没被使用的文档恢复 ID。
+    pub(super) fn new_recovery_id(&self) -> String {
+        let mut seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_nanos());
+        loop {
+            let id = format!("{seed:x}");
+            if self
+                .documents
+                .iter()
+                .all(|document| document.recovery_id != id)
+            {
+                return id;
+            }
+            seed += 1;
+        }
+    }
+
+    /// 启动时检查恢复目录，有可恢复的文档时准备弹出恢复窗口。
+    pub(super) fn scan_recovery(&mut self) {
+        let candidates = scan_recovery_dir(&self.recovery_path());
+        self.recovery.candidates = (!candidates.is_empty()).then_some(candidates);
+    }
+
+    /// 往快照日志末尾追加一条记录。
+    fn append_snapshot_log(&self, record: &RecoveryRecord) {
+        let dir = self.recovery_path();
+        let result = ron::to_string(record)
+            .map_err(|err| err.to_string())
+            .and_then(|line| {
+                std::fs::create_dir_all(&dir)
+                    .and_then(|()| {
+                        std::fs::OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(dir.join(SNAPSHOT_LOG_FILE))
+                    })
+                    .and_then(|mut file| writeln!(file, "{line}"))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            log::warn!("写入快照日志失败 '{}'：{err}", dir.display());
+        }
+    }
+
+    /// 文档已保存或即将关闭：记下它不再需要恢复，并删除它的快照。
+    pub(super) fn forget_recovery(&mut self, index: usize) {
+        let Some(document) = self.documents.get(index) else {
+            return;
+        };
+        if self.recovery.candidates.is_some() {
+            return;
+        }
+        let record = RecoveryRecord {
+            document: document.recovery_id.clone(),
+            name: document.name.clone(),
+            path: document.path.clone(),
+            time: unix_now(),
+            graph: None,
+        };
+        self.append_snapshot_log(&record);
+        let _ignored: std::io::Result<()> =
+            std::fs::remove_file(self.snapshot_path(&record.document));
+        self.recovery.logged.remove(&record.document);
+    }
+
+    /// 第 `index` 个文档当前的图数据；前台文档的图数据在 `NodeGraphApp` 上。
+    fn document_graph(&self, index: usize) -> Option<DocumentGraph> {
+        if index == self.active_document {
+            Some(self.current_graph())
+        } else {
+            self.documents
+                .get(index)
+                .map(|document| document.graph.clone())
+        }
+    }
+
+    /// 每个修改过的文档和上次写日志时不同，就往快照日志追加一条它的完整快照。
+    ///
+    /// 后台文档也要检查：切换标签页前最后一次修改可能还没写进日志。
+    fn log_modified_documents(&mut self) {
+        for index in 0..self.documents.len() {
+            let Some(document) = self
+                .documents
+                .get(index)
+                .filter(|document| document.modified)
+            else {
+                continue;
+            };
+            let Some(graph) = self.document_graph(index) else {
+                continue;
+            };
+            let Ok(text) = ron::to_string(&graph) else {
+                continue;
+            };
+            let mut hasher = DefaultHasher::new();
+            text.hash(&mut hasher);
+            let hash = hasher.finish();
+            if self.recovery.logged.get(&document.recovery_id) == Some(&hash) {
+                continue;
+            }
+            let record = RecoveryRecord {
+                document: document.recovery_id.clone(),
+                name: document.name.clone(),
+                path: document.path.clone(),
+                time: unix_now(),
+                graph: Some(graph),
+            };
+            self.append_snapshot_log(&record);
+            self.recovery.logged.insert(record.document, hash);
+        }
+    }
+
+    /// 给每个修改过的文档写一份快照，删除其余的快照，然后清空快照日志。
+    fn autosave_documents(&self) {
+        let dir = self.recovery_path();
+        if let Err(err) = std::fs::create_dir_all(&dir) {
+            log::warn!("创建恢复目录失败 '{}'：{err}", dir.display());
+            return;
+        }
+        let time = unix_now();
+        let mut kept = Vec::new();
+        for (index, document) in self.documents.iter().enumerate() {
+            if !document.modified {
+                continue;
+            }
+            let Some(graph) = self.document_graph(index) else {
+                continue;
+            };
+            let record = RecoveryRecord {
+                document: document.recovery_id.clone(),
+                name: document.name.clone(),
+                path: document.path.clone(),
+                time,
+                graph: Some(graph),
+            };
+            let path = self.snapshot_path(&record.document);
+            let result = ron::ser::to_string_pretty(&record, ron::ser::PrettyConfig::default())
+                .map_err(|err| err.to_string())
+                .and_then(|text| std::fs::write(&path, text).map_err(|err| err.to_string()));
+            match result {
+                Ok(()) => kept.push(path),
+                // 快照没写成功时保留快照日志，崩溃后还能从日志恢复。
+                Err(err) => {
+                    log::warn!("写入恢复快照失败 '{}'：{err}", path.display());
+                    return;
+                }
+            }
+        }
+        self.remove_recovery_files(&kept);
+    }
+
+    /// 删除恢复目录里的快照日志和 `keep` 以外的快照。
+    fn remove_recovery_files(&self, keep: &[PathBuf]) {
+        let dir = self.recovery_path();
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            return;
+        };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            let ours = path.extension().and_then(|ext| ext.to_str()) == Some(SNAPSHOT_EXTENSION)
+                || path.file_name().and_then(|name| name.to_str()) == Some(SNAPSHOT_LOG_FILE);
+            if ours
+                && !keep.contains(&path)
+                && let Err(err) = std::fs::remove_file(&path)
+            {
+                log::warn!("删除恢复文件失败 '{}'：{err}", path.display());
+            }
+        }
+    }
+
+    /// 正常退出时清空恢复目录；恢复窗口还没处理时保留，下次启动再问。
+    pub(super) fn clear_recovery(&self) {
+        if self.recovery.candidates.is_none() {
+            self.remove_recovery_files(&[]);
+        }
+    }
+
+    /// 每帧调用：有输入后最多每秒写一次快照日志，并定期写快照文件。
+    pub(super) fn update_recovery(&mut self, ctx: &egui::Context) {
+        // 恢复窗口还开着时不动恢复目录，免得覆盖掉待恢复的内容。
+        if self.recovery.candidates.is_some() {
+            return;
+        }
+        let now = ctx.input(|i| i.time);
+        if ctx.input(|i| !i.events.is_empty() || i.pointer.any_down()) {
+            self.recovery.check_pending = true;
+        }
+        if self.recovery.check_pending {
+            let wait = SNAPSHOT_LOG_INTERVAL - (now - self.recovery.last_log_check);
+            if wait > 0.0 {
+                // 编辑停下来以后也要再检查一次，保证最后一次修改写进日志。
+                ctx.request_repaint_after_secs(wait as f32);
+            } else {
+                self.log_modified_documents();
+                self.recovery.last_log_check = now;
+                self.recovery.check_pending = false;
+            }
+        }
+        if now - self.recovery.last_autosave >= AUTOSAVE_INTERVAL {
+            self.autosave_documents();
+            self.recovery.last_autosave = now;
+        }
+    }
+
+    /// 启动时的恢复窗口：列出可恢复的文档及预览；勾选的文档已经打开时替换它的内容，否则在新标签页里打开。
+    pub(super) fn show_recovery_prompt(&mut self, ctx: &egui::Context) {
+        let Some(mut candidates) = self.recovery.candidates.take() else {
+            return;
+        };
+        let theme = self.theme;
+        let mut decision = None;
+        egui::Modal::new(egui::Id::new("recovery_prompt")).show(ctx, |ui| {
+            ui.heading("Recover unsaved work?");
+            ui.label("Renode did not shut down cleanly. These documents had unsaved changes:");
+            egui::ScrollArea::vertical()
+                .max_height(360.0)
+                .show(ui, |ui| {
+                    for candidate in &mut candidates {
+                        let record = &candidate.record;
+                        let Some(graph) = &record.graph else {
+                            continue;
+                        };
+                        ui.separator();
+                        ui.checkbox(&mut candidate.restore, &record.name);
+                        ui.weak(format!(
+                            "{} nodes, {} links, {}",
+                            graph.nodes.len(),
+                            graph.connections.len(),
+                            time_ago(record.time)
+                        ));
+                        draw_graph_preview(ui, &theme, &graph.nodes, &graph.connections);
+                    }
+                });
+            ui.separator();
+            ui.horizontal(|ui| {
+                if ui.button("Recover Selected").clicked() {
+                    decision = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    decision = Some(false);
+                }
+            });
+        });
+
+        let Some(recover) = decision else {
+            self.recovery.candidates = Some(candidates);
+            return;
+        };
+        self.remove_recovery_files(&[]);
+        if !recover {
+            return;
+        }
+        for candidate in candidates.into_iter().filter(|candidate| candidate.restore) {
+            let record = candidate.record;
+            let Some(graph) = record.graph else {
+                continue;
+            };
+            if let Some(index) = self
+                .documents
+                .iter()
+                .position(|document| document.recovery_id == record.document)
+            {
+                self.restore_open_document(index, graph);
+                continue;
+            }
+            let document = Document::recovered(
+                self.new_recovery_id(),
+                format!("{} (recovered)", record.name),
+                record.path,
+                graph,
+            );
+            self.push_document(document, ctx);
+        }
+        self.status_message = Some("Recovered unsaved documents".to_owned());
+    }
+
+    /// 随应用状态打开的文档就是崩溃前的那一个，但可能是较旧的版本：换成恢复出来的内容。
+    fn restore_open_document(&mut self, index: usize, graph: DocumentGraph) {
+        if index == self.active_document {
+            self.apply_graph(graph);
+            self.selected_nodes.clear();
+            self.selected_link = None;
+        } else if let Some(document) = self.documents.get_mut(index) {
+            document.graph = graph;
+        }
+        if let Some(document) = self.documents.get_mut(index) {
+            document.modified = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{RecoveryRecord, SNAPSHOT_LOG_FILE, scan_recovery_dir, unix_now};
+    use crate::app::NodeGraphApp;
+    use crate::app::undo::TextEditEvents;
+
+    fn record(document: &str, app: Option<&NodeGraphApp>) -> RecoveryRecord {
+        RecoveryRecord {
+            document: document.to_owned(),
+            name: document.to_owned(),
+            path: String::new(),
+            time: unix_now(),
+            graph: app.map(NodeGraphApp::current_graph),
+        }
+    }
+
+    #[test]
+    fn snapshot_log_overrides_snapshots() {
+        let dir = std::env::temp_dir().join(format!("renode-recovery-test-{}", unix_now()));
+        std::fs::create_dir_all(&dir).expect("应该能创建临时目录");
+        let mut app = NodeGraphApp::empty();
+        let snapshot = ron::to_string(&record("a", Some(&app))).expect("记录应该能序列化");
+        std::fs::write(dir.join("a.ron"), snapshot).expect("应该能写快照");
+        app.add_graph_node("later");
+        let lines = [
+            ron::to_string(&record("a", Some(&app))).expect("记录应该能序列化"),
+            ron::to_string(&record("b", Some(&app))).expect("记录应该能序列化"),
+            ron::to_string(&record("b", None)).expect("记录应该能序列化"),
+            "(document: \"c\", name:".to_owned(),
+        ];
+        std::fs::write(dir.join(SNAPSHOT_LOG_FILE), lines.join("\n")).expect("应该能写日志");
+
+        let candidates = scan_recovery_dir(&dir);
+        std::fs::remove_dir_all(&dir).expect("应该能删除临时目录");
+        let documents: Vec<(&str, usize)> = candidates
+            .iter()
+            .map(|candidate| {
+                let nodes = candidate
+                    .record
+                    .graph
+                    .as_ref()
+                    .map_or(0, |graph| graph.nodes.len());
+                (candidate.record.document.as_str(), nodes)
+            })
+            .collect();
+        assert_eq!(
+            documents,
+            [("a", 1)],
+            "日志里较新的快照覆盖快照文件，已关闭的文档和写了一半的行都不恢复"
+        );
+    }
+
+    #[test]
+    fn recovered_content_replaces_open_document() {
+        let mut app = NodeGraphApp::empty();
+        let mut recovered = NodeGraphApp::empty();
+        recovered.add_graph_node("recovered");
+        app.restore_open_document(0, recovered.current_graph());
+        assert_eq!(app.documents.len(), 1, "不应再打开一个同 ID 的标签页");
+        assert!(
+            app.documents
+                .first()
+                .is_some_and(|document| document.modified),
+            "替换后的文档应该标记为有未保存的修改"
+        );
+        assert_eq!(
+            app.node_ids()
+                .iter()
+                .filter_map(|&id| app.node_title(id))
+                .collect::<Vec<_>>(),
+            ["recovered"],
+            "前台文档应该换成恢复的内容"
+        );
+    }
+
+    #[test]
+    fn text_only_edit_reaches_snapshot_log() {
+        let dir = std::env::temp_dir().join(format!("renode-text-edit-test-{}", unix_now()));
+        let mut app = NodeGraphApp::empty();
+        app.recovery_dir = dir.display().to_string();
+        let node = app.add_graph_node("draft");
+        let widget = egui::Id::new("title");
+        let focus = TextEditEvents {
+            gained_focus: true,
+            ..TextEditEvents::default()
+        };
+        app.track_text_events(widget, focus, "draft");
+        if let Some(node) = app.node_by_id_mut(node) {
+            "typed on canvas".clone_into(&mut node.title);
+        }
+        let typed = TextEditEvents {
+            changed: true,
+            ..TextEditEvents::default()
+        };
+        app.track_text_events(widget, typed, "typed on canvas");
+        app.log_modified_documents();
+
+        let log = std::fs::read_to_string(dir.join(SNAPSHOT_LOG_FILE));
+        let _ignored: std::io::Result<()> = std::fs::remove_dir_all(&dir);
+        assert!(
+            log.is_ok_and(|log| log.contains("typed on canvas")),
+            "只在画布上改过文字的文档也应该写进快照日志"
+        );
+    }
+}
@@ -0,0 +1,459 @@
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+
+use egui::{CornerRadius, CursorIcon, FontId, Pos2, Rect, Sense, Vec2};
+
+use super::context_menu::ContextAction;
+use super::{
+    DragLinkState, HEADER_HEIGHT, NODE_SIZE, Node, NodeGraphApp, NodeKind, PORT_HIT_RADIUS,
+    PORT_OUTSET, PortKind, Theme,
+};
+
+// ============================================================
+// 子图
+//
+// 所有节点仍然存放在同一个 `nodes` 列表里，子图只是一层“归属”关系：
+// - 节点的 `parent` 指向包含它的子图节点，`None` 表示在顶层
+// - 画布一次只显示一层（`current_subgraph`），更深层的节点由本层的子图节点代表
+// - 跨越子图边界的连线接到子图节点的“外露端口”上：每个被外部连入的内部节点一个输入端口，
+//   每个连到外部的内部节点一个输出端口；从外露端口拖线等于直接连到内部节点
+// 连线始终记录真实的两端节点，所以展开（解散）子图时原样恢复平铺布局。
+// ============================================================
+
+// 外露端口的竖直间距下限，端口多时节点会显得拥挤，但不会重叠。
+const EXPOSED_PORT_MIN_SPACING: f32 = 18.0;
+// 面包屑导航距画布左上角的距离。
+const BREADCRUMB_MARGIN: f32 = 10.0;
+
+/// 子图相关的菜单操作。
+#[derive(Clone, Copy, Debug)]
+pub(super) enum SubgraphAction {
+    /// 把选中节点收进一个新的子图节点。
+    CollapseSelection,
+    /// 进入某一层（`None` 为顶层），面包屑导航也用它返回上层。
+    Open(Option<usize>),
+    /// 解散子图：内部节点回到子图所在的层，子图节点删除。
+    Ungroup(usize),
+}
+
+impl SubgraphAction {
+    pub(super) fn is_undoable(self) -> bool {
+        !matches!(self, Self::Open(_))
+    }
+}
+
+/// 子图节点的外露端口：对应的内部节点 ID（按 ID 排序）。
+#[derive(Clone, Default)]
+struct ExposedPorts {
+    inputs: Vec<usize>,
+    outputs: Vec<usize>,
+}
+
+impl ExposedPorts {
+    fn side(&self, port: PortKind) -> &[usize] {
+        match port {
+            PortKind::Input => &self.inputs,
+            PortKind::Output => &self.outputs,
+        }
+    }
+}
+
+/// 当前层每个子图节点的外露端口。
+///
+/// 每条连线的两个端点都要查外露端口位置，逐个扫描全部连线太慢，
+/// 所以每帧绘制连线前一次算好（见 `update_exposed_ports`），切换文档时清空。
+#[derive(Default)]
+pub(super) struct ExposedPortCache {
+    ports: BTreeMap<usize, ExposedPorts>,
+}
+
+/// 第 `index` 个（共 `count` 个）外露端口在节点矩形上的位置：在正文区域竖直均匀分布。
+fn exposed_port_pos(rect: Rect, port: PortKind, index: usize, count: usize) -> Pos2 {
+    let top = rect.top() + HEADER_HEIGHT;
+    let spacing = ((rect.bottom() - top) / (count + 1) as f32).max(EXPOSED_PORT_MIN_SPACING);
+    let y = top + spacing * (index + 1) as f32;
+    match port {
+        PortKind::Input => Pos2::new(rect.left() - PORT_OUTSET, y),
+        PortKind::Output => Pos2::new(rect.right() + PORT_OUTSET, y),
+    }
+}
+
+impl NodeGraphApp {
+    /// `node_id` 是否在子图 `subgraph_id` 里（任意深度）。
+    pub(super) fn is_inside(&self, node_id: usize, subgraph_id: usize) -> bool {
+        let mut parent = self.node_by_id(node_id).and_then(|node| node.parent);
+        // 归属关系不会成环；以节点数为上限防止数据损坏时死循环。
+        for _ in 0..self.nodes.len() {
+            match parent {
+                Some(id) if id == subgraph_id => return true,
+                Some(id) => parent = self.node_by_id(id).and_then(|node| node.parent),
+                None => return false,
+            }
+        }
+        false
+    }
+
+    /// 节点在当前层的代表：本层节点是它自己，更深层的节点是包含它的本层子图节点。
+    /// 节点不在当前层之下时返回 `None`。
+    pub(super) fn scope_representative(&self, node_id: usize) -> Option<usize> {
+        let mut id = node_id;
+        for _ in 0..self.nodes.len() {
+            let node = self.node_by_id(id)?;
+            if node.parent == self.current_subgraph {
+                return Some(id);
+            }
+            id = node.parent?;
+        }
+        None
+    }
+
+    /// 子图节点直接包含的节点。
+    fn subgraph_children(&self, subgraph_id: usize) -> Vec<usize> {
+        self.nodes
+            .iter()
+            .filter(|node| node.parent == Some(subgraph_id))
+            .map(|node| node.id)
+            .collect()
+    }
+
+    /// 重建当前层子图节点的外露端口缓存，每帧绘制连线前调用。
+    ///
+    /// 只扫描一遍连线：端点在当前层的代表是某个子图节点（且不是它自己）就说明端点在子图里面。
+    pub(super) fn update_exposed_ports(&mut self) {
+        let mut sides: BTreeMap<usize, (BTreeSet<usize>, BTreeSet<usize>)> = self
+            .visible_nodes()
+            .filter(|node| node.kind == NodeKind::Subgraph)
+            .map(|node| (node.id, Default::default()))
+            .collect();
+        for connection in &self.connections {
+            let (from, to) = connection.key();
+            let from_scope = self.scope_representative(from).filter(|&id| id != from);
+            let to_scope = self.scope_representative(to).filter(|&id| id != to);
+            if from_scope == to_scope {
+                continue;
+            }
+            if let Some((inputs, _)) = to_scope.and_then(|id| sides.get_mut(&id)) {
+                inputs.insert(to);
+            }
+            if let Some((_, outputs)) = from_scope.and_then(|id| sides.get_mut(&id)) {
+                outputs.insert(from);
+            }
+        }
+        self.exposed_port_cache = ExposedPortCache {
+            ports: sides
+                .into_iter()
+                .map(|(id, (inputs, outputs))| {
+                    let ports = ExposedPorts {
+                        inputs: inputs.into_iter().collect(),
+                        outputs: outputs.into_iter().collect(),
+                    };
+                    (id, ports)
+                })
+                .collect(),
+        };
+    }
+
+    /// 子图节点的外露端口：当前层的子图节点直接取缓存，其他的现算。
+    fn exposed_ports(&self, subgraph_id: usize) -> Cow<'_, ExposedPorts> {
+        self.exposed_port_cache.ports.get(&subgraph_id).map_or_else(
+            || Cow::Owned(self.scan_exposed_ports(subgraph_id)),
+            Cow::Borrowed,
+        )
+    }
+
+    /// 扫描全部连线，推导出子图节点的外露端口。
+    fn scan_exposed_ports(&self, subgraph_id: usize) -> ExposedPorts {
+        let mut inputs = BTreeSet::new();
+        let mut outputs = BTreeSet::new();
+        for connection in &self.connections {
+            let from_inside = self.is_inside(connection.from_node_id, subgraph_id);
+            let to_inside = self.is_inside(connection.to_node_id, subgraph_id);
+            if to_inside && !from_inside {
+                inputs.insert(connection.to_node_id);
+            } else if from_inside && !to_inside {
+                outputs.insert(connection.from_node_id);
+            }
+        }
+        ExposedPorts {
+            inputs: inputs.into_iter().collect(),
+            outputs: outputs.into_iter().collect(),
+        }
+    }
+
+    /// 内部节点 `inner_id` 在子图节点上对应的外露端口位置（世界坐标）。
+    pub(super) fn exposed_port_world(
+        &self,
+        subgraph: &Node,
+        inner_id: usize,
+        port: PortKind,
+    ) -> Option<Pos2> {
+        let ports = self.exposed_ports(subgraph.id);
+        let side = ports.side(port);
+        let index = side.iter().position(|&id| id == inner_id)?;
+        Some(exposed_port_pos(
+            subgraph.world_rect(),
+            port,
+            index,
+            side.len(),
+        ))
+    }
+
+    /// 子图节点的外露端口及其对应的内部节点。
+    pub(super) fn exposed_port_nodes(&self, subgraph_id: usize) -> Vec<(PortKind, usize)> {
+        let ports = self.exposed_ports(subgraph_id);
+        let inputs = ports.inputs.iter().map(|&id| (PortKind::Input, id));
+        let outputs = ports.outputs.iter().map(|&id| (PortKind::Output, id));
+        inputs.chain(outputs).collect()
+    }
+
+    /// 子图节点所有外露端口的位置（世界坐标）；其他节点返回空列表。
+    pub(super) fn exposed_port_positions(&self, node: &Node) -> Vec<(PortKind, Pos2)> {
+        if node.kind != NodeKind::Subgraph {
+            return Vec::new();
+        }
+        let ports = self.exposed_ports(node.id);
+        [PortKind::Input, PortKind::Output]
+            .into_iter()
+            .flat_map(|port| {
+                let side = ports.side(port);
+                (0..side.len()).map(move |index| {
+                    (
+                        port,
+                        exposed_port_pos(node.world_rect(), port, index, side.len()),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// 命中测试：当前层子图节点上的外露端口，返回对应的内部节点。
+    pub(super) fn exposed_port_at(&self, pointer_pos: Pos2) -> Option<(usize, PortKind)> {
+        self.visible_nodes()
+            .filter(|node| node.kind == NodeKind::Subgraph)
+            .find_map(|node| {
+                let ports = self.exposed_ports(node.id);
+                let rect = self.node_rect_screen(node);
+                [PortKind::Input, PortKind::Output]
+                    .into_iter()
+                    .find_map(|port| {
+                        let side = ports.side(port);
+                        side.iter().enumerate().find_map(|(index, &inner_id)| {
+                            (exposed_port_pos(rect, port, index, side.len()).distance(pointer_pos)
+                                <= PORT_HIT_RADIUS)
+                                .then_some((inner_id, port))
+                        })
+                    })
+            })
+    }
+
+    /// 绘制子图节点的外露端口；从端口拖出时直接以内部节点为起点新建连线。
+    pub(super) fn draw_exposed_ports(&mut self, ui: &egui::Ui, subgraph_id: usize, rect: Rect) {
+        let ports = self.exposed_ports(subgraph_id);
+        let theme = self.theme;
+        let mut drag_start = None;
+
+        for port in [PortKind::Input, PortKind::Output] {
+            let side = ports.side(port);
+            for (index, &inner_id) in side.iter().enumerate() {
+                let pos = exposed_port_pos(rect, port, index, side.len());
+                let response = ui
+                    .interact(
+                        Rect::from_center_size(pos, Vec2::splat(PORT_HIT_RADIUS * 2.0)),
+                        ui.make_persistent_id(("exposed_port", subgraph_id, inner_id, port as u8)),
+                        Sense::drag(),
+                    )
+                    .on_hover_cursor(CursorIcon::PointingHand);
+                let response = match self.node_by_id(inner_id) {
+                    Some(inner) => response.on_hover_text(&inner.title),
+                    None => response,
+                };
+                Self::draw_port_socket(ui, &theme, pos, port, response.hovered());
+                if response.drag_started() {
+                    drag_start = Some((inner_id, port, response.interact_pointer_pos()));
+                }
+            }
+        }
+
+        if let Some((from_node, from_port, pointer_pos)) = drag_start {
+            self.begin_link_edit();
+            self.dragging_link = Some(DragLinkState {
+                from_node,
+                from_port,
+                current_pos: pointer_pos.unwrap_or_default(),
+            });
+        }
+    }
+
+    /// 子图节点正文：显示内部概况，双击进入子图。
+    pub(super) fn draw_subgraph_summary(
+        ui: &egui::Ui,
+        theme: &Theme,
+        node_id: usize,
+        summary: &str,
+        content_rect: Rect,
+    ) -> egui::Response {
+        ui.painter().text(
+            content_rect.center(),
+            egui::Align2::CENTER_CENTER,
+            summary,
+            FontId::proportional(12.0),
+            theme.node_text.gamma_multiply(0.75),
+        );
+        ui.interact(
+            content_rect,
+            ui.make_persistent_id(("subgraph_summary", node_id)),
+            Sense::click(),
+        )
+        .on_hover_cursor(CursorIcon::PointingHand)
+    }
+
+    /// 子图节点正文里显示的概况文字。
+    pub(super) fn subgraph_summary(&self, subgraph_id: usize) -> String {
+        let ports = self.exposed_ports(subgraph_id);
+        format!(
+            "{} nodes · {} in · {} out\nDouble-click to open",
+            self.subgraph_children(subgraph_id).len(),
+            ports.inputs.len(),
+            ports.outputs.len(),
+        )
+    }
+
+    pub(super) fn apply_subgraph_action(&mut self, action: SubgraphAction, canvas_rect: Rect) {
+        match action {
+            SubgraphAction::CollapseSelection => self.collapse_selection_to_subgraph(),
+            SubgraphAction::Open(scope) => self.open_subgraph(scope, canvas_rect),
+            SubgraphAction::Ungroup(subgraph_id) => self.ungroup_subgraph(subgraph_id),
+        }
+    }
+
+    /// 把当前层选中的节点收进一个新的子图节点，子图节点放在它们的包围盒中心。
+    fn collapse_selection_to_subgraph(&mut self) {
+        let selected: Vec<usize> = self
+            .visible_nodes()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .map(|node| node.id)
+            .collect();
+        let Some(bounds) = selected
+            .iter()
+            .filter_map(|&id| self.node_by_id(id))
+            .map(Node::world_rect)
+            .reduce(|a, b| a.union(b))
+        else {
+            return;
+        };
+
+        let id = self.add_node_at(NodeKind::Subgraph, bounds.center());
+        for node in &mut self.nodes {
+            if selected.contains(&node.id) {
+                node.parent = Some(id);
+            }
+        }
+        self.selected_nodes = BTreeSet::from([id]);
+    }
+
+    /// 切换到某一层，并让该层的节点居中显示。
+    pub(super) fn open_subgraph(&mut self, scope: Option<usize>, canvas_rect: Rect) {
+        if scope.is_some_and(|id| {
+            self.node_by_id(id)
+                .is_none_or(|node| node.kind != NodeKind::Subgraph)
+        }) {
+            return;
+        }
+
+        self.current_subgraph = scope;
+        // 缓存里是上一层的子图节点，换层后作废。
+        self.exposed_port_cache = ExposedPortCache::default();
+        self.selected_nodes.clear();
+        self.node_drag = None;
+        self.cancel_link_edit();
+        self.frame_drag = None;
+        let bounds = self
+            .visible_nodes()
+            .map(Node::world_rect)
+            .reduce(|a, b| a.union(b))
+            .unwrap_or(Rect::from_min_size(Pos2::ZERO, NODE_SIZE));
+        self.center_view_on(bounds, canvas_rect);
+    }
+
+    /// 解散子图：内部节点和分组框回到子图所在的层，位置保持不变。
+    fn ungroup_subgraph(&mut self, subgraph_id: usize) {
+        let Some(parent) = self
+            .node_by_id(subgraph_id)
+            .filter(|node| node.kind == NodeKind::Subgraph)
+            .map(|node| node.parent)
+        else {
+            return;
+        };
+
+        let children = self.subgraph_children(subgraph_id);
+        for node in &mut self.nodes {
+            if node.parent == Some(subgraph_id) {
+                node.parent = parent;
+            }
+        }
+        for frame in &mut self.frames {
+            if frame.parent == Some(subgraph_id) {
+                frame.parent = parent;
+            }
+        }
+        self.remove_node(subgraph_id);
+        if parent == self.current_subgraph {
+            self.selected_nodes = children.into_iter().collect();
+        }
+    }
+
+    /// 从顶层到当前层的路径：`(子图 ID, 标题)`。
+    fn subgraph_path(&self) -> Vec<(usize, String)> {
+        let mut path = Vec::new();
+        let mut scope = self.current_subgraph;
+        while let Some(id) = scope {
+            let Some(node) = self.node_by_id(id) else {
+                break;
+            };
+            path.push((id, node.title.clone()));
+            scope = node.parent;
+            if path.len() > self.nodes.len() {
+                break;
+            }
+        }
+        path.reverse();
+        path
+    }
+
+    /// 画布左上角的面包屑导航（只在子图内显示），点击某一级返回该层。
+    pub(super) fn draw_breadcrumb(&mut self, ui: &mut egui::Ui, canvas_rect: Rect) {
+        if self.current_subgraph.is_none() {
+            return;
+        }
+
+        let path = self.subgraph_path();
+        let mut target = None;
+        let area = canvas_rect.shrink(BREADCRUMB_MARGIN);
+        ui.scope_builder(egui::UiBuilder::new().max_rect(area), |ui| {
+            egui::Frame::new()
+                .fill(self.theme.side_panel_bg)
+                .corner_radius(CornerRadius::same(6))
+                .inner_margin(egui::Margin::symmetric(8, 4))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if ui.link("Root").clicked() {
+                            target = Some(None);
+                        }
+                        let last = path.len().saturating_sub(1);
+                        for (index, (id, title)) in path.iter().enumerate() {
+                            ui.weak("›");
+                            if index == last {
+                                ui.strong(title);
+                            } else if ui.link(title).clicked() {
+                                target = Some(Some(*id));
+                            }
+                        }
+                    });
+                });
+        });
+
+        if let Some(scope) = target {
+            self.pending_action = Some(ContextAction::Subgraph(SubgraphAction::Open(scope)));
+        }
+    }
+}
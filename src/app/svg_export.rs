@@ -0,0 +1,364 @@
+use std::fmt::Write as _;
+
+use egui::{Color32, Pos2, Rect};
+
+use super::group_frame::{FRAME_FILL_ALPHA, FRAME_HEADER_HEIGHT, GroupFrame};
+use super::link_routing::LinkPath;
+use super::link_style::LinkDash;
+use super::node_tags::tag_fill;
+use super::{
+    Connection, HEADER_HEIGHT, NODE_INNER_PADDING_X, NODE_INNER_PADDING_Y, Node, NodeGraphApp,
+    NodeKind, PORT_RADIUS, PortKind, REROUTE_PORT_RADIUS, Theme,
+};
+
+// ============================================================
+// SVG 导出
+//
+// 按世界坐标把当前层级的分组框、节点、连线、转接点写成一张 SVG 图片，
+// 外观尽量与画布上一致（当前主题的配色与同样的连线走线）。
+// ============================================================
+
+// 图片四周留白（世界坐标单位）。
+const EXPORT_MARGIN: f32 = 40.0;
+// 正文每行的行高，与画布上正文字号 12 对应。
+const CONTENT_LINE_HEIGHT: f32 = 16.0;
+// 估算连线标签宽度时每个字符的平均宽度（字号 12）。
+const LABEL_CHAR_WIDTH: f32 = 7.0;
+// 估算标签胶囊宽度时每个字符的平均宽度（字号 10.5）。
+const TAG_CHAR_WIDTH: f32 = 6.0;
+
+/// `Color32` -> SVG 颜色字符串。
+fn svg_color(color: Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+/// 转义 XML 特殊字符。
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl NodeGraphApp {
+    /// 把整张图导出为 SVG 文本。
+    pub(super) fn to_svg(&self) -> String {
+        let mut svg = String::new();
+        self.write_svg(&mut svg)
+            .expect("writing to a String cannot fail");
+        svg
+    }
+
+    fn write_svg(&self, svg: &mut String) -> std::fmt::Result {
+        let bounds = self
+            .visible_nodes()
+            .map(|node| node.world_rect())
+            .chain(self.frames_in_scope().map(GroupFrame::visible_rect))
+            .reduce(|a, b| a.union(b))
+            .unwrap_or(Rect::from_min_size(Pos2::ZERO, egui::Vec2::splat(100.0)))
+            .expand(EXPORT_MARGIN);
+        let theme = &self.theme;
+
+        writeln!(
+            svg,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}" font-family="sans-serif">"#,
+            bounds.min.x,
+            bounds.min.y,
+            bounds.width(),
+            bounds.height(),
+            bounds.width(),
+            bounds.height(),
+        )?;
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+            bounds.min.x,
+            bounds.min.y,
+            bounds.width(),
+            bounds.height(),
+            svg_color(theme.canvas_bg),
+        )?;
+
+        // 与画布一致：先画分组框，再画连线，最后画节点。
+        for frame in self.frames_in_scope() {
+            Self::write_svg_frame(svg, theme, frame)?;
+        }
+
+        for connection in &self.connections {
+            let Some(path) = self.connection_path_world(connection) else {
+                continue;
+            };
+            Self::write_svg_link(svg, theme, connection, &path)?;
+        }
+
+        for node in self.visible_nodes() {
+            Self::write_svg_node(svg, theme, node)?;
+            for (port, center) in self.exposed_port_positions(node) {
+                Self::write_svg_port(svg, theme, center, port, PORT_RADIUS)?;
+            }
+        }
+
+        writeln!(svg, "</svg>")
+    }
+
+    /// 写入单个分组框：半透明框体 + 标题栏（折叠时只有标题栏）。
+    fn write_svg_frame(svg: &mut String, theme: &Theme, frame: &GroupFrame) -> std::fmt::Result {
+        let rect = frame.visible_rect();
+        let color = svg_color(frame.color);
+        if !frame.collapsed {
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="6" fill="{color}" fill-opacity="{}" stroke="{color}"/>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                f32::from(FRAME_FILL_ALPHA) / 255.0,
+            )?;
+        }
+        let header = frame.header_rect();
+        writeln!(
+            svg,
+            r#"<rect x="{}" y="{}" width="{}" height="{}" rx="6" fill="{color}"/>"#,
+            header.min.x,
+            header.min.y,
+            header.width(),
+            FRAME_HEADER_HEIGHT,
+        )?;
+        writeln!(
+            svg,
+            r#"<text x="{}" y="{}" font-size="13" fill="{}">{}</text>"#,
+            header.min.x + FRAME_HEADER_HEIGHT,
+            header.center().y + 4.5,
+            svg_color(theme.frame_title_text),
+            escape_xml(&frame.display_title()),
+        )
+    }
+
+    /// 写入单条连线（含箭头和标签）。
+    fn write_svg_link(
+        svg: &mut String,
+        theme: &Theme,
+        connection: &Connection,
+        path: &LinkPath,
+    ) -> std::fmt::Result {
+        let style = &connection.style;
+        let color = svg_color(style.color(theme.link));
+        let dash_attributes = match style.dash {
+            LinkDash::Solid => String::new(),
+            LinkDash::Dashed => {
+                let (dash, gap) = style.dash_pattern();
+                format!(r#" stroke-dasharray="{dash} {gap}""#)
+            }
+            // 长度为 0 的线段配合圆头端点画出圆点。
+            LinkDash::Dotted => format!(
+                r#" stroke-dasharray="0 {}" stroke-linecap="round""#,
+                style.dot_spacing()
+            ),
+        };
+        writeln!(
+            svg,
+            r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}"{dash_attributes}/>"#,
+            path.svg_path_data(),
+            color,
+            style.width,
+        )?;
+
+        if style.arrow
+            && let Some([tip, left, right]) = style.arrow_points(path)
+        {
+            writeln!(
+                svg,
+                r#"<polygon points="{},{} {},{} {},{}" fill="{}"/>"#,
+                tip.x, tip.y, left.x, left.y, right.x, right.y, color,
+            )?;
+        }
+
+        if !connection.label.is_empty() {
+            let center = path.midpoint();
+            // SVG 里无法预先测量文字，按字数估算标签背景宽度。
+            let width = connection.label.chars().count() as f32 * LABEL_CHAR_WIDTH + 12.0;
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="4" fill="{}" stroke="{}"/>"#,
+                center.x - width * 0.5,
+                center.y - 9.0,
+                width,
+                18.0,
+                svg_color(theme.canvas_bg),
+                color,
+            )?;
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="12" text-anchor="middle" fill="{}">{}</text>"#,
+                center.x,
+                center.y + 4.0,
+                svg_color(theme.link_label_text),
+                escape_xml(&connection.label),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// 写入单个节点（含端口）。
+    fn write_svg_node(svg: &mut String, theme: &Theme, node: &Node) -> std::fmt::Result {
+        let rect = node.world_rect();
+        let header_color = svg_color(node.header_color.unwrap_or(theme.node_header));
+
+        if node.kind == NodeKind::Reroute {
+            writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="1.5"/>"#,
+                rect.center().x,
+                rect.center().y,
+                rect.width().min(rect.height()) * 0.5 - 2.0,
+                header_color,
+                svg_color(theme.node_border_idle),
+            )?;
+        } else {
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" rx="8" fill="{}" stroke="{}" stroke-width="1.5"/>"#,
+                rect.min.x,
+                rect.min.y,
+                rect.width(),
+                rect.height(),
+                svg_color(theme.node_bg),
+                svg_color(theme.node_border_idle),
+            )?;
+            // 标题栏：只有上方两个角是圆角。
+            writeln!(
+                svg,
+                r#"<path d="M {} {} a 8 8 0 0 1 8 -8 h {} a 8 8 0 0 1 8 8 v {} h {} z" fill="{}"/>"#,
+                rect.min.x,
+                rect.min.y + 8.0,
+                rect.width() - 16.0,
+                HEADER_HEIGHT - 8.0,
+                -rect.width(),
+                header_color,
+            )?;
+            writeln!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="14" fill="{}">{}</text>"#,
+                rect.min.x + NODE_INNER_PADDING_X,
+                rect.min.y + HEADER_HEIGHT * 0.5 + 5.0,
+                svg_color(theme.node_title_text),
+                escape_xml(&node.icon.as_ref().map_or_else(
+                    || node.title.clone(),
+                    |icon| format!("{icon} {}", node.title),
+                )),
+            )?;
+
+            // 画布上正文超出节点时可以滚动，导出时按正文区域裁剪。
+            let content_top = rect.min.y + HEADER_HEIGHT + NODE_INNER_PADDING_Y;
+            writeln!(
+                svg,
+                r#"<clipPath id="node-{}-content"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+                node.id,
+                rect.min.x + NODE_INNER_PADDING_X,
+                content_top,
+                rect.width() - NODE_INNER_PADDING_X * 2.0,
+                rect.max.y - NODE_INNER_PADDING_Y - node.tag_row_height() - content_top,
+            )?;
+            write!(
+                svg,
+                r#"<text x="{}" y="{}" font-size="12" fill="{}" clip-path="url(#node-{}-content)">"#,
+                rect.min.x + NODE_INNER_PADDING_X + 8.0,
+                content_top,
+                svg_color(theme.node_text),
+                node.id,
+            )?;
+            for line in node.content.lines() {
+                write!(
+                    svg,
+                    r#"<tspan x="{}" dy="{}">{}</tspan>"#,
+                    rect.min.x + NODE_INNER_PADDING_X + 8.0,
+                    CONTENT_LINE_HEIGHT,
+                    escape_xml(line),
+                )?;
+            }
+            writeln!(svg, "</text>")?;
+            Self::write_svg_tags(svg, theme, node)?;
+        }
+
+        let port_radius = if node.kind == NodeKind::Reroute {
+            REROUTE_PORT_RADIUS
+        } else {
+            PORT_RADIUS
+        };
+        for port in [PortKind::Input, PortKind::Output] {
+            if node.kind.has_port(port) {
+                Self::write_svg_port(
+                    svg,
+                    theme,
+                    Self::port_pos_world(node, port),
+                    port,
+                    port_radius,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 写入节点底部的标签胶囊（放不下的省略）。
+    fn write_svg_tags(svg: &mut String, theme: &Theme, node: &Node) -> std::fmt::Result {
+        let rect = node.world_rect();
+        let top = rect.max.y - node.tag_row_height() - 2.0;
+        let right = rect.max.x - NODE_INNER_PADDING_X;
+        let mut x = rect.min.x + NODE_INNER_PADDING_X;
+        for tag in &node.tags {
+            let width = tag.chars().count() as f32 * TAG_CHAR_WIDTH + 10.0;
+            if x + width > right {
+                break;
+            }
+            let [r, g, b, alpha] = tag_fill(theme, tag).to_srgba_unmultiplied();
+            writeln!(
+                svg,
+                r#"<rect x="{x}" y="{top}" width="{width}" height="14" rx="7" fill="{}" fill-opacity="{}"/><text x="{}" y="{}" font-size="10.5" fill="{}">{}</text>"#,
+                svg_color(Color32::from_rgb(r, g, b)),
+                f32::from(alpha) / 255.0,
+                x + 5.0,
+                top + 10.5,
+                svg_color(theme.tag_text),
+                escape_xml(tag),
+            )?;
+            x += width + 4.0;
+        }
+        Ok(())
+    }
+
+    /// 写入一个端口圆点。
+    fn write_svg_port(
+        svg: &mut String,
+        theme: &Theme,
+        center: Pos2,
+        port: PortKind,
+        radius: f32,
+    ) -> std::fmt::Result {
+        let color = match port {
+            PortKind::Input => theme.port_input,
+            PortKind::Output => theme.port_output,
+        };
+        writeln!(
+            svg,
+            r#"<circle cx="{}" cy="{}" r="{}" fill="{}" stroke="{}" stroke-width="2"/>"#,
+            center.x,
+            center.y,
+            radius,
+            svg_color(theme.node_bg),
+            svg_color(color),
+        )
+    }
+
+    /// 把 SVG 写入 `export_path`，结果显示在侧边栏状态栏。
+    pub(super) fn export_svg_to_file(&mut self) {
+        let svg = self.to_svg();
+        self.status_message = Some(match std::fs::write(&self.export_path, svg) {
+            Ok(()) => format!("Exported to {}", self.export_path),
+            Err(err) => {
+                log::warn!("导出 SVG 失败 '{}'：{err}", self.export_path);
+                format!("Export failed: {err}")
+            }
+        });
+    }
+}
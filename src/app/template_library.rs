@@ -0,0 +1,458 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+
+use egui::{CornerRadius, Pos2, Rect, Sense, Stroke, StrokeKind, Vec2};
+
+use super::group_frame::GroupFrame;
+use super::{Connection, HEADER_HEIGHT, Node, NodeGraphApp, PortKind, Theme};
+
+// ============================================================
+// 模板库
+//
+// 把选中的一组节点（连同子图里的节点和它们之间的连线）存成命名模板，
+// 每个模板是模板库目录下的一个 `.ron` 文件，可以直接拷贝给别人。
+// 框住的节点全部被选中的分组框、以及选中子图里的分组框一起存进模板。
+// 插入模板时重新分配节点和分组框 ID，保持相对位置和内部连线。
+// 保存时模板名对应的文件已存在，先询问是否覆盖。
+// ============================================================
+
+// 模板文件扩展名。
+const TEMPLATE_EXTENSION: &str = "ron";
+// 侧边栏模板预览图的尺寸。
+const PREVIEW_SIZE: Vec2 = Vec2::new(180.0, 90.0);
+const PREVIEW_PADDING: f32 = 6.0;
+
+/// 一个模板：节点位置相对于模板包围盒左上角。
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize)]
+pub(super) struct GraphTemplate {
+    name: String,
+    nodes: Vec<Node>,
+    connections: Vec<Connection>,
+    /// 旧版本的模板文件里没有分组框。
+    #[serde(default)]
+    frames: Vec<GroupFrame>,
+}
+
+impl GraphTemplate {
+    /// 顶层节点（不在模板内某个子图里的节点）。
+    fn top_level_nodes(&self) -> impl Iterator<Item = &Node> {
+        self.nodes.iter().filter(|node| node.parent.is_none())
+    }
+
+    /// 顶层节点的包围盒。
+    fn bounds(&self) -> Option<Rect> {
+        self.top_level_nodes()
+            .map(Node::world_rect)
+            .reduce(|a, b| a.union(b))
+    }
+}
+
+/// 模板名 -> 文件名：只保留字母、数字、`-` 和 `_`，其余字符替换为 `_`。
+fn template_file_name(name: &str) -> String {
+    let stem: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    format!("{stem}.{TEMPLATE_EXTENSION}")
+}
+
+/// 读取模板库目录下的所有模板，按名称排序；目录不存在时视为空库。
+fn load_templates(dir: &Path) -> std::io::Result<Vec<(PathBuf, GraphTemplate)>> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut templates = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(TEMPLATE_EXTENSION) {
+            continue;
+        }
+        // 单个文件损坏时跳过它，不影响整个模板库。
+        match std::fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| ron::from_str::<GraphTemplate>(&text).map_err(|err| err.to_string()))
+        {
+            Ok(template) => templates.push((path, template)),
+            Err(err) => log::warn!("读取模板失败 '{}'：{err}", path.display()),
+        }
+    }
+    templates.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+    Ok(templates)
+}
+
+/// 模板库面板的状态（不随文档保存）。
+#[derive(Default)]
+pub(super) struct TemplateLibrary {
+    /// 已读取的模板；`None` 表示还没有扫描过目录。
+    templates: Option<Vec<(PathBuf, GraphTemplate)>>,
+    /// “保存为模板”输入框里的名称。
+    new_name: String,
+    /// 同名模板文件已存在，正在询问是否覆盖。
+    confirm_overwrite: bool,
+}
+
+impl NodeGraphApp {
+    /// 把选中节点（以及选中子图里的所有节点）和它们之间的连线打包成模板。
+    fn template_from_selection(&self, name: &str) -> Option<GraphTemplate> {
+        let roots: Vec<&Node> = self
+            .visible_nodes()
+            .filter(|node| self.selected_nodes.contains(&node.id))
+            .collect();
+        let origin = roots
+            .iter()
+            .map(|node| node.world_rect())
+            .reduce(|a, b| a.union(b))?
+            .min;
+        let root_ids: BTreeSet<usize> = roots.iter().map(|node| node.id).collect();
+        let ids: BTreeSet<usize> = self
+            .nodes
+            .iter()
+            .filter(|node| {
+                root_ids.contains(&node.id)
+                    || root_ids.iter().any(|&root| self.is_inside(node.id, root))
+            })
+            .map(|node| node.id)
+            .collect();
+
+        let nodes = self
+            .nodes
+            .iter()
+            .filter(|node| ids.contains(&node.id))
+            .map(|node| Node {
+                position: node.position - origin.to_vec2(),
+                parent: node.parent.filter(|_| !root_ids.contains(&node.id)),
+                ..node.clone()
+            })
+            .collect();
+        let connections = self
+            .connections
+            .iter()
+            .filter(|connection| {
+                ids.contains(&connection.from_node_id) && ids.contains(&connection.to_node_id)
+            })
+            .cloned()
+            .collect();
+        // 当前层里框住的节点都被选中的分组框，以及选中子图里的分组框。
+        let frames = self
+            .frames
+            .iter()
+            .filter(|frame| match frame.parent {
+                Some(parent) if ids.contains(&parent) => true,
+                parent if parent == self.current_subgraph => {
+                    let members = self.frame_members(frame);
+                    !members.is_empty() && members.iter().all(|id| root_ids.contains(id))
+                }
+                _ => false,
+            })
+            .map(|frame| {
+                let parent = frame.parent.filter(|parent| ids.contains(parent));
+                frame.copied(frame.id, parent, -origin.to_vec2(), |id| {
+                    ids.contains(&id).then_some(id)
+                })
+            })
+            .collect();
+
+        Some(GraphTemplate {
+            name: name.trim().to_owned(),
+            nodes,
+            connections,
+            frames,
+        })
+    }
+
+    /// 模板名对应的文件路径。
+    fn template_path(&self, name: &str) -> PathBuf {
+        PathBuf::from(&self.library_dir).join(template_file_name(name))
+    }
+
+    /// 把选中节点保存到模板库目录，结果显示在侧边栏状态栏。
+    fn save_selection_as_template(&mut self) {
+        let name = self.template_library.new_name.trim().to_owned();
+        let Some(template) = self.template_from_selection(&name) else {
+            return;
+        };
+
+        self.template_library.confirm_overwrite = false;
+        let dir = PathBuf::from(&self.library_dir);
+        let path = self.template_path(&name);
+        let result = std::fs::create_dir_all(&dir)
+            .map_err(|err| err.to_string())
+            .and_then(|()| {
+                ron::ser::to_string_pretty(&template, ron::ser::PrettyConfig::default())
+                    .map_err(|err| err.to_string())
+            })
+            .and_then(|text| std::fs::write(&path, text).map_err(|err| err.to_string()));
+
+        self.status_message = Some(match result {
+            Ok(()) => {
+                self.template_library.new_name.clear();
+                self.template_library.templates = None;
+                format!("Saved template '{name}'")
+            }
+            Err(err) => {
+                log::warn!("保存模板失败 '{}'：{err}", path.display());
+                format!("Saving template failed: {err}")
+            }
+        });
+    }
+
+    /// 在世界坐标 `world_center` 处插入模板：重新分配 ID，保持相对位置和内部连线。
+    /// 插入的顶层节点放在当前子图层级，并成为新的选中。
+    fn insert_template(&mut self, template: &GraphTemplate, world_center: Pos2) {
+        let Some(bounds) = template.bounds() else {
+            return;
+        };
+        self.record_undo();
+
+        let offset = world_center - bounds.center();
+        let ids: BTreeMap<usize, usize> = template
+            .nodes
+            .iter()
+            .enumerate()
+            .map(|(index, node)| (node.id, self.next_node_id + index))
+            .collect();
+        self.next_node_id += template.nodes.len();
+
+        self.selected_nodes.clear();
+        for node in &template.nodes {
+            let Some(&id) = ids.get(&node.id) else {
+                continue;
+            };
+            let parent = if let Some(parent) = node.parent {
+                ids.get(&parent).copied()
+            } else {
+                self.selected_nodes.insert(id);
+                self.current_subgraph
+            };
+            self.nodes.push(Node {
+                id,
+                position: node.position + offset,
+                parent,
+                ..node.clone()
+            });
+        }
+        for connection in &template.connections {
+            if let (Some(&from_node_id), Some(&to_node_id)) = (
+                ids.get(&connection.from_node_id),
+                ids.get(&connection.to_node_id),
+            ) {
+                self.connections.push(Connection {
+                    from_node_id,
+                    to_node_id,
+                    ..connection.clone()
+                });
+            }
+        }
+        for frame in &template.frames {
+            let parent = match frame.parent {
+                Some(parent) => ids.get(&parent).copied(),
+                None => self.current_subgraph,
+            };
+            let id = self.next_frame_id;
+            self.next_frame_id += 1;
+            self.frames
+                .push(frame.copied(id, parent, offset, |id| ids.get(&id).copied()));
+        }
+    }
+
+    fn delete_template(&mut self, path: &Path) {
+        if let Err(err) = std::fs::remove_file(path) {
+            log::warn!("删除模板失败 '{}'：{err}", path.display());
+            self.status_message = Some(format!("Deleting template failed: {err}"));
+        }
+        self.template_library.templates = None;
+    }
+
+    /// 侧边栏“模板库”页。
+    pub(super) fn library_panel_contents(&mut self, ui: &mut egui::Ui) {
+        ui.label("Library directory");
+        ui.horizontal(|ui| {
+            if ui.text_edit_singleline(&mut self.library_dir).lost_focus() {
+                self.template_library.templates = None;
+            }
+        });
+        if ui.button("Refresh").clicked() {
+            self.template_library.templates = None;
+        }
+
+        ui.separator();
+        ui.label("Save selection as template");
+        if ui
+            .text_edit_singleline(&mut self.template_library.new_name)
+            .changed()
+        {
+            self.template_library.confirm_overwrite = false;
+        }
+        let can_save =
+            !self.selected_nodes.is_empty() && !self.template_library.new_name.trim().is_empty();
+        if ui
+            .add_enabled(can_save, egui::Button::new("Save Template"))
+            .clicked()
+        {
+            if self.template_path(&self.template_library.new_name).exists() {
+                self.template_library.confirm_overwrite = true;
+            } else {
+                self.save_selection_as_template();
+            }
+        }
+        if self.template_library.confirm_overwrite {
+            self.overwrite_prompt(ui);
+        }
+
+        ui.separator();
+        if self.template_library.templates.is_none() {
+            match load_templates(Path::new(&self.library_dir)) {
+                Ok(templates) => self.template_library.templates = Some(templates),
+                Err(err) => {
+                    ui.weak(format!("Cannot read library: {err}"));
+                    return;
+                }
+            }
+        }
+        self.template_list(ui);
+    }
+
+    /// 同名模板文件已存在时的确认：覆盖或取消。
+    fn overwrite_prompt(&mut self, ui: &mut egui::Ui) {
+        let file_name = template_file_name(&self.template_library.new_name);
+        ui.colored_label(
+            ui.visuals().warn_fg_color,
+            format!("'{file_name}' already exists in the library"),
+        );
+        ui.horizontal(|ui| {
+            if ui.button("Overwrite").clicked() {
+                self.save_selection_as_template();
+            }
+            if ui.button("Cancel").clicked() {
+                self.template_library.confirm_overwrite = false;
+            }
+        });
+    }
+
+    /// 模板列表：预览图 + 插入 / 删除按钮。
+    fn template_list(&mut self, ui: &mut egui::Ui) {
+        let templates = self.template_library.templates.take().unwrap_or_default();
+        if templates.is_empty() {
+            ui.weak("No templates yet");
+        }
+
+        let theme = self.theme;
+        let mut insert = None;
+        let mut delete = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for (index, (_, template)) in templates.iter().enumerate() {
+                ui.strong(&template.name);
+                draw_graph_preview(ui, &theme, &template.nodes, &template.connections);
+                ui.horizontal(|ui| {
+                    if ui.button("Insert").clicked() {
+                        insert = Some(index);
+                    }
+                    if ui.button("Delete").clicked() {
+                        delete = Some(index);
+                    }
+                });
+                ui.add_space(6.0);
+            }
+        });
+
+        // 插入到当前画布可见区域的中心。
+        if let Some((_, template)) = insert.and_then(|index| templates.get(index)) {
+            let center = self.canvas_rect.center() - self.pan_offset;
+            self.insert_template(template, center);
+        }
+        let deleted = delete.and_then(|index| templates.get(index).map(|(path, _)| path.clone()));
+        self.template_library.templates = Some(templates);
+        if let Some(path) = deleted {
+            self.delete_template(&path);
+        }
+    }
+}
+
+/// 模板 / 恢复文档的预览：把顶层节点和连线缩放画进一个小矩形。
+pub(super) fn draw_graph_preview(
+    ui: &mut egui::Ui,
+    theme: &Theme,
+    nodes: &[Node],
+    connections: &[Connection],
+) {
+    let (rect, _) = ui.allocate_exact_size(PREVIEW_SIZE, Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, CornerRadius::same(4), theme.canvas_bg);
+    painter.rect_stroke(
+        rect,
+        CornerRadius::same(4),
+        Stroke::new(1.0, theme.node_border_idle),
+        StrokeKind::Inside,
+    );
+    let top_level_nodes = || nodes.iter().filter(|node| node.parent.is_none());
+    let Some(bounds) = top_level_nodes()
+        .map(Node::world_rect)
+        .reduce(|a, b| a.union(b))
+    else {
+        return;
+    };
+
+    let inner = rect.shrink(PREVIEW_PADDING);
+    let scale = (inner.width() / bounds.width())
+        .min(inner.height() / bounds.height())
+        .min(1.0);
+    let offset = inner.center() - bounds.center().to_vec2() * scale;
+    let to_preview = |pos: Pos2| offset + pos.to_vec2() * scale;
+    let node_rect = |node: &Node| {
+        let rect = node.world_rect();
+        Rect::from_min_max(to_preview(rect.min), to_preview(rect.max))
+    };
+
+    // 连线两端都取顶层节点（子图内部的节点由子图节点代表）。
+    let top_level = |id: usize| {
+        let mut node = nodes.iter().find(|node| node.id == id)?;
+        for _ in 0..nodes.len() {
+            let Some(parent) = node.parent else {
+                return Some(node);
+            };
+            node = nodes.iter().find(|node| node.id == parent)?;
+        }
+        None
+    };
+    for connection in connections {
+        if let (Some(from), Some(to)) = (
+            top_level(connection.from_node_id),
+            top_level(connection.to_node_id),
+        ) && from.id != to.id
+        {
+            painter.line_segment(
+                [
+                    to_preview(NodeGraphApp::port_pos_world(from, PortKind::Output)),
+                    to_preview(NodeGraphApp::port_pos_world(to, PortKind::Input)),
+                ],
+                Stroke::new(1.0, theme.link),
+            );
+        }
+    }
+    for node in top_level_nodes() {
+        let rect = node_rect(node);
+        painter.rect_filled(rect, CornerRadius::same(2), theme.node_bg);
+        let header_height = (HEADER_HEIGHT * scale).min(rect.height());
+        painter.rect_filled(
+            Rect::from_min_size(rect.min, Vec2::new(rect.width(), header_height)),
+            CornerRadius::same(2),
+            node.header_color.unwrap_or(theme.node_header),
+        );
+        painter.rect_stroke(
+            rect,
+            CornerRadius::same(2),
+            Stroke::new(1.0, theme.node_border_hover),
+            StrokeKind::Inside,
+        );
+    }
+}
@@ -0,0 +1,315 @@
+use egui::Color32;
+
+use super::NodeGraphApp;
+
+// ============================================================
+// 主题
+//
+// 画布、节点、连线、端口、分组框以及各种交互提示（切线、参考线、数据流）的颜色
+// 都来自 `Theme`，不再是编译期常量。
+// 内置深色 / 浅色两套主题，默认跟随系统的深浅色设置；
+// 在侧边栏 `Theme` 页修改任意颜色会得到一份自定义主题，
+// 主题可以导出为 `.ron` 文件，也可以从文件导入。
+// ============================================================
+
+/// 一套界面配色。
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub(super) struct Theme {
+    /// 侧边栏等 egui 控件使用深色还是浅色外观。
+    pub(super) dark: bool,
+    pub(super) canvas_bg: Color32,
+    pub(super) side_panel_bg: Color32,
+    pub(super) grid_minor: Color32,
+    pub(super) grid_major: Color32,
+    pub(super) node_bg: Color32,
+    /// 节点标题栏的默认颜色（节点可以单独设置）。
+    pub(super) node_header: Color32,
+    pub(super) node_title_text: Color32,
+    pub(super) node_text: Color32,
+    pub(super) node_border_idle: Color32,
+    pub(super) node_border_hover: Color32,
+    pub(super) node_border_selected: Color32,
+    /// 连线的默认颜色（连线可以单独设置）。
+    pub(super) link: Color32,
+    pub(super) drag_link: Color32,
+    pub(super) link_label_text: Color32,
+    pub(super) port_input: Color32,
+    pub(super) port_output: Color32,
+    /// 错误提示（检查器里的非法输入、校验错误）。
+    pub(super) error: Color32,
+    /// 按住 Ctrl 右键拖出的切线轨迹。
+    pub(super) cut_stroke: Color32,
+    /// 数据流动画：活跃度低 / 高时的颜色，中间按活跃度插值。
+    pub(super) flow_low: Color32,
+    pub(super) flow_high: Color32,
+    /// 拖动节点时的对齐参考线。
+    pub(super) snap_guide: Color32,
+    /// 新建分组框的颜色（分组框可以单独设置）。
+    pub(super) frame_default: Color32,
+    pub(super) frame_title_text: Color32,
+    /// 标签胶囊上的文字。
+    pub(super) tag_text: Color32,
+    /// 搜索命中节点的描边。
+    pub(super) search_match: Color32,
+    /// 依赖高亮：上游 / 下游节点和连线。
+    pub(super) upstream: Color32,
+    pub(super) downstream: Color32,
+    /// 路径查询找到的路径。
+    pub(super) path: Color32,
+    /// 校验警告。
+    pub(super) warning: Color32,
+    /// 颜色色块悬停时的描边。
+    pub(super) swatch_hover: Color32,
+    /// 分组框右下角的缩放三角。
+    pub(super) frame_handle: Color32,
+    /// 放不下的标签数（`+N`）。
+    pub(super) tag_overflow: Color32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// 内置深色主题（原来的配色）。
+    pub(super) fn dark() -> Self {
+        Self {
+            dark: true,
+            canvas_bg: Color32::from_rgb(20, 23, 29),
+            side_panel_bg: Color32::from_rgb(25, 28, 34),
+            grid_minor: Color32::from_rgba_unmultiplied(120, 130, 150, 16),
+            grid_major: Color32::from_rgba_unmultiplied(120, 130, 150, 30),
+            node_bg: Color32::from_rgb(30, 30, 35),
+            node_header: Color32::from_rgb(57, 116, 245),
+            node_title_text: Color32::WHITE,
+            node_text: Color32::from_gray(220),
+            node_border_idle: Color32::from_rgb(82, 82, 91),
+            node_border_hover: Color32::from_rgb(148, 163, 184),
+            node_border_selected: Color32::from_rgb(250, 204, 21),
+            link: Color32::from_rgb(122, 134, 156),
+            drag_link: Color32::from_rgb(100, 180, 255),
+            link_label_text: Color32::from_gray(230),
+            port_input: Color32::from_rgb(255, 95, 87), // mac red
+            port_output: Color32::from_rgb(254, 188, 46), // mac yellow
+            error: Color32::from_rgb(248, 113, 113),
+            cut_stroke: Color32::from_rgb(255, 95, 87),
+            flow_low: Color32::from_rgb(100, 180, 255),
+            flow_high: Color32::from_rgb(255, 160, 60),
+            snap_guide: Color32::from_rgb(236, 72, 153),
+            frame_default: Color32::from_rgb(96, 104, 120),
+            frame_title_text: Color32::WHITE,
+            tag_text: Color32::WHITE,
+            search_match: Color32::from_rgb(45, 212, 191),
+            upstream: Color32::from_rgb(56, 189, 248),
+            downstream: Color32::from_rgb(251, 146, 60),
+            path: Color32::from_rgb(167, 139, 250),
+            warning: Color32::from_rgb(251, 191, 36),
+            swatch_hover: Color32::WHITE,
+            frame_handle: Color32::from_white_alpha(40),
+            tag_overflow: Color32::GRAY,
+        }
+    }
+
+    /// 内置浅色主题。
+    pub(super) fn light() -> Self {
+        Self {
+            dark: false,
+            canvas_bg: Color32::from_rgb(241, 243, 246),
+            side_panel_bg: Color32::from_rgb(231, 234, 239),
+            grid_minor: Color32::from_rgba_unmultiplied(80, 90, 110, 20),
+            grid_major: Color32::from_rgba_unmultiplied(80, 90, 110, 40),
+            node_bg: Color32::from_rgb(255, 255, 255),
+            node_header: Color32::from_rgb(57, 116, 245),
+            node_title_text: Color32::WHITE,
+            node_text: Color32::from_gray(40),
+            node_border_idle: Color32::from_rgb(203, 207, 214),
+            node_border_hover: Color32::from_rgb(113, 124, 145),
+            node_border_selected: Color32::from_rgb(217, 160, 6),
+            link: Color32::from_rgb(118, 128, 148),
+            drag_link: Color32::from_rgb(37, 99, 235),
+            link_label_text: Color32::from_gray(40),
+            port_input: Color32::from_rgb(230, 72, 64),
+            port_output: Color32::from_rgb(226, 150, 12),
+            error: Color32::from_rgb(220, 38, 38),
+            cut_stroke: Color32::from_rgb(220, 38, 38),
+            flow_low: Color32::from_rgb(37, 99, 235),
+            flow_high: Color32::from_rgb(234, 88, 12),
+            snap_guide: Color32::from_rgb(219, 39, 119),
+            frame_default: Color32::from_rgb(148, 163, 184),
+            frame_title_text: Color32::from_gray(30),
+            tag_text: Color32::from_gray(20),
+            search_match: Color32::from_rgb(13, 148, 136),
+            upstream: Color32::from_rgb(2, 132, 199),
+            downstream: Color32::from_rgb(234, 88, 12),
+            path: Color32::from_rgb(124, 58, 237),
+            warning: Color32::from_rgb(202, 138, 4),
+            swatch_hover: Color32::from_gray(30),
+            frame_handle: Color32::from_black_alpha(50),
+            tag_overflow: Color32::from_gray(110),
+        }
+    }
+
+    /// 所有颜色项及其显示名称，供主题编辑器逐项编辑。
+    fn colors_mut(&mut self) -> [(&'static str, &mut Color32); 32] {
+        [
+            ("Canvas", &mut self.canvas_bg),
+            ("Side panel", &mut self.side_panel_bg),
+            ("Grid (minor)", &mut self.grid_minor),
+            ("Grid (major)", &mut self.grid_major),
+            ("Node body", &mut self.node_bg),
+            ("Node header", &mut self.node_header),
+            ("Node title", &mut self.node_title_text),
+            ("Node text", &mut self.node_text),
+            ("Border", &mut self.node_border_idle),
+            ("Border (hover)", &mut self.node_border_hover),
+            ("Border (selected)", &mut self.node_border_selected),
+            ("Link", &mut self.link),
+            ("Link (dragging)", &mut self.drag_link),
+            ("Link label", &mut self.link_label_text),
+            ("Input port", &mut self.port_input),
+            ("Output port", &mut self.port_output),
+            ("Error", &mut self.error),
+            ("Cut stroke", &mut self.cut_stroke),
+            ("Flow (low)", &mut self.flow_low),
+            ("Flow (high)", &mut self.flow_high),
+            ("Snap guide", &mut self.snap_guide),
+            ("Frame (new)", &mut self.frame_default),
+            ("Frame title", &mut self.frame_title_text),
+            ("Tag text", &mut self.tag_text),
+            ("Search match", &mut self.search_match),
+            ("Upstream", &mut self.upstream),
+            ("Downstream", &mut self.downstream),
+            ("Path", &mut self.path),
+            ("Warning", &mut self.warning),
+            ("Swatch hover", &mut self.swatch_hover),
+            ("Frame resize handle", &mut self.frame_handle),
+            ("Tag overflow", &mut self.tag_overflow),
+        ]
+    }
+}
+
+/// 使用哪套主题。
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+pub(super) enum ThemeMode {
+    /// 跟随系统的深浅色设置。
+    #[default]
+    System,
+    Dark,
+    Light,
+    /// 编辑过或从文件导入的主题。
+    Custom,
+}
+
+impl ThemeMode {
+    const ALL: [Self; 4] = [Self::System, Self::Dark, Self::Light, Self::Custom];
+
+    fn label(self) -> &'static str {
+        match self {
+            Self::System => "Follow system",
+            Self::Dark => "Dark",
+            Self::Light => "Light",
+            Self::Custom => "Custom",
+        }
+    }
+}
+
+impl NodeGraphApp {
+    /// 每帧开始时确定本帧使用的主题，并让 egui 控件的深浅色与之一致。
+    pub(super) fn refresh_theme(&mut self, ctx: &egui::Context) {
+        let preference = match self.theme_mode {
+            ThemeMode::System => egui::ThemePreference::System,
+            ThemeMode::Dark => egui::ThemePreference::Dark,
+            ThemeMode::Custom if self.custom_theme.dark => egui::ThemePreference::Dark,
+            ThemeMode::Light | ThemeMode::Custom => egui::ThemePreference::Light,
+        };
+        if ctx.options(|options| options.theme_preference) != preference {
+            ctx.set_theme(preference);
+        }
+
+        // 跟随系统时由 egui 给出当前生效的深浅色。
+        self.theme = match (self.theme_mode, ctx.theme()) {
+            (ThemeMode::Custom, _) => self.custom_theme,
+            (_, egui::Theme::Dark) => Theme::dark(),
+            (_, egui::Theme::Light) => Theme::light(),
+        };
+    }
+
+    /// 侧边栏“主题”页：选择主题、逐项编辑颜色、导入 / 导出主题文件。
+    pub(super) fn theme_panel_contents(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("Theme")
+            .selected_text(self.theme_mode.label())
+            .show_ui(ui, |ui| {
+                for mode in ThemeMode::ALL {
+                    ui.selectable_value(&mut self.theme_mode, mode, mode.label());
+                }
+            });
+
+        ui.label("Theme file");
+        ui.text_edit_singleline(&mut self.theme_path);
+        ui.horizontal(|ui| {
+            if ui.button("Import").clicked() {
+                self.import_theme();
+            }
+            if ui.button("Export").clicked() {
+                self.export_theme();
+            }
+        });
+        ui.separator();
+
+        // 编辑内置主题时，先把它复制成自定义主题再修改。
+        let mut theme = self.theme;
+        let mut changed = ui.checkbox(&mut theme.dark, "Dark widgets").changed();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new("theme_colors")
+                .num_columns(2)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (label, color) in theme.colors_mut() {
+                        ui.label(label);
+                        changed |= ui.color_edit_button_srgba(color).changed();
+                        ui.end_row();
+                    }
+                });
+        });
+        if changed {
+            self.custom_theme = theme;
+            self.theme_mode = ThemeMode::Custom;
+            self.theme = theme;
+        }
+    }
+
+    /// 把当前主题写入 `theme_path`。
+    fn export_theme(&mut self) {
+        let result = ron::ser::to_string_pretty(&self.theme, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())
+            .and_then(|text| std::fs::write(&self.theme_path, text).map_err(|err| err.to_string()));
+        self.status_message = Some(match result {
+            Ok(()) => format!("Exported theme to {}", self.theme_path),
+            Err(err) => {
+                log::warn!("导出主题失败 '{}'：{err}", self.theme_path);
+                format!("Exporting theme failed: {err}")
+            }
+        });
+    }
+
+    /// 从 `theme_path` 读取主题，作为自定义主题使用；文件中缺少的颜色取深色主题的值。
+    fn import_theme(&mut self) {
+        let result = std::fs::read_to_string(&self.theme_path)
+            .map_err(|err| err.to_string())
+            .and_then(|text| ron::from_str::<Theme>(&text).map_err(|err| err.to_string()));
+        self.status_message = Some(match result {
+            Ok(theme) => {
+                self.custom_theme = theme;
+                self.theme_mode = ThemeMode::Custom;
+                format!("Imported theme from {}", self.theme_path)
+            }
+            Err(err) => {
+                log::warn!("导入主题失败 '{}'：{err}", self.theme_path);
+                format!("Importing theme failed: {err}")
+            }
+        });
+    }
+}
@@ -0,0 +1,292 @@
+use egui::{Key, KeyboardShortcut, Modifiers};
+
+use super::group_frame::GroupFrame;
+use super::{Connection, Node, NodeGraphApp};
+
+// ============================================================
+// 撤销 / 重做
+//
+// 用快照实现：每个可撤销操作执行前，把整张图（节点、连线、分组框、ID 计数）存一份。
+// 图的规模很小，整份复制比为每种操作写逆操作简单可靠。
+// 画布上的文字输入框（节点标题 / 正文、连线标签、分组框标题）直接改图数据：获得焦点时存快照，
+// 失去焦点时文字变了才记为一步撤销，和检查器里“一次编辑一步撤销”一致。
+// 历史只在本次运行期间有效，不随文档保存。
+// ============================================================
+
+// 最多保留的撤销步数。
+const MAX_UNDO_STEPS: usize = 100;
+
+const UNDO_SHORTCUT: KeyboardShortcut = KeyboardShortcut::new(Modifiers::COMMAND, Key::Z);
+const REDO_SHORTCUT: KeyboardShortcut =
+    KeyboardShortcut::new(Modifiers::COMMAND.plus(Modifiers::SHIFT), Key::Z);
+
+/// 图数据的一份快照。
+#[derive(Clone, Debug)]
+struct GraphSnapshot {
+    nodes: Vec<Node>,
+    connections: Vec<Connection>,
+    next_node_id: usize,
+    frames: Vec<GroupFrame>,
+    next_frame_id: usize,
+}
+
+/// 画布上一次文字编辑（获得焦点到失去焦点）开始时的状态。
+#[derive(Debug)]
+struct TextEditSession {
+    widget: egui::Id,
+    /// 开始编辑时的文字。
+    text: String,
+    before: GraphSnapshot,
+}
+
+/// 文字输入框本帧发生的事（取自 `egui::Response`）。
+#[derive(Clone, Copy, Debug, Default)]
+pub(super) struct TextEditEvents {
+    pub(super) gained_focus: bool,
+    pub(super) changed: bool,
+    pub(super) lost_focus: bool,
+}
+
+impl From<&egui::Response> for TextEditEvents {
+    fn from(response: &egui::Response) -> Self {
+        Self {
+            gained_focus: response.gained_focus(),
+            changed: response.changed(),
+            lost_focus: response.lost_focus(),
+        }
+    }
+}
+
+/// 撤销 / 重做栈。
+#[derive(Default)]
+pub(super) struct UndoHistory {
+    undo: Vec<GraphSnapshot>,
+    redo: Vec<GraphSnapshot>,
+    /// 拖线开始时的快照：松开时连线真的变了才记为一步撤销（见 `begin_link_edit`）。
+    pending: Option<GraphSnapshot>,
+    /// 正在进行的画布文字编辑（见 `track_text_edit`）。
+    text_edit: Option<TextEditSession>,
+}
+
+impl UndoHistory {
+    pub(super) fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub(super) fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+}
+
+impl NodeGraphApp {
+    fn snapshot(&self) -> GraphSnapshot {
+        GraphSnapshot {
+            nodes: self.nodes.clone(),
+            connections: self.connections.clone(),
+            next_node_id: self.next_node_id,
+            frames: self.frames.clone(),
+            next_frame_id: self.next_frame_id,
+        }
+    }
+
+    fn restore(&mut self, snapshot: GraphSnapshot) {
+        self.nodes = snapshot.nodes;
+        self.connections = snapshot.connections;
+        self.next_node_id = snapshot.next_node_id;
+        self.frames = snapshot.frames;
+        self.next_frame_id = snapshot.next_frame_id;
+
+        // 快照里可能没有这些节点了，清掉引用它们的临时状态。
+        self.selected_nodes
+            .retain(|id| self.nodes.iter().any(|node| node.id == *id));
+        self.dragging_link = None;
+        self.node_drag = None;
+        self.renaming_node = None;
+        self.editing_link_label = None;
+        self.selected_link = None;
+        self.inspector_edit = None;
+        self.renaming_frame = None;
+        self.frame_drag = None;
+        self.undo_history.pending = None;
+        self.undo_history.text_edit = None;
+        // 当前所在的子图可能被撤销掉了，回到顶层。
+        if self
+            .current_subgraph
+            .is_some_and(|id| self.node_by_id(id).is_none())
+        {
+            self.current_subgraph = None;
+        }
+    }
+
+    /// 在执行可撤销操作之前调用：记录当前状态，并清空重做栈。
+    pub(super) fn record_undo(&mut self) {
+        let snapshot = self.snapshot();
+        self.push_undo(snapshot);
+    }
+
+    fn push_undo(&mut self, snapshot: GraphSnapshot) {
+        let history = &mut self.undo_history;
+        history.undo.push(snapshot);
+        if history.undo.len() > MAX_UNDO_STEPS {
+            history.undo.remove(0);
+        }
+        history.redo.clear();
+        self.mark_document_modified();
+    }
+
+    /// 开始拖线（新建、改接或复制连线）之前调用：先存一份快照，还不算一步撤销。
+    pub(super) fn begin_link_edit(&mut self) {
+        self.undo_history.pending = Some(self.snapshot());
+    }
+
+    /// 拖线被打断（切换文档、换层、起点节点被删）时调用：丢掉开始时的快照，
+    /// 免得之后别的 `finish_link_edit` 把它当成自己的撤销步骤。
+    pub(super) fn cancel_link_edit(&mut self) {
+        self.dragging_link = None;
+        self.undo_history.pending = None;
+    }
+
+    /// 拖线结束后调用：连线和开始时不同才把快照记为一步撤销，
+    /// 点一下端口或拖到空处又放回原样都不会留下空的撤销步骤。
+    pub(super) fn finish_link_edit(&mut self) {
+        let Some(snapshot) = self.undo_history.pending.take() else {
+            return;
+        };
+        let changed = snapshot.connections.len() != self.connections.len()
+            || snapshot
+                .connections
+                .iter()
+                .zip(&self.connections)
+                .any(|(before, after)| before.key() != after.key());
+        if changed {
+            self.push_undo(snapshot);
+        }
+    }
+
+    /// 画布上的文字输入框每帧调用，`text` 是输入框本帧之后的文字：
+    /// 一改动就标记文档有未保存的修改，失去焦点时文字和开始时不同才记为一步撤销。
+    pub(super) fn track_text_edit(&mut self, response: &egui::Response, text: &str) {
+        self.track_text_events(response.id, TextEditEvents::from(response), text);
+    }
+
+    pub(super) fn track_text_events(
+        &mut self,
+        widget: egui::Id,
+        events: TextEditEvents,
+        text: &str,
+    ) {
+        if events.gained_focus {
+            self.undo_history.text_edit = Some(TextEditSession {
+                widget,
+                text: text.to_owned(),
+                before: self.snapshot(),
+            });
+        }
+        if events.changed {
+            self.mark_document_modified();
+        }
+        if events.lost_focus
+            && let Some(session) = self
+                .undo_history
+                .text_edit
+                .take_if(|session| session.widget == widget)
+            && session.text != text
+        {
+            self.push_undo(session.before);
+        }
+    }
+
+    pub(super) fn undo(&mut self) {
+        if let Some(snapshot) = self.undo_history.undo.pop() {
+            let current = self.snapshot();
+            self.undo_history.redo.push(current);
+            self.restore(snapshot);
+            self.mark_document_modified();
+        }
+    }
+
+    pub(super) fn redo(&mut self) {
+        if let Some(snapshot) = self.undo_history.redo.pop() {
+            let current = self.snapshot();
+            self.undo_history.undo.push(current);
+            self.restore(snapshot);
+            self.mark_document_modified();
+        }
+    }
+
+    /// Ctrl+Z 撤销，Ctrl+Shift+Z / Ctrl+Y 重做。
+    ///
+    /// 正在编辑文字时不处理，留给输入框自己的撤销。
+    pub(super) fn handle_undo_shortcuts(&mut self, ctx: &egui::Context) {
+        if ctx.wants_keyboard_input() {
+            return;
+        }
+
+        // 先匹配带 Shift 的重做，否则 Ctrl+Z 会把它吃掉。
+        let (redo, undo) = ctx.input_mut(|i| {
+            let redo =
+                i.consume_shortcut(&REDO_SHORTCUT) || i.consume_key(Modifiers::COMMAND, Key::Y);
+            (redo, i.consume_shortcut(&UNDO_SHORTCUT))
+        });
+        if redo {
+            self.redo();
+        } else if undo {
+            self.undo();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TextEditEvents;
+    use crate::app::NodeGraphApp;
+
+    /// 模拟一次画布上的标题编辑：获得焦点、改成 `title`、失去焦点。
+    fn edit_title(app: &mut NodeGraphApp, node_id: usize, title: &str) {
+        let widget = egui::Id::new(("title", node_id));
+        let before = app.node_title(node_id).unwrap_or_default().to_owned();
+        let focus = TextEditEvents {
+            gained_focus: true,
+            ..TextEditEvents::default()
+        };
+        app.track_text_events(widget, focus, &before);
+        if let Some(node) = app.node_by_id_mut(node_id) {
+            title.clone_into(&mut node.title);
+        }
+        let typed = TextEditEvents {
+            changed: true,
+            ..TextEditEvents::default()
+        };
+        app.track_text_events(widget, typed, title);
+        let blur = TextEditEvents {
+            lost_focus: true,
+            ..TextEditEvents::default()
+        };
+        app.track_text_events(widget, blur, title);
+    }
+
+    #[test]
+    fn canvas_text_edit_is_one_undo_step() {
+        let mut app = NodeGraphApp::empty();
+        let node = app.add_graph_node("before");
+        edit_title(&mut app, node, "after");
+        assert!(
+            app.documents
+                .first()
+                .is_some_and(|document| document.modified),
+            "文字编辑应该标记文档有未保存的修改"
+        );
+        assert!(app.undo_history.can_undo(), "文字编辑应该记为一步撤销");
+        app.undo();
+        assert_eq!(app.node_title(node), Some("before"), "撤销后恢复原来的标题");
+        assert!(!app.undo_history.can_undo(), "整次编辑只记一步");
+    }
+
+    #[test]
+    fn unchanged_text_edit_leaves_no_undo_step() {
+        let mut app = NodeGraphApp::empty();
+        let node = app.add_graph_node("same");
+        edit_title(&mut app, node, "same");
+        assert!(!app.undo_history.can_undo(), "文字没变时不应留下撤销步骤");
+    }
+}
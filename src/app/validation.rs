@@ -0,0 +1,557 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::hash::{DefaultHasher, Hash as _, Hasher as _};
+
+use egui::{Align2, Color32, CornerRadius, FontId, Stroke, StrokeKind};
+
+use super::context_menu::ContextAction;
+use super::{LinkKey, NodeGraphApp, NodeKind, PortKind, Theme};
+
+// ============================================================
+// 图校验
+//
+// 每条规则实现 `ValidationRule`，检查整张图并报告问题。内置规则由 `built_in_rules`
+// 给出；宿主程序可以实现这个 trait，再用 `register_validation_rule` 加上自己的规则。
+// 图的结构（节点、标题、层级、连线）变化后自动重新校验：
+// - 侧边栏 `Issues` 页按严重程度列出问题，点击选中相关节点，可以一键修复的问题带修复按钮
+// - 画布上给有问题的节点加描边和角标，给有问题的连线垫一层颜色
+// 每条规则都可以在 `Issues` 页单独关闭，关闭的规则随应用状态保存。
+// ============================================================
+
+// 节点右上角问题角标的半径。
+const BADGE_RADIUS: f32 = 7.0;
+
+/// 问题的严重程度。
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    fn color(self, theme: &Theme) -> Color32 {
+        match self {
+            Self::Warning => theme.warning,
+            Self::Error => theme.error,
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Warning => "⚠",
+            Self::Error => "⛔",
+        }
+    }
+}
+
+/// 可以一键修复的问题的修复方式。
+#[derive(Clone, Debug)]
+pub enum QuickFix {
+    /// 删除连线（例如端点已不存在的连线）。
+    DeleteLink(LinkKey),
+    /// 删除节点（例如没有输入的转接点）。
+    DeleteNode(usize),
+    /// 给除第一个以外的重名节点加上编号后缀。
+    RenameDuplicates(Vec<usize>),
+    /// 所在子图已不存在的节点移回顶层。
+    MoveToTopLevel(usize),
+}
+
+impl QuickFix {
+    fn label(&self) -> &'static str {
+        match self {
+            Self::DeleteLink(_) => "Delete link",
+            Self::DeleteNode(_) => "Delete node",
+            Self::RenameDuplicates(_) => "Rename",
+            Self::MoveToTopLevel(_) => "Move to top level",
+        }
+    }
+}
+
+/// 校验发现的一个问题。
+#[derive(Clone, Debug)]
+pub struct Issue {
+    severity: Severity,
+    message: String,
+    /// 相关的节点，点击问题时选中它们，画布上给它们加描边。
+    nodes: Vec<usize>,
+    /// 相关的连线，画布上给它们垫一层颜色。
+    links: Vec<LinkKey>,
+    fix: Option<QuickFix>,
+}
+
+impl Issue {
+    /// 一个还没有关联节点、连线和修复方式的问题。
+    pub fn new(severity: Severity, message: String) -> Self {
+        Self {
+            severity,
+            message,
+            nodes: Vec::new(),
+            links: Vec::new(),
+            fix: None,
+        }
+    }
+
+    /// 关联的节点。
+    pub fn with_nodes(mut self, nodes: Vec<usize>) -> Self {
+        self.nodes = nodes;
+        self
+    }
+
+    /// 关联的连线（起点节点 ID，终点节点 ID）。
+    pub fn with_links(mut self, links: Vec<LinkKey>) -> Self {
+        self.links = links;
+        self
+    }
+
+    /// 一键修复的方式。
+    pub fn with_fix(mut self, fix: QuickFix) -> Self {
+        self.fix = Some(fix);
+        self
+    }
+}
+
+/// 一条校验规则。
+pub trait ValidationRule {
+    /// 保存“已关闭的规则”时使用的标识，不要修改，也不要和其他规则重复。
+    fn id(&self) -> &'static str;
+    /// `Issues` 页里显示的名称。
+    fn label(&self) -> &'static str;
+    /// 检查整张图，把发现的问题追加到 `issues`。
+    fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>);
+}
+
+/// 连线端点或节点所在的子图已不存在。
+struct DanglingReferences;
+
+impl ValidationRule for DanglingReferences {
+    fn id(&self) -> &'static str {
+        "dangling-references"
+    }
+
+    fn label(&self) -> &'static str {
+        "Dangling references"
+    }
+
+    fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>) {
+        for connection in &app.connections {
+            let missing: Vec<usize> = [connection.from_node_id, connection.to_node_id]
+                .into_iter()
+                .filter(|&id| app.node_by_id(id).is_none())
+                .collect();
+            if missing.is_empty() {
+                continue;
+            }
+            let ids: Vec<String> = missing.iter().map(|id| format!("#{id}")).collect();
+            let existing: Vec<usize> = [connection.from_node_id, connection.to_node_id]
+                .into_iter()
+                .filter(|id| !missing.contains(id))
+                .collect();
+            issues.push(
+                Issue::new(
+                    Severity::Error,
+                    format!("Link points to missing node {}", ids.join(", ")),
+                )
+                .with_nodes(existing)
+                .with_links(vec![connection.key()])
+                .with_fix(QuickFix::DeleteLink(connection.key())),
+            );
+        }
+
+        for node in &app.nodes {
+            let Some(parent) = node.parent else {
+                continue;
+            };
+            if app
+                .node_by_id(parent)
+                .is_none_or(|parent| parent.kind != NodeKind::Subgraph)
+            {
+                issues.push(
+                    Issue::new(
+                        Severity::Error,
+                        format!("{} belongs to missing subgraph #{parent}", node.title),
+                    )
+                    .with_nodes(vec![node.id])
+                    .with_fix(QuickFix::MoveToTopLevel(node.id)),
+                );
+            }
+        }
+    }
+}
+
+/// 有输入端口的节点没有任何输入连线。
+struct UnconnectedInputs;
+
+impl ValidationRule for UnconnectedInputs {
+    fn id(&self) -> &'static str {
+        "unconnected-inputs"
+    }
+
+    fn label(&self) -> &'static str {
+        "Unconnected inputs"
+    }
+
+    fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>) {
+        for node in &app.nodes {
+            if !node.kind.has_port(PortKind::Input)
+                || app
+                    .connections
+                    .iter()
+                    .any(|connection| connection.to_node_id == node.id)
+            {
+                continue;
+            }
+            let issue = Issue::new(
+                Severity::Warning,
+                format!("{} has no input connected", node.title),
+            )
+            .with_nodes(vec![node.id]);
+            // 没有输入的转接点没有用处，可以直接删掉。
+            issues.push(if node.kind == NodeKind::Reroute {
+                issue.with_fix(QuickFix::DeleteNode(node.id))
+            } else {
+                issue
+            });
+        }
+    }
+}
+
+/// 多个节点标题相同（不区分大小写，忽略首尾空白）。
+struct DuplicateTitles;
+
+impl ValidationRule for DuplicateTitles {
+    fn id(&self) -> &'static str {
+        "duplicate-titles"
+    }
+
+    fn label(&self) -> &'static str {
+        "Duplicate titles"
+    }
+
+    fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>) {
+        let mut by_title: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for node in app
+            .nodes
+            .iter()
+            .filter(|node| node.kind != NodeKind::Reroute)
+        {
+            by_title
+                .entry(node.title.trim().to_lowercase())
+                .or_default()
+                .push(node.id);
+        }
+        for ids in by_title.into_values().filter(|ids| ids.len() > 1) {
+            let title = ids.first().map(|&id| app.node_name(id)).unwrap_or_default();
+            issues.push(
+                Issue::new(
+                    Severity::Warning,
+                    format!("{} nodes are titled \"{title}\"", ids.len()),
+                )
+                .with_nodes(ids.clone())
+                .with_fix(QuickFix::RenameDuplicates(ids)),
+            );
+        }
+    }
+}
+
+/// 沿连线方向能回到自身的节点组（强连通分量）。
+struct Cycles;
+
+impl ValidationRule for Cycles {
+    fn id(&self) -> &'static str {
+        "cycles"
+    }
+
+    fn label(&self) -> &'static str {
+        "Cycles"
+    }
+
+    fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>) {
+        let ids: BTreeSet<usize> = app.nodes.iter().map(|node| node.id).collect();
+        for cycle in app.strongly_connected_components(&ids) {
+            let names: Vec<String> = cycle.iter().map(|&id| app.node_name(id)).collect();
+            let links = app
+                .connections
+                .iter()
+                .map(|connection| connection.key())
+                .filter(|(from, to)| cycle.contains(from) && cycle.contains(to))
+                .collect();
+            issues.push(
+                Issue::new(
+                    Severity::Error,
+                    format!("Cycle through {}", names.join(", ")),
+                )
+                .with_nodes(cycle)
+                .with_links(links),
+            );
+        }
+    }
+}
+
+/// 内置的校验规则，按 `Issues` 页里的显示顺序排列。
+pub(super) fn built_in_rules() -> Vec<Box<dyn ValidationRule>> {
+    vec![
+        Box::new(DanglingReferences),
+        Box::new(UnconnectedInputs),
+        Box::new(DuplicateTitles),
+        Box::new(Cycles),
+    ]
+}
+
+/// 最近一次校验的结果。
+#[derive(Debug, Default)]
+pub(super) struct Validation {
+    /// 校验时图结构的指纹；和当前不同说明需要重新校验。
+    fingerprint: Option<u64>,
+    /// 按严重程度从高到低排序。
+    issues: Vec<Issue>,
+}
+
+impl NodeGraphApp {
+    /// 影响校验结果的那部分图数据的指纹（不含位置、尺寸等）。
+    fn validation_fingerprint(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        for node in &self.nodes {
+            (node.id, node.kind.label(), &node.title, node.parent).hash(&mut hasher);
+        }
+        for connection in &self.connections {
+            connection.key().hash(&mut hasher);
+        }
+        self.disabled_rules.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// 每帧绘制之前检查图结构有没有变化，有变化时重新运行全部启用的规则。
+    pub(super) fn update_validation(&mut self) {
+        let fingerprint = self.validation_fingerprint();
+        if self.validation.fingerprint == Some(fingerprint) {
+            return;
+        }
+        let mut issues = Vec::new();
+        for rule in &self.validation_rules {
+            if !self.disabled_rules.contains(rule.id()) {
+                rule.check(self, &mut issues);
+            }
+        }
+        issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+        self.validation = Validation {
+            fingerprint: Some(fingerprint),
+            issues,
+        };
+    }
+
+    /// 在内置规则之后加一条校验规则，下一帧起生效；规则可以和内置规则一样在 `Issues` 页关闭。
+    pub fn register_validation_rule(&mut self, rule: Box<dyn ValidationRule>) {
+        self.validation_rules.push(rule);
+        self.validation = Validation::default();
+    }
+
+    /// 执行一个快速修复。
+    pub(super) fn apply_quick_fix(&mut self, fix: QuickFix) {
+        match fix {
+            QuickFix::DeleteLink(link) => {
+                self.connections
+                    .retain(|connection| connection.key() != link);
+            }
+            QuickFix::DeleteNode(node_id) => self.remove_node(node_id),
+            QuickFix::RenameDuplicates(ids) => {
+                let mut taken: BTreeSet<String> = self
+                    .nodes
+                    .iter()
+                    .map(|node| node.title.trim().to_lowercase())
+                    .collect();
+                for &id in ids.iter().skip(1) {
+                    let Some(node) = self.node_by_id_mut(id) else {
+                        continue;
+                    };
+                    let base = node.title.trim().to_owned();
+                    let title = (2..)
+                        .map(|number| format!("{base} {number}"))
+                        .find(|title| !taken.contains(&title.to_lowercase()))
+                        .unwrap_or(base);
+                    taken.insert(title.to_lowercase());
+                    node.title = title;
+                }
+            }
+            QuickFix::MoveToTopLevel(node_id) => {
+                if let Some(node) = self.node_by_id_mut(node_id) {
+                    node.parent = None;
+                }
+            }
+        }
+    }
+
+    /// 节点在问题列表里出现时，返回其中最严重的程度。
+    fn node_severity(&self, node_id: usize) -> Option<Severity> {
+        self.validation
+            .issues
+            .iter()
+            .filter(|issue| {
+                issue
+                    .nodes
+                    .iter()
+                    .any(|&id| self.scope_representative(id) == Some(node_id))
+            })
+            .map(|issue| issue.severity)
+            .max()
+    }
+
+    /// 有问题的连线的标记色。
+    pub(super) fn issue_link_color(&self, link: LinkKey) -> Option<Color32> {
+        self.validation
+            .issues
+            .iter()
+            .filter(|issue| issue.links.contains(&link))
+            .map(|issue| issue.severity)
+            .max()
+            .map(|severity| severity.color(&self.theme))
+    }
+
+    /// 给有问题的节点（或包含它们的子图节点）画描边和右上角的角标。
+    pub(super) fn draw_issue_marks(&self, ui: &egui::Ui) {
+        if self.validation.issues.is_empty() {
+            return;
+        }
+        let painter = ui.painter();
+        for node in self.visible_nodes() {
+            let Some(severity) = self.node_severity(node.id) else {
+                continue;
+            };
+            let rect = self.node_rect_screen(node);
+            painter.rect_stroke(
+                rect.expand(2.0),
+                CornerRadius::same(9),
+                Stroke::new(1.5, severity.color(&self.theme)),
+                StrokeKind::Outside,
+            );
+            painter.circle_filled(rect.right_top(), BADGE_RADIUS, severity.color(&self.theme));
+            painter.text(
+                rect.right_top(),
+                Align2::CENTER_CENTER,
+                "!",
+                FontId::proportional(11.0),
+                Color32::BLACK,
+            );
+        }
+    }
+
+    /// 侧边栏“问题”页：规则开关和问题列表。
+    pub(super) fn issues_panel_contents(&mut self, ui: &mut egui::Ui) {
+        let issues = &self.validation.issues;
+        let errors = issues
+            .iter()
+            .filter(|issue| issue.severity == Severity::Error)
+            .count();
+        if issues.is_empty() {
+            ui.label("No issues");
+        } else {
+            ui.label(format!(
+                "{errors} errors, {} warnings",
+                issues.len() - errors
+            ));
+        }
+
+        let rules: Vec<(&'static str, &'static str)> = self
+            .validation_rules
+            .iter()
+            .map(|rule| (rule.id(), rule.label()))
+            .collect();
+        ui.collapsing("Rules", |ui| {
+            for (id, label) in rules {
+                let mut enabled = !self.disabled_rules.contains(id);
+                if ui.checkbox(&mut enabled, label).changed() {
+                    if enabled {
+                        self.disabled_rules.remove(id);
+                    } else {
+                        self.disabled_rules.insert(id.to_owned());
+                    }
+                }
+            }
+        });
+        ui.separator();
+
+        let mut action = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for issue in &self.validation.issues {
+                ui.horizontal_wrapped(|ui| {
+                    ui.colored_label(issue.severity.color(&self.theme), issue.severity.icon());
+                    let response = ui.link(&issue.message);
+                    if response.clicked() && !issue.nodes.is_empty() {
+                        action = Some(ContextAction::SelectNodes(issue.nodes.clone()));
+                    }
+                    if let Some(fix) = &issue.fix
+                        && ui.small_button(fix.label()).clicked()
+                    {
+                        action = Some(ContextAction::Fix(fix.clone()));
+                    }
+                });
+            }
+        });
+        if action.is_some() {
+            self.pending_action = action;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Issue, Severity, ValidationRule};
+    use crate::app::NodeGraphApp;
+
+    /// 标题为 “TODO” 的节点报一个警告。
+    struct TodoTitles;
+
+    impl ValidationRule for TodoTitles {
+        fn id(&self) -> &'static str {
+            "todo-titles"
+        }
+
+        fn label(&self) -> &'static str {
+            "TODO titles"
+        }
+
+        fn check(&self, app: &NodeGraphApp, issues: &mut Vec<Issue>) {
+            for id in app.node_ids() {
+                if app.node_title(id) == Some("TODO") {
+                    issues.push(
+                        Issue::new(Severity::Warning, "Unfinished node".to_owned())
+                            .with_nodes(vec![id]),
+                    );
+                }
+            }
+        }
+    }
+
+    fn messages(app: &NodeGraphApp) -> Vec<&str> {
+        app.validation
+            .issues
+            .iter()
+            .map(|issue| issue.message.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn registered_rule_runs_and_can_be_disabled() {
+        let mut app = NodeGraphApp::empty();
+        app.add_graph_node("TODO");
+        app.add_graph_node("Done");
+        app.update_validation();
+        assert!(
+            !messages(&app).contains(&"Unfinished node"),
+            "注册之前不应有这条规则的问题"
+        );
+
+        app.register_validation_rule(Box::new(TodoTitles));
+        app.update_validation();
+        assert!(
+            messages(&app).contains(&"Unfinished node"),
+            "注册的规则应该参与校验"
+        );
+
+        app.disabled_rules.insert("todo-titles".to_owned());
+        app.update_validation();
+        assert!(
+            !messages(&app).contains(&"Unfinished node"),
+            "关闭的规则不再报告问题"
+        );
+    }
+}
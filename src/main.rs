@@ -2,7 +2,7 @@
 // 在 Windows 的 release 模式下隐藏控制台窗口（避免弹黑框）
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use eframe_template::app::NodeGraphApp;
+use eframe_template::app::{APP_NAME, NodeGraphApp};
 
 // 程序入口：
 // `eframe::Result` 是 eframe 约定的返回类型，便于统一处理启动错误。
@@ -31,11 +31,11 @@ fn main() -> eframe::Result {
     // - `cc` 是 CreationContext（创建上下文），包含 egui 上下文和持久化存储等信息
     // - 返回我们自己的 App 状态对象 `TemplateApp`
     eframe::run_native(
-        "eframe template",
+        APP_NAME,
         native_options,
         Box::new(|cc| {
             configure_system_font(&cc.egui_ctx);
-            Ok(Box::new(NodeGraphApp::default()))
+            Ok(Box::new(NodeGraphApp::new(cc)))
         }),
     )
 }